@@ -3,8 +3,7 @@ var filesToCache = [
   './',
   './index.html',
   './libre_learning.js',
-  './libre_learning_bg.wasm', 
-  './ebisu.min.es6.js'
+  './libre_learning_bg.wasm'
 ];
 
 /* Start the service worker and cache all of the app's content */
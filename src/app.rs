@@ -13,9 +13,6 @@ use crate::app_controller::AppController;
 
 // error warning when auto fetching -> disable auto fetching
 
-// save app state and tag it, enable loading/switching saved app states, that way can use multiple different decks. (only meta data needs to be saved, saving card data is convinient)
-// possibly have random manager, to switch between the tags for learning.
-
 // then add telegram bot functionality.
 
 // Current language learning Apps function without adaption to the student i.e they use a pre planned lesson plan and then apply a spaced repetition algorithm to aid in the memorization process.
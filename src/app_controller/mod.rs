@@ -14,6 +14,7 @@ pub struct AppController {
 impl AppController {
     pub fn restore(&mut self) {
         let view_data: ViewModel = self.view_controller.view_model_controller.get_data();
+        view_data.restore_default_properties();
         self.model_controller.restore(&view_data);
     }
 
@@ -0,0 +1,188 @@
+// Cloze deletions in the style of Anki: "Terima {{c1::kasih}}, selamat {{c2::idul fitri::holiday}}."
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClozeSegment {
+    Text(String),
+    Cloze {
+        number: u8,
+        answer: String,
+        hint: Option<String>,
+    },
+}
+
+/// Splits `text` into plain text and cloze deletions, returns None if it contains no cloze.
+pub fn parse_cloze(text: &str) -> Option<Vec<ClozeSegment>> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{c") {
+        let after_marker = &rest[start + 3..];
+        let digits = after_marker
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after_marker.len());
+        let end = after_marker.find("}}");
+        let (number, end) = match (after_marker[..digits].parse::<u8>(), end) {
+            (Ok(number), Some(end)) if after_marker[digits..].starts_with("::") && end > digits => {
+                (number, end)
+            }
+            _ => {
+                // not a cloze, keep the braces as text.
+                segments.push(ClozeSegment::Text(rest[..start + 3].to_string()));
+                rest = after_marker;
+                continue;
+            }
+        };
+
+        if start > 0 {
+            segments.push(ClozeSegment::Text(rest[..start].to_string()));
+        }
+        let mut parts = after_marker[digits + 2..end].splitn(2, "::");
+        segments.push(ClozeSegment::Cloze {
+            number,
+            answer: parts.next().unwrap_or("").to_string(),
+            hint: parts.next().map(|hint| hint.to_string()),
+        });
+        rest = &after_marker[end + 2..];
+    }
+    if !rest.is_empty() {
+        segments.push(ClozeSegment::Text(rest.to_string()));
+    }
+
+    if segments
+        .iter()
+        .any(|segment| matches!(segment, ClozeSegment::Cloze { .. }))
+    {
+        Some(segments)
+    } else {
+        None
+    }
+}
+
+/// The distinct cloze numbers in order of first appearance, each becomes its own card.
+pub fn cloze_numbers(segments: &[ClozeSegment]) -> Vec<u8> {
+    let mut numbers = Vec::new();
+    for segment in segments {
+        if let ClozeSegment::Cloze { number, .. } = segment {
+            if !numbers.contains(number) {
+                numbers.push(*number);
+            }
+        }
+    }
+    numbers
+}
+
+/// Returns the sentence as alternating text and gap segments (even indices are text, odd indices are gaps),
+/// the other clozes are filled in. A gap segment holds the hint or is empty.
+pub fn cloze_context(segments: &[ClozeSegment], number: u8) -> Vec<String> {
+    let mut context = vec![String::new()];
+    for segment in segments {
+        match segment {
+            ClozeSegment::Text(text) => context.last_mut().unwrap().push_str(text),
+            ClozeSegment::Cloze {
+                number: n, answer, ..
+            } if *n != number => context.last_mut().unwrap().push_str(answer),
+            ClozeSegment::Cloze { hint, .. } => {
+                context.push(hint.to_owned().unwrap_or_default());
+                context.push(String::new());
+            }
+        }
+    }
+    context
+}
+
+/// The text the learner has to type, several gaps of the same cloze are separated by a space.
+pub fn cloze_answer(segments: &[ClozeSegment], number: u8) -> String {
+    segments
+        .iter()
+        .filter_map(|segment| match segment {
+            ClozeSegment::Cloze {
+                number: n, answer, ..
+            } if *n == number => Some(answer.to_owned()),
+            _ => None,
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Plain text rendering of the gaps, e.g. "Terima [...], selamat idul fitri."
+pub fn format_cloze_context(context: &[String]) -> String {
+    context
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| {
+            if i % 2 == 0 {
+                segment.to_owned()
+            } else if segment.is_empty() {
+                "[...]".to_string()
+            } else {
+                format!("[{}]", segment)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_without_cloze_is_none() {
+        assert_eq!(parse_cloze("Terima kasih"), None);
+        assert_eq!(parse_cloze("{{c1 kasih}}"), None);
+        assert_eq!(parse_cloze("{{cx::kasih}}"), None);
+    }
+
+    #[test]
+    fn parses_clozes_with_hints() {
+        assert_eq!(
+            parse_cloze("Terima {{c1::kasih}}, selamat {{c2::idul fitri::holiday}}."),
+            Some(vec![
+                ClozeSegment::Text("Terima ".to_string()),
+                ClozeSegment::Cloze {
+                    number: 1,
+                    answer: "kasih".to_string(),
+                    hint: None
+                },
+                ClozeSegment::Text(", selamat ".to_string()),
+                ClozeSegment::Cloze {
+                    number: 2,
+                    answer: "idul fitri".to_string(),
+                    hint: Some("holiday".to_string())
+                },
+                ClozeSegment::Text(".".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn invalid_markers_stay_text() {
+        let segments = parse_cloze("{{cx}} and {{c1::kasih}}").unwrap();
+        assert_eq!(segments[0], ClozeSegment::Text("{{c".to_string()));
+        assert_eq!(cloze_context(&segments, 1), vec!["{{cx}} and ", "", ""]);
+    }
+
+    #[test]
+    fn every_cloze_number_is_one_card() {
+        let segments = parse_cloze("{{c2::a}} {{c1::b}} {{c2::c}}").unwrap();
+        assert_eq!(cloze_numbers(&segments), vec![2, 1]);
+    }
+
+    #[test]
+    fn other_clozes_are_filled_in() {
+        let segments = parse_cloze("Terima {{c1::kasih}}, selamat {{c2::idul fitri::holiday}}.").unwrap();
+        let context = cloze_context(&segments, 2);
+        assert_eq!(context, vec!["Terima kasih, selamat ", "holiday", "."]);
+        assert_eq!(format_cloze_context(&context), "Terima kasih, selamat [holiday].");
+        assert_eq!(
+            format_cloze_context(&cloze_context(&segments, 1)),
+            "Terima [...], selamat idul fitri."
+        );
+    }
+
+    #[test]
+    fn gaps_of_the_same_cloze_are_answered_together() {
+        let segments = parse_cloze("{{c1::satu}} dan {{c1::dua}} {{c2::tiga}}").unwrap();
+        assert_eq!(cloze_answer(&segments, 1), "satu dua");
+        assert_eq!(cloze_answer(&segments, 2), "tiga");
+    }
+}
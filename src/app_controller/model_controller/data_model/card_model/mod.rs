@@ -1,6 +1,10 @@
 use super::download::audio::AudioItem;
+use super::download::DownloadState;
 use super::download::image::ImageItem;
+use super::learning_steps::LearningStep;
+use super::normalization::MatchOverrides;
 use super::request_model::RequestConfig;
+use cloze::{cloze_answer, cloze_context, cloze_numbers, format_cloze_context, parse_cloze};
 use any_ascii::any_ascii;
 use std::sync::Arc;
 use std::sync::RwLock;
@@ -8,15 +12,31 @@ use std::sync::RwLock;
 use egui_extras::RetainedImage;
 use std::sync::Mutex;
 
+pub mod cloze;
+
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
 pub struct CardDisplayData { 
 
     pub question_text: String,
     pub context_text: String,
     pub label_text: String,
+    /// Answers accepted besides label_text.
+    #[serde(default)]
+    pub alternative_labels: Vec<String>,
     pub placeholder_text: String,
     pub audio_item: Option<AudioItem>,
     pub image_item: Option<Vec<ImageItem>>,
+    /// Set for cloze cards, see cloze::cloze_context.
+    #[serde(default)]
+    pub cloze_context: Option<Vec<String>>,
+    #[serde(default)]
+    pub kind: CardKind,
+    /// Options of a multiple-choice card, if None they are taken from the labels of other cards.
+    #[serde(default)]
+    pub choices: Option<Vec<String>>,
+    /// Answer matching of the card file, see MatchOverrides.
+    #[serde(default)]
+    pub match_overrides: Option<MatchOverrides>,
 
     #[serde(skip)]
     pub image_index: usize,
@@ -32,6 +52,23 @@ impl CardDisplayData {
         // Terima kasih, selamat idul fitri. // Where are you from? // What is your name?
         self.context_text.to_owned()
     }
+    /// Alternating text and gap segments for cloze cards.
+    pub fn get_cloze_context(&self) -> Option<Vec<String>> {
+        self.cloze_context.to_owned()
+    }
+    /// The label followed by its alternatives.
+    pub fn get_accepted_labels(&self) -> Vec<String> {
+        let mut labels = vec![self.get_label()];
+        labels.extend(self.alternative_labels.iter().cloned());
+        labels
+    }
+    pub fn is_multiple_choice(&self) -> bool {
+        self.kind == CardKind::MultipleChoice
+    }
+    /// Dictation cards hide the context and only play its audio.
+    pub fn is_dictation(&self) -> bool {
+        self.kind == CardKind::Dictation
+    }
     pub fn get_label(
         &self, /*
                ignore_sentence_punctuation_symbols: bool,
@@ -110,13 +147,225 @@ impl CardDisplayData {
     pub fn has_audio(&self) -> bool {
         self.audio_item.is_some()
     }
+
+    /// Reads the audio and images from the media store instead of downloading them, `media_key` gives the key of a
+    /// stored file, see deck::Deck::media_key.
+    pub fn load_local_media<F>(&mut self, media_key: F)
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let download_items = self
+            .audio_item
+            .iter_mut()
+            .map(|audio_item| &mut audio_item.download_item)
+            .chain(
+                self.image_item
+                    .iter_mut()
+                    .flatten()
+                    .map(|image_item| &mut image_item.download_item),
+            );
+        for download_item in download_items {
+            if download_item.get_download_state() == DownloadState::None {
+                if let Some(key) = media_key(&download_item.url) {
+                    download_item.load_stored(&key);
+                }
+            }
+        }
+    }
+}
+
+/// How the answer is given.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CardKind {
+    /// The answer is typed.
+    Translation,
+    /// The answer is picked from a few options.
+    MultipleChoice,
+    /// The context is only played as audio and typed by the learner.
+    Dictation,
+}
+
+impl Default for CardKind {
+    fn default() -> Self {
+        CardKind::Translation
+    }
+}
+
+/// Forward cards ask for the label given the context, reverse cards for the context given the label.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum CardDirection {
+    Forward,
+    Reverse,
+}
+
+/// The cards generated from one card file, each has its own review history.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum CardVariant {
+    Forward,
+    Reverse,
+    /// Asks for the n-th cloze deletion of the context.
+    Cloze(u8),
+    /// Asks to type the context after listening to its audio.
+    Dictation,
+}
+
+impl Default for CardVariant {
+    fn default() -> Self {
+        CardVariant::Forward
+    }
 }
 
+impl From<CardDirection> for CardVariant {
+    fn from(direction: CardDirection) -> Self {
+        match direction {
+            CardDirection::Forward => CardVariant::Forward,
+            CardDirection::Reverse => CardVariant::Reverse,
+        }
+    }
+}
+
+/// "{deck id}/{card id}", unique across decks, see Card::parse.
+pub type CardId = String;
+
+/// Identifies a card, all variants of a card file share the id.
+pub type CardKey = (CardId, CardVariant);
+
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
 pub struct CardMetaData {
-    pub id: u16, // max value is 65553, switch to u32 if to small. (let me know if you got that many cards)
+    /// Used to be a number unique within a single deck, see AppData::migrate_card_ids.
+    #[serde(deserialize_with = "deserialize_card_id")]
+    pub id: CardId,
+    #[serde(default, alias = "direction")]
+    pub variant: CardVariant,
+    /// Key of the deck the card belongs to, see deck::Deck::key.
+    #[serde(default)]
+    pub deck: u32,
     pub timestamps: Vec<f64>,
-    pub scores: Vec<bool>,
+    /// Recall quality of each review, from 0.0 (forgotten) to 1.0 (perfect), see spaced_repetition::Grade.
+    #[serde(deserialize_with = "deserialize_scores")]
+    pub scores: Vec<f64>,
+    /// None once the card graduated into the long-term scheduler (or if it predates learning steps).
+    #[serde(default)]
+    pub learning_step: Option<LearningStep>,
+    /// Number of times the card was forgotten after it graduated.
+    #[serde(default)]
+    pub lapses: usize,
+    #[serde(default)]
+    pub leech: bool,
+    #[serde(default)]
+    pub suspended: bool,
+    #[serde(default)]
+    pub buried_until: Option<f64>,
+    /// Number of hints used in each review, reviews made before hints existed count as 0.
+    #[serde(default)]
+    pub hints: Vec<usize>,
+    /// Given by the author of the card file, see CardFile::tags.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl CardMetaData {
+    pub fn key(&self) -> CardKey {
+        (self.id.to_owned(), self.variant)
+    }
+
+    /// Suspended and buried cards are left out of the review queue.
+    pub fn is_hidden(&self, now: f64) -> bool {
+        self.suspended || self.buried_until.map(|t| t > now).unwrap_or(false)
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StoredScore {
+    Bool(bool),
+    Float(f64),
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StoredCardId {
+    Number(u64),
+    Text(String),
+}
+
+impl StoredCardId {
+    fn into_string(self) -> String {
+        match self {
+            StoredCardId::Number(id) => id.to_string(),
+            StoredCardId::Text(id) => id,
+        }
+    }
+}
+
+/// Card ids may be written as numbers (as they used to be) or as strings.
+fn deserialize_card_id<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let id: StoredCardId = serde::Deserialize::deserialize(deserializer)?;
+    Ok(id.into_string())
+}
+
+fn deserialize_optional_card_id<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let id: Option<StoredCardId> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(id.map(StoredCardId::into_string))
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Labels {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// `label_text` is either a single answer or a list of accepted answers, the first one is shown as the solution.
+fn deserialize_labels<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let labels: Labels = serde::Deserialize::deserialize(deserializer)?;
+    Ok(match labels {
+        Labels::One(label) => vec![label],
+        Labels::Many(labels) => labels,
+    })
+}
+
+/// Scores used to be stored as booleans, these are read as 0.0 (wrong) and 1.0 (correct).
+fn deserialize_scores<'de, D>(deserializer: D) -> Result<Vec<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let scores: Vec<StoredScore> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(scores
+        .into_iter()
+        .map(|score| match score {
+            StoredScore::Bool(true) => 1.0,
+            StoredScore::Bool(false) => 0.0,
+            StoredScore::Float(score) => score,
+        })
+        .collect())
+}
+
+/// FNV-1a hash of the texts of a card file without id, stable across builds (unlike the std hasher).
+fn content_hash(v: &CardFile) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let texts = [&v.question_text, &v.context_text]
+        .into_iter()
+        .chain(v.label_text.iter());
+    for text in texts {
+        for byte in text.bytes().chain(std::iter::once(0x1f)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}", hash)
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
@@ -125,41 +374,293 @@ pub struct Card {
     pub meta_data: CardMetaData,
 }
 
+/// A card file as served by the file server, see file_server/static.
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
-struct CardFile {
-    question_text: String,
-    context_text: String,
-    label_text: String,
-    placeholder_text: String,
-    audio_item: Option<String>,
-    image_item: Option<Vec<String>>,
-    id: u16,
+pub struct CardFile {
+    pub question_text: String,
+    /// May contain cloze deletions such as {{c1::kasih}}, each cloze number becomes its own card.
+    pub context_text: String,
+    #[serde(default, deserialize_with = "deserialize_labels")]
+    pub label_text: Vec<String>,
+    pub placeholder_text: String,
+    pub audio_item: Option<String>,
+    pub image_item: Option<Vec<String>>,
+    /// Unique within the deck, if missing the card is identified by a hash of its texts.
+    #[serde(default, deserialize_with = "deserialize_optional_card_id")]
+    pub id: Option<String>,
+    /// e.g. ["forward", "reverse"], if missing only the forward card is created (unless reverse cards are enabled globally).
+    /// An empty list counts as ["forward"], a card file always makes at least one card.
+    pub directions: Option<Vec<CardDirection>>,
+    pub reverse_question_text: Option<String>,
+    pub reverse_placeholder_text: Option<String>,
+    #[serde(default)]
+    pub kind: CardKind,
+    /// Options of a multiple-choice card (the label is added if missing).
+    pub choices: Option<Vec<String>>,
+    #[serde(default, rename = "match")]
+    pub match_overrides: Option<MatchOverrides>,
+    /// e.g. ["food", "chapter-3"]
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl CardFile {
+    /// Only cloze card files (the clozes are the answers) and dictation card files with audio (the context is typed)
+    /// may leave out the label, other card files without label are rejected instead of asking for an empty answer.
+    fn has_answer(&self) -> bool {
+        self.label_text.iter().any(|label| !label.trim().is_empty())
+            || parse_cloze(&self.context_text).is_some()
+            || (self.kind == CardKind::Dictation && self.audio_item.is_some())
+    }
 }
 
 impl Card {
-    pub fn parse(json_str: &str) -> Option<Self> {
-        match serde_json::from_str::<CardFile>(json_str) {
-            Err(_) => None,
-            Ok(v) => Some(Self {
-                display_data: CardDisplayData { 
-                    question_text: v.question_text,
-                    context_text: v.context_text,
-                    label_text: v.label_text,
-                    placeholder_text: v.placeholder_text,
-                    audio_item: v.audio_item.map(|x| AudioItem::new(&x)),
-                    image_item: v.image_item.map(|x| {
-                        x.into_iter()
-                            .map(|y| ImageItem::new(&y))
-                            .collect::<Vec<ImageItem>>()
-                    }),
+    /// Returns one card per direction (or per cloze), `add_reverse_cards` adds the reverse card if the card file does not list its directions.
+    ///
+    /// `add_dictation_cards` adds a dictation card for every card file with audio.
+    ///
+    /// The cards are identified by "{deck_id}/{id of the card file}".
+    pub fn parse(
+        json_str: &str,
+        deck_id: &str,
+        add_reverse_cards: bool,
+        add_dictation_cards: bool,
+    ) -> Option<Vec<Self>> {
+        serde_json::from_str::<CardFile>(json_str)
+            .ok()
+            .filter(|v| v.has_answer())
+            .filter(|v| {
+                v.match_overrides
+                    .as_ref()
+                    .map_or(true, |match_overrides| match_overrides.error().is_none())
+            })
+            .map(|v| Card::from_card_file(&v, deck_id, add_reverse_cards, add_dictation_cards))
+    }
+
+    /// The cards of a card file, see Card::parse.
+    pub fn from_card_file(
+        v: &CardFile,
+        deck_id: &str,
+        add_reverse_cards: bool,
+        add_dictation_cards: bool,
+    ) -> Vec<Self> {
+        let id = format!(
+            "{}/{}",
+            deck_id,
+            v.id.to_owned().unwrap_or_else(|| content_hash(v))
+        );
+        let has_audio = v.audio_item.is_some();
+        let mut variants: Vec<CardVariant> = if v.kind == CardKind::Dictation && has_audio {
+            Vec::new()
+        } else if let Some(segments) = parse_cloze(&v.context_text) {
+            cloze_numbers(&segments)
+                .into_iter()
+                .map(CardVariant::Cloze)
+                .collect()
+        } else {
+            v.directions
+                .clone()
+                .map(|directions| {
+                    if directions.is_empty() {
+                        vec![CardDirection::Forward]
+                    } else {
+                        directions
+                    }
+                })
+                .unwrap_or_else(|| {
+                    if add_reverse_cards {
+                        vec![CardDirection::Forward, CardDirection::Reverse]
+                    } else {
+                        vec![CardDirection::Forward]
+                    }
+                })
+                .into_iter()
+                .map(CardVariant::from)
+                .collect()
+        };
+        if (v.kind == CardKind::Dictation || add_dictation_cards) && has_audio {
+            variants.push(CardVariant::Dictation);
+        }
+        variants
+            .into_iter()
+            .map(|variant| Card::new(v, &id, variant))
+            .collect()
+    }
+
+    fn new(v: &CardFile, id: &str, variant: CardVariant) -> Self {
+        let image_item = v.image_item.as_ref().map(|x| {
+            x.iter()
+                .map(|y| ImageItem::new(y))
+                .collect::<Vec<ImageItem>>()
+        });
+        // a dictation card file without audio falls back to a typed card.
+        let kind = match v.kind {
+            CardKind::Dictation => CardKind::Translation,
+            kind => kind,
+        };
+        let display_data = match variant {
+            CardVariant::Forward => CardDisplayData {
+                question_text: v.question_text.to_owned(),
+                context_text: v.context_text.to_owned(),
+                label_text: v.label_text.first().cloned().unwrap_or_default(),
+                alternative_labels: v.label_text.iter().skip(1).cloned().collect(),
+                placeholder_text: v.placeholder_text.to_owned(),
+                audio_item: v.audio_item.as_ref().map(|x| AudioItem::new(x)),
+                image_item,
+                cloze_context: None,
+                kind,
+                choices: v.choices.to_owned(),
+                match_overrides: v.match_overrides.to_owned(),
+                image_index: 0usize,
+            },
+            // the audio is a recording of the context, it would give the answer away.
+            CardVariant::Reverse => CardDisplayData {
+                question_text: v
+                    .reverse_question_text
+                    .to_owned()
+                    .unwrap_or_else(|| v.question_text.to_owned()),
+                context_text: v.label_text.first().cloned().unwrap_or_default(),
+                label_text: v.context_text.to_owned(),
+                alternative_labels: Vec::new(),
+                placeholder_text: v
+                    .reverse_placeholder_text
+                    .to_owned()
+                    .unwrap_or_else(|| "Type the translation".to_string()),
+                audio_item: None,
+                image_item,
+                cloze_context: None,
+                kind,
+                choices: None,
+                match_overrides: v.match_overrides.to_owned(),
+                image_index: 0usize,
+            },
+            CardVariant::Cloze(number) => {
+                let segments = parse_cloze(&v.context_text).unwrap_or_default();
+                let context = cloze_context(&segments, number);
+                // the audio reads the whole sentence, it would give the gap away.
+                CardDisplayData {
+                    question_text: v.question_text.to_owned(),
+                    context_text: format_cloze_context(&context),
+                    label_text: cloze_answer(&segments, number),
+                    alternative_labels: Vec::new(),
+                    placeholder_text: v.placeholder_text.to_owned(),
+                    audio_item: None,
+                    image_item,
+                    cloze_context: Some(context),
+                    kind,
+                    choices: None,
+                    match_overrides: v.match_overrides.to_owned(),
+                    image_index: 0usize,
+                }
+            }
+            // the learner types what they hear, so the label is the (hidden) context.
+            CardVariant::Dictation => {
+                let is_dictation_card_file = v.kind == CardKind::Dictation;
+                CardDisplayData {
+                    question_text: if is_dictation_card_file {
+                        v.question_text.to_owned()
+                    } else {
+                        "Listen and type what you hear".to_string()
+                    },
+                    context_text: v.context_text.to_owned(),
+                    label_text: v.context_text.to_owned(),
+                    alternative_labels: Vec::new(),
+                    placeholder_text: if is_dictation_card_file {
+                        v.placeholder_text.to_owned()
+                    } else {
+                        "Type what you hear".to_string()
+                    },
+                    audio_item: v.audio_item.as_ref().map(|x| AudioItem::new(x)),
+                    image_item,
+                    cloze_context: None,
+                    kind: CardKind::Dictation,
+                    choices: None,
+                    match_overrides: v.match_overrides.to_owned(),
                     image_index: 0usize,
-                },
-                meta_data: CardMetaData {
-                    id: v.id,
-                    timestamps: Vec::new(),
-                    scores: Vec::new(),
-                },
-            }),
+                }
+            }
+        };
+        Self {
+            display_data,
+            meta_data: CardMetaData {
+                id: id.to_owned(),
+                variant,
+                deck: 0,
+                timestamps: Vec::new(),
+                scores: Vec::new(),
+                learning_step: Some(LearningStep::Learning(0)),
+                lapses: 0,
+                leech: false,
+                suspended: false,
+                buried_until: None,
+                hints: Vec::new(),
+                tags: v.tags.to_owned(),
+            },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variants(cards: &[Card]) -> Vec<CardVariant> {
+        cards.iter().map(|card| card.meta_data.variant).collect()
+    }
+
+    #[test]
+    fn directions_choose_the_variants() {
+        let json = r#"{"question_text": "Translate", "context_text": "kucing", "label_text": "cat",
+            "placeholder_text": "", "id": 1, "directions": ["forward", "reverse"]}"#;
+        let cards = Card::parse(json, "deck", false, false).unwrap();
+        assert_eq!(variants(&cards), vec![CardVariant::Forward, CardVariant::Reverse]);
+        assert_eq!(cards[1].display_data.label_text, "kucing");
+        assert!(cards.iter().all(|card| card.meta_data.id == "deck/1"));
+    }
+
+    #[test]
+    fn without_directions_the_global_toggle_decides() {
+        let json = r#"{"question_text": "Translate", "context_text": "kucing", "label_text": "cat",
+            "placeholder_text": "", "id": 1}"#;
+        let forward_only = Card::parse(json, "deck", false, false).unwrap();
+        assert_eq!(variants(&forward_only), vec![CardVariant::Forward]);
+        let both = Card::parse(json, "deck", true, false).unwrap();
+        assert_eq!(variants(&both), vec![CardVariant::Forward, CardVariant::Reverse]);
+    }
+
+    #[test]
+    fn cloze_cards_have_no_audio() {
+        let json = r#"{"question_text": "Type the missing word", "context_text": "Terima {{c1::kasih}}",
+            "placeholder_text": "", "id": 1, "audio_item": "terima_kasih.mp3"}"#;
+        let cards = Card::parse(json, "deck", false, false).unwrap();
+        assert_eq!(variants(&cards), vec![CardVariant::Cloze(1)]);
+        assert!(!cards[0].display_data.has_audio());
+        assert_eq!(cards[0].display_data.label_text, "kasih");
+    }
+
+    #[test]
+    fn card_files_without_label_are_rejected() {
+        let missing = r#"{"question_text": "Translate", "context_text": "kucing", "placeholder_text": "", "id": 1}"#;
+        assert!(Card::parse(missing, "deck", false, false).is_none());
+        let empty = r#"{"question_text": "Translate", "context_text": "kucing", "label_text": " ",
+            "placeholder_text": "", "id": 1}"#;
+        assert!(Card::parse(empty, "deck", false, false).is_none());
+    }
+
+    #[test]
+    fn dictation_card_files_with_audio_need_no_label() {
+        let json = r#"{"question_text": "Type what you hear", "context_text": "Selamat pagi", "placeholder_text": "",
+            "id": 1, "kind": "dictation", "audio_item": "selamat_pagi.mp3"}"#;
+        let cards = Card::parse(json, "deck", false, false).unwrap();
+        assert_eq!(variants(&cards), vec![CardVariant::Dictation]);
+        assert_eq!(cards[0].display_data.label_text, "Selamat pagi");
+    }
+
+    #[test]
+    fn empty_directions_make_a_forward_card() {
+        let json = r#"{"question_text": "Translate", "context_text": "kucing", "label_text": "cat",
+            "placeholder_text": "", "id": 1, "directions": []}"#;
+        let cards = Card::parse(json, "deck", true, false).unwrap();
+        assert_eq!(variants(&cards), vec![CardVariant::Forward]);
+    }
+}
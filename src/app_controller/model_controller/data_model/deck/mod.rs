@@ -0,0 +1,556 @@
+// Manifest and settings of the deck author, read from the optional "deck.json" next to the card files, e.g.
+// {"id": "bahasa-indonesia", "title": "Bahasa Indonesia", "languages": ["id", "en"], "version": "1.2",
+//  "cards": ["greetings.json", "food.json"], "normalization": ["nfc", "fold_accents", "lowercase"], "match": {"typo_tolerance": 10}}
+//
+// Without a list of card files, cards are discovered by probing card_0.json, card_1.json, ...
+//
+// Every Deck has its own server, manifest, synonyms and daily stats, the cards of all selected decks are studied together.
+
+use super::card_model::Card;
+use super::download::card::CardItem;
+use super::download::deck::DeckItem;
+use super::download::media_store::media_key;
+use super::download::synonyms::SynonymsItem;
+use super::download::DownloadState;
+use super::normalization::MatchOverrides;
+use super::normalization::NormalizationPipeline;
+use super::normalization::NormalizationStep;
+use super::request_model::RequestConfig;
+use super::review_queue::DailyStats;
+use super::synonyms::Synonyms;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Clone)]
+pub struct DeckMetadata {
+    /// Identifies the deck in card ids, defaults to the endpoint, see Deck::id.
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Languages of the deck, e.g. ["id", "en"] for Indonesian cards with English translations.
+    #[serde(default)]
+    pub languages: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_version")]
+    pub version: Option<String>,
+    /// Card files relative to the endpoint.
+    #[serde(default)]
+    pub cards: Option<Vec<String>>,
+    /// Number of card files, enough to discover card_0.json to card_{card_count - 1}.json without listing them.
+    #[serde(default)]
+    pub card_count: Option<usize>,
+    /// Recommended order to introduce the card files in, files missing here come afterwards.
+    #[serde(default)]
+    pub introduction_order: Option<Vec<String>>,
+    /// Replaces the answer normalisation of the user for this deck.
+    #[serde(default)]
+    pub normalization: Option<Vec<NormalizationStep>>,
+    /// Answer matching for every card of the deck, card files may override it in turn.
+    #[serde(default, rename = "match")]
+    pub match_overrides: Option<MatchOverrides>,
+    /// Characters that are hard to type, e.g. ["ñ", "á", "¿"], see AppData::special_characters.
+    #[serde(default)]
+    pub special_characters: Option<Vec<String>>,
+    /// Shows the answer and lets the user grade themselves instead of typing, replaces the option of the user.
+    #[serde(default)]
+    pub self_graded: Option<bool>,
+}
+
+impl DeckMetadata {
+    /// A deck.json with an invalid normalisation pattern is rejected like a malformed one.
+    pub fn parse(json_str: &str) -> Option<DeckMetadata> {
+        serde_json::from_str::<DeckMetadata>(json_str)
+            .ok()
+            .filter(|metadata| {
+                metadata
+                    .normalization_pipeline()
+                    .map_or(true, |pipeline| pipeline.compile().errors.is_empty())
+                    && metadata
+                        .match_overrides
+                        .as_ref()
+                        .map_or(true, |match_overrides| match_overrides.error().is_none())
+            })
+    }
+
+    /// All card files of the deck in the order they should be introduced, None if the deck has no manifest.
+    pub fn card_files(&self) -> Option<Vec<String>> {
+        let listed_files = match (&self.cards, self.card_count) {
+            (Some(cards), _) => cards.to_owned(),
+            (None, Some(card_count)) => (0..card_count).map(|i| format!("card_{}.json", i)).collect(),
+            (None, None) => return None,
+        };
+        let mut card_files: Vec<String> = Vec::new();
+        for card_file in self
+            .introduction_order
+            .iter()
+            .flatten()
+            .chain(listed_files.iter())
+        {
+            if !card_files.contains(card_file) {
+                card_files.push(card_file.to_owned());
+            }
+        }
+        Some(card_files)
+    }
+
+    pub fn normalization_pipeline(&self) -> Option<NormalizationPipeline> {
+        self.normalization
+            .as_ref()
+            .map(|steps| NormalizationPipeline {
+                steps: steps.to_owned(),
+            })
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, derivative::Derivative)]
+#[derivative(Debug)]
+pub struct Deck {
+    /// Identifies the deck in CardMetaData::deck, unlike its position it does not change when other decks are removed.
+    pub key: u32,
+    /// Given by the user, see Deck::display_name.
+    #[serde(default)]
+    pub name: String,
+    /// Only the cards of selected decks are studied.
+    #[serde(default)]
+    pub selected: bool,
+    pub request_config: Arc<RwLock<RequestConfig>>,
+    #[serde(default)]
+    pub metadata: DeckMetadata,
+    /// The id the cards of the deck were added with, see Deck::resolve_id.
+    #[serde(default)]
+    pub resolved_id: Option<String>,
+    #[serde(default)]
+    pub synonyms: Synonyms,
+    /// Card files that were added, see DeckMetadata::card_files.
+    #[serde(default)]
+    pub added_card_files: Vec<String>,
+    /// Card files that can not be read, they are skipped until the manifest is downloaded again.
+    #[serde(default)]
+    pub failed_card_files: Vec<String>,
+    #[serde(default)]
+    pub card_download: Option<CardItem>,
+    #[serde(default)]
+    pub daily_stats: DailyStats,
+    /// Limits of the deck on top of the limits for all decks, None if it has none of its own.
+    #[serde(default)]
+    pub new_cards_per_day: Option<usize>,
+    #[serde(default)]
+    pub maximum_reviews_per_day: Option<usize>,
+    /// Imported cards without review history, they are added like new cards within the daily limit.
+    #[serde(default)]
+    #[derivative(Debug = "ignore")]
+    pub pending_cards: Vec<Card>,
+    /// Names of the media files of a deck imported from a local file, they are kept in the media store.
+    #[serde(default)]
+    pub media_files: Vec<String>,
+    /// Media files saved with the deck (base64 by file name) before the media store, see AppData::migrate_media.
+    #[serde(default, skip_serializing, rename = "media")]
+    #[derivative(Debug = "ignore")]
+    pub legacy_media: HashMap<String, String>,
+
+    #[serde(skip)]
+    synonyms_download: Option<SynonymsItem>,
+
+    #[serde(skip)]
+    deck_download: Option<DeckItem>,
+}
+
+impl Deck {
+    pub fn new(key: u32) -> Self {
+        Self {
+            key,
+            name: "".to_string(),
+            selected: true,
+            request_config: Arc::new(RwLock::new(RequestConfig::default())),
+            metadata: DeckMetadata::default(),
+            resolved_id: None,
+            synonyms: Synonyms::default(),
+            added_card_files: Vec::new(),
+            failed_card_files: Vec::new(),
+            card_download: None,
+            daily_stats: DailyStats::default(),
+            new_cards_per_day: None,
+            maximum_reviews_per_day: None,
+            pending_cards: Vec::new(),
+            media_files: Vec::new(),
+            legacy_media: HashMap::new(),
+            synonyms_download: None,
+            deck_download: None,
+        }
+    }
+
+    /// The name given by the user, or else the title of deck.json.
+    pub fn display_name(&self) -> String {
+        if !self.name.trim().is_empty() {
+            return self.name.trim().to_string();
+        }
+        self.metadata
+            .title
+            .to_owned()
+            .unwrap_or_else(|| format!("Deck {}", self.key + 1))
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.request_config
+            .read()
+            .map(|conf| conf.is_initialized())
+            .unwrap_or(false)
+    }
+
+    /// Identifies the deck in card ids: the id its cards were added with, or else the id of deck.json, or else the
+    /// endpoint the cards are fetched from.
+    pub fn id(&self) -> String {
+        if let Some(id) = &self.resolved_id {
+            return id.to_owned();
+        }
+        if let Some(id) = &self.metadata.id {
+            return id.to_owned();
+        }
+        self.request_config
+            .read()
+            .map(|conf| conf.endpoint.trim_end_matches('/').to_string())
+            .ok()
+            .filter(|endpoint| !endpoint.is_empty())
+            .unwrap_or_else(|| "local".to_string())
+    }
+
+    /// Keeps the current id for good, the ids of the cards do not change when deck.json or the endpoint does later.
+    pub fn resolve_id(&mut self) -> String {
+        let id = self.id();
+        self.resolved_id = Some(id.to_owned());
+        id
+    }
+
+    pub fn new_card_limit_reached(&self) -> bool {
+        self.new_cards_per_day
+            .map(|limit| self.daily_stats.new_cards >= limit)
+            .unwrap_or(false)
+    }
+
+    pub fn review_limit_reached(&self) -> bool {
+        self.maximum_reviews_per_day
+            .map(|limit| self.daily_stats.reviews >= limit)
+            .unwrap_or(false)
+    }
+
+    /// Downloads the synonyms of the deck, a deck without synonym file has none.
+    ///
+    /// Without `restart` only a download already in progress is continued.
+    pub fn try_fetch_synonyms(&mut self, restart: bool) -> Result<(), DownloadState> {
+        if restart {
+            self.synonyms_download = Some(SynonymsItem::default());
+        }
+        if let Some(ref mut synonyms_download) = self.synonyms_download {
+            match synonyms_download.fetch_synonyms(&self.request_config) {
+                Ok(synonyms) => {
+                    self.synonyms = synonyms.unwrap_or_default();
+                    self.synonyms_download = None;
+                }
+                Err(DownloadState::Failed(status)) => {
+                    self.synonyms = Synonyms::default();
+                    self.synonyms_download = None;
+                    return Err(DownloadState::Failed(status));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Downloads the deck metadata, a deck without deck.json uses the defaults.
+    ///
+    /// Without `restart` only a download already in progress is continued. A network or server error keeps the
+    /// metadata downloaded before, only a missing deck.json (404) resets it.
+    pub fn try_fetch_deck_metadata(&mut self, restart: bool) -> Result<(), DownloadState> {
+        if restart {
+            self.deck_download = Some(DeckItem::default());
+        }
+        if let Some(ref mut deck_download) = self.deck_download {
+            let result = deck_download.fetch_deck_metadata(&self.request_config);
+            return self.receive_deck_metadata(result);
+        }
+        Ok(())
+    }
+
+    /// Takes the result of the metadata download, see try_fetch_deck_metadata.
+    fn receive_deck_metadata(
+        &mut self,
+        result: Result<Option<DeckMetadata>, DownloadState>,
+    ) -> Result<(), DownloadState> {
+        match result {
+            Ok(deck_metadata) => {
+                self.metadata = deck_metadata.unwrap_or_default();
+                self.failed_card_files.clear();
+                self.deck_download = None;
+            }
+            Err(DownloadState::Failed(status)) => {
+                if status == "404" {
+                    self.metadata = DeckMetadata::default();
+                }
+                self.deck_download = None;
+                return Err(DownloadState::Failed(status));
+            }
+            Err(err) => return Err(err),
+        }
+        Ok(())
+    }
+
+    /// Key of the media file in the media store, None if it is downloaded from the server.
+    pub fn media_key(&self, name: &str) -> Option<String> {
+        self.media_files
+            .iter()
+            .any(|media_file| media_file == name)
+            .then(|| media_key(&self.id(), name))
+    }
+
+    /// Takes the pending cards with the id of the first one, the variants of a note are added together.
+    pub fn take_pending_cards(&mut self) -> Option<Vec<Card>> {
+        let id = self.pending_cards.first()?.meta_data.id.to_owned();
+        let (cards, pending_cards) = std::mem::take(&mut self.pending_cards)
+            .into_iter()
+            .partition(|card| card.meta_data.id == id);
+        self.pending_cards = pending_cards;
+        Some(cards)
+    }
+
+    pub fn is_downloading_metadata(&self) -> bool {
+        self.deck_download.is_some()
+    }
+
+    /// The card file added next: the first file of the deck manifest that was neither added nor skipped yet, or else
+    /// the first such `card_{n}.json` for decks without a manifest. None once all card files of the manifest were
+    /// added or skipped.
+    pub fn next_card_file(&self) -> Option<String> {
+        match self.metadata.card_files() {
+            Some(card_files) => card_files
+                .into_iter()
+                .find(|card_file| !self.is_card_file_handled(card_file)),
+            None => (0..)
+                .map(|i| format!("card_{}.json", i))
+                .find(|card_file| !self.is_card_file_handled(card_file)),
+        }
+    }
+
+    /// Skips `card_file` from now on if retrying would not help: it can not be parsed, or the server does not have a
+    /// card file the manifest lists. Without manifest a missing card_{n}.json is the end of the deck, and network
+    /// errors are always retried.
+    pub fn skip_failed_card_file(&mut self, card_file: &str, err: &DownloadState) {
+        let skip = match err {
+            DownloadState::ParseError => true,
+            DownloadState::Failed(status) => {
+                self.metadata.card_files().is_some()
+                    && status
+                        .parse::<u16>()
+                        .map(|status| (400..500).contains(&status))
+                        .unwrap_or(false)
+            }
+            _ => false,
+        };
+        if skip && !self.failed_card_files.iter().any(|failed| failed == card_file) {
+            self.failed_card_files.push(card_file.to_owned());
+        }
+    }
+
+    /// Added, or skipped because it can not be read.
+    fn is_card_file_handled(&self, card_file: &str) -> bool {
+        self.is_card_file_added(card_file) || self.failed_card_files.iter().any(|failed| failed == card_file)
+    }
+
+    fn is_card_file_added(&self, card_file: &str) -> bool {
+        self.added_card_files.iter().any(|added| added == card_file)
+    }
+
+    /// Number of card files of the deck, if its manifest tells.
+    pub fn card_file_total(&self) -> Option<usize> {
+        self.metadata.card_files().map(|card_files| card_files.len())
+    }
+
+    /// e.g. "Bahasa Indonesia 1.2 (id, en): 12 of 150 card files added, 1 can not be read (food.json)",
+    /// `card_file_count` being the files added so far.
+    pub fn summary(&self, card_file_count: usize) -> String {
+        let mut summary = self.display_name();
+        if let Some(version) = &self.metadata.version {
+            summary.push_str(&format!(" {}", version));
+        }
+        if let Some(languages) = &self.metadata.languages {
+            summary.push_str(&format!(" ({})", languages.join(", ")));
+        }
+        match self.card_file_total() {
+            Some(count) => summary.push_str(&format!(
+                ": {} of {} card files added",
+                card_file_count.min(count),
+                count
+            )),
+            None => summary.push_str(&format!(": {} card files added", card_file_count)),
+        }
+        if !self.pending_cards.is_empty() {
+            summary.push_str(&format!(", {} imported cards not added yet", self.pending_cards.len()));
+        }
+        if !self.failed_card_files.is_empty() {
+            summary.push_str(&format!(
+                ", {} can not be read ({})",
+                self.failed_card_files.len(),
+                self.failed_card_files.join(", ")
+            ));
+        }
+        summary
+    }
+
+    /// Continues the download of the card file in progress, or else starts downloading `card_file`.
+    pub fn fetch_card(
+        &mut self,
+        card_file: &str,
+        add_reverse_cards: bool,
+        add_dictation_cards: bool,
+    ) -> Result<Vec<Card>, DownloadState> {
+        let deck_id = self.resolve_id();
+        if let Some(ref mut card_download) = &mut self.card_download {
+            match card_download.fetch_card(
+                &self.request_config,
+                &deck_id,
+                add_reverse_cards,
+                add_dictation_cards,
+            ) {
+                Ok(Some(c)) => {
+                    self.card_download = None;
+                    Ok(c)
+                }
+                Err(err) => Err(err),
+                Ok(None) => Err(DownloadState::ParseError),
+            }
+        } else {
+            let mut card_download = CardItem::new(card_file);
+            match card_download.fetch_card(
+                &self.request_config,
+                &deck_id,
+                add_reverse_cards,
+                add_dictation_cards,
+            ) {
+                Ok(Some(c)) => Ok(c),
+                Ok(None) => {
+                    self.card_download = Some(card_download);
+                    Err(DownloadState::ParseError)
+                }
+                Err(err) => {
+                    self.card_download = Some(card_download);
+                    Err(err)
+                }
+            }
+        }
+    }
+}
+
+/// What the deck list in the settings shows and edits of a Deck.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct DeckSettings {
+    pub key: u32,
+    pub name: String,
+    /// Read only, see Deck::display_name.
+    pub display_name: String,
+    pub selected: bool,
+    pub new_cards_per_day: Option<usize>,
+    pub maximum_reviews_per_day: Option<usize>,
+    /// Read only, e.g. "Bahasa Indonesia: 12 card files added, today 3 new cards and 25 reviews".
+    pub summary: String,
+    /// The deck whose server is edited in the API settings.
+    pub active: bool,
+}
+
+/// The version may be written as a string or as a number.
+fn deserialize_version<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let version: Option<serde_json::Value> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(match version {
+        Some(serde_json::Value::String(version)) => Some(version),
+        Some(serde_json::Value::Number(version)) => Some(version.to_string()),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_controller::model_controller::data_model::card_model::{CardKey, CardVariant};
+
+    const CARD_FILE: &str = r#"{"question_text": "Translate", "context_text": "kucing", "label_text": "cat",
+        "placeholder_text": "", "id": 1}"#;
+
+    fn deck_with_manifest() -> Deck {
+        let mut deck = Deck::new(0);
+        deck.request_config = Arc::new(RwLock::new(RequestConfig {
+            endpoint: "https://example.org/bahasa/".to_string(),
+            ..RequestConfig::default()
+        }));
+        let metadata = DeckMetadata::parse(r#"{"id": "bahasa-indonesia", "cards": ["greetings.json"]}"#);
+        assert_eq!(deck.receive_deck_metadata(Ok(metadata)), Ok(()));
+        deck
+    }
+
+    /// The keys of the cards the deck would add now, see Deck::fetch_card.
+    fn card_keys(deck: &mut Deck) -> Vec<CardKey> {
+        let deck_id = deck.resolve_id();
+        Card::parse(CARD_FILE, &deck_id, true, false)
+            .unwrap()
+            .iter()
+            .map(|card| card.meta_data.key())
+            .collect()
+    }
+
+    #[test]
+    fn failed_metadata_download_keeps_the_manifest() {
+        for status in ["Error", "500", "503"] {
+            let mut deck = deck_with_manifest();
+            let result = deck.receive_deck_metadata(Err(DownloadState::Failed(status.to_string())));
+            assert_eq!(result, Err(DownloadState::Failed(status.to_string())));
+            assert_eq!(deck.metadata.id.as_deref(), Some("bahasa-indonesia"));
+            assert_eq!(deck.next_card_file().as_deref(), Some("greetings.json"));
+        }
+    }
+
+    #[test]
+    fn missing_deck_json_resets_the_manifest() {
+        let mut deck = deck_with_manifest();
+        let result = deck.receive_deck_metadata(Err(DownloadState::Failed("404".to_string())));
+        assert_eq!(result, Err(DownloadState::Failed("404".to_string())));
+        assert_eq!(deck.metadata.id, None);
+        assert_eq!(deck.next_card_file().as_deref(), Some("card_0.json"));
+    }
+
+    #[test]
+    fn card_keys_survive_failed_metadata_downloads() {
+        let mut deck = deck_with_manifest();
+        let keys = card_keys(&mut deck);
+        assert_eq!(
+            keys,
+            vec![
+                ("bahasa-indonesia/1".to_string(), CardVariant::Forward),
+                ("bahasa-indonesia/1".to_string(), CardVariant::Reverse),
+            ]
+        );
+        for result in [
+            Err(DownloadState::Failed("Error".to_string())),
+            Err(DownloadState::Failed("404".to_string())),
+            Ok(None),
+        ] {
+            let _ = deck.receive_deck_metadata(result);
+            assert_eq!(card_keys(&mut deck), keys);
+        }
+    }
+
+    #[test]
+    fn deck_without_cards_follows_deck_json() {
+        let mut deck = Deck::new(0);
+        deck.request_config = Arc::new(RwLock::new(RequestConfig {
+            endpoint: "https://example.org/bahasa/".to_string(),
+            ..RequestConfig::default()
+        }));
+        assert_eq!(deck.id(), "https://example.org/bahasa");
+        let metadata = DeckMetadata::parse(r#"{"id": "bahasa-indonesia"}"#);
+        assert_eq!(deck.receive_deck_metadata(Ok(metadata)), Ok(()));
+        assert_eq!(deck.id(), "bahasa-indonesia");
+    }
+}
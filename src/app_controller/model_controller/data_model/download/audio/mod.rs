@@ -7,7 +7,7 @@ use super::DownloadItem;
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
 #[serde(default)]
 pub struct AudioItem {
-    download_item: DownloadItem,
+    pub download_item: DownloadItem,
 }
 
 impl Default for AudioItem {
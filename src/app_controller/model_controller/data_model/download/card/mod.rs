@@ -27,7 +27,10 @@ impl CardItem {
     pub fn fetch_card(
         &mut self,
         request_config: &Arc<RwLock<RequestConfig>>,
-    ) -> Result<Option<Card>, super::DownloadState> {
+        deck_id: &str,
+        add_reverse_cards: bool,
+        add_dictation_cards: bool,
+    ) -> Result<Option<Vec<Card>>, super::DownloadState> {
         match if let Ok(conf) = request_config.read() {
             self.download_item.try_fetch_download(&conf)
         } else {
@@ -37,8 +40,8 @@ impl CardItem {
             Ok(vec) => {
                 match std::str::from_utf8(&vec[..]) {
                     Ok(v) => {
-                        let mut card = Card::parse(v); 
-                        Ok(card)
+                        let cards = Card::parse(v, deck_id, add_reverse_cards, add_dictation_cards);
+                        Ok(cards)
                     }
                     Err(_e) => Ok(None), //  panic!("Invalid UTF-8 sequence: {}", e),
                 }
@@ -0,0 +1,43 @@
+use super::DownloadItem;
+use crate::app_controller::model_controller::data_model::deck::DeckMetadata;
+use crate::app_controller::model_controller::data_model::request_model::RequestConfig;
+
+use std::sync::Arc;
+use std::sync::RwLock;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+#[serde(default)]
+pub struct DeckItem {
+    download_item: DownloadItem,
+}
+
+impl Default for DeckItem {
+    fn default() -> Self {
+        DeckItem::new("deck.json")
+    }
+}
+
+impl DeckItem {
+    pub fn new(url: &str) -> DeckItem {
+        DeckItem {
+            download_item: DownloadItem::new(url),
+        }
+    }
+
+    pub fn fetch_deck_metadata(
+        &mut self,
+        request_config: &Arc<RwLock<RequestConfig>>,
+    ) -> Result<Option<DeckMetadata>, super::DownloadState> {
+        match if let Ok(conf) = request_config.read() {
+            self.download_item.try_fetch_download(&conf)
+        } else {
+            Err(super::DownloadState::None)
+        } {
+            Err(err) => Err(err),
+            Ok(vec) => match std::str::from_utf8(&vec[..]) {
+                Ok(v) => Ok(DeckMetadata::parse(v)),
+                Err(_e) => Ok(None),
+            },
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use super::DownloadState;
+
+use std::sync::{Arc, Mutex};
+use wasm_bindgen::JsCast;
+
+enum Pick {
+    InProgress,
+    Done(String, Vec<u8>),
+    Cancelled,
+}
+
+/// A file the user picks from their device, read in the background like a download.
+pub struct LocalFile {
+    pick: Arc<Mutex<Pick>>,
+}
+
+impl LocalFile {
+    /// Opens the file dialog, e.g. `LocalFile::pick("Anki deck", &["apkg"])`.
+    pub fn pick(filter_name: &str, extensions: &[&str]) -> LocalFile {
+        let pick = Arc::new(Mutex::new(Pick::InProgress));
+        let pick_store = pick.clone();
+        let dialog = rfd::AsyncFileDialog::new().add_filter(filter_name, extensions);
+
+        wasm_bindgen_futures::spawn_local(async move {
+            let picked = match dialog.pick_file().await {
+                Some(file) => Pick::Done(file.file_name(), file.read().await),
+                None => Pick::Cancelled,
+            };
+            if let Ok(mut pick_store) = pick_store.lock() {
+                *pick_store = picked;
+            }
+        });
+        LocalFile { pick }
+    }
+
+    /// Returns the name and content of the file once it was read.
+    pub fn try_read(&self) -> Result<(String, Vec<u8>), DownloadState> {
+        match self.pick.lock() {
+            Err(_) => Err(DownloadState::InProgress),
+            Ok(pick) => match &*pick {
+                Pick::InProgress => Err(DownloadState::InProgress),
+                Pick::Done(name, bytes) => Ok((name.to_owned(), bytes.to_owned())),
+                Pick::Cancelled => Err(DownloadState::Failed("No file was picked".to_string())),
+            },
+        }
+    }
+}
+
+/// Offers `bytes` to the user as a download named `file_name`, the browser decides where it is saved.
+pub fn save_file(file_name: &str, bytes: &[u8]) -> Result<(), String> {
+    let error = |err: wasm_bindgen::JsValue| format!("Could not save {}: {:?}", file_name, err);
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let blob = web_sys::Blob::new_with_u8_array_sequence(&parts).map_err(error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(error)?;
+
+    let anchor = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.create_element("a").ok())
+        .and_then(|element| element.dyn_into::<web_sys::HtmlAnchorElement>().ok())
+        .ok_or_else(|| format!("Could not save {}: no document", file_name))?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).map_err(error)
+}
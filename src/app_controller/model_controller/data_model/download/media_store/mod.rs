@@ -0,0 +1,109 @@
+// Media files of imported decks, kept in the IndexedDB of the browser instead of the saved app state: the saved
+// state is written on every save and copied into every checkpoint, media files are too large for that.
+//
+// Every file is stored under "{deck id}/{file name}", see media_key.
+
+use js_sys::{Promise, Uint8Array};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbOpenDbRequest, IdbRequest, IdbTransactionMode};
+
+const DATABASE_NAME: &str = "libre_learning_media";
+const STORE_NAME: &str = "media";
+
+pub fn media_key(deck_id: &str, name: &str) -> String {
+    format!("{}/{}", deck_id, name)
+}
+
+/// Stores the files by key in the background, a file that could not be stored fails to load later on.
+pub fn store(files: Vec<(String, Vec<u8>)>) {
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(err) = put(files).await {
+            crate::log(&format!("Could not store the media files: {:?}", err));
+        }
+    });
+}
+
+/// Reads the file stored under `key` in the background, `on_load` gets None if there is none.
+pub fn load<F>(key: &str, on_load: F)
+where
+    F: FnOnce(Option<Vec<u8>>) + 'static,
+{
+    let key = key.to_owned();
+    wasm_bindgen_futures::spawn_local(async move {
+        on_load(get(&key).await.unwrap_or(None));
+    });
+}
+
+/// Deletes the files in the background, e.g. the media of a removed deck.
+pub fn remove(keys: Vec<String>) {
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(err) = delete(keys).await {
+            crate::log(&format!("Could not remove the media files: {:?}", err));
+        }
+    });
+}
+
+/// Resolves with the result of the request once it succeeded.
+async fn wait_for(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    JsFuture::from(promise).await?;
+    request.result()
+}
+
+/// Opens the database, the object store is created the first time.
+async fn open() -> Result<IdbDatabase, JsValue> {
+    let factory = web_sys::window()
+        .ok_or_else(|| JsValue::from_str("no window"))?
+        .indexed_db()?
+        .ok_or_else(|| JsValue::from_str("IndexedDB is not available"))?;
+    let request = factory.open_with_u32(DATABASE_NAME, 1)?;
+    let on_upgrade_needed = Closure::once_into_js(move |event: web_sys::Event| {
+        let database = event
+            .target()
+            .and_then(|target| target.dyn_into::<IdbOpenDbRequest>().ok())
+            .and_then(|request| request.result().ok())
+            .and_then(|result| result.dyn_into::<IdbDatabase>().ok());
+        if let Some(database) = database {
+            database.create_object_store(STORE_NAME).ok();
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade_needed.unchecked_ref()));
+    wait_for(&request).await?.dyn_into::<IdbDatabase>()
+}
+
+async fn put(files: Vec<(String, Vec<u8>)>) -> Result<(), JsValue> {
+    let database = open().await?;
+    for (key, bytes) in files {
+        let store = database
+            .transaction_with_str_and_mode(STORE_NAME, IdbTransactionMode::Readwrite)?
+            .object_store(STORE_NAME)?;
+        let request = store.put_with_key(&Uint8Array::from(&bytes[..]), &JsValue::from_str(&key))?;
+        wait_for(&request).await?;
+    }
+    Ok(())
+}
+
+async fn get(key: &str) -> Result<Option<Vec<u8>>, JsValue> {
+    let database = open().await?;
+    let store = database
+        .transaction_with_str(STORE_NAME)?
+        .object_store(STORE_NAME)?;
+    let value = wait_for(&store.get(&JsValue::from_str(key))?).await?;
+    Ok(value.dyn_into::<Uint8Array>().ok().map(|array| array.to_vec()))
+}
+
+async fn delete(keys: Vec<String>) -> Result<(), JsValue> {
+    let database = open().await?;
+    for key in keys {
+        let store = database
+            .transaction_with_str_and_mode(STORE_NAME, IdbTransactionMode::Readwrite)?
+            .object_store(STORE_NAME)?;
+        wait_for(&store.delete(&JsValue::from_str(&key))?).await?;
+    }
+    Ok(())
+}
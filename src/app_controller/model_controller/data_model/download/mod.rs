@@ -1,6 +1,10 @@
 pub mod audio;
 pub mod card;
+pub mod deck;
 pub mod image;
+pub mod local_file;
+pub mod media_store;
+pub mod synonyms;
 
 use js_sys::Date;
 use std::sync::{Arc, Mutex};
@@ -12,6 +16,8 @@ enum Download {
     InProgress,
     Done(ehttp::Response),
     Failed(ehttp::Result<ehttp::Response>, f64),
+    /// Read from the media store, see DownloadItem::load_stored.
+    Local(Vec<u8>),
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Clone)]
@@ -67,6 +73,7 @@ impl DownloadItem {
                 Download::None => DownloadState::None,
                 Download::InProgress => DownloadState::InProgress,
                 Download::Done(_response) => DownloadState::Done,
+                Download::Local(_) => DownloadState::Done,
             }
         }
     }
@@ -98,6 +105,9 @@ impl DownloadItem {
                 Download::Done(response) => {
                     return Ok(response.bytes.clone());
                 }
+                Download::Local(bytes) => {
+                    return Ok(bytes.clone());
+                }
             };
         }
         if !request_config.is_initialized() {
@@ -125,6 +135,9 @@ impl DownloadItem {
                 Download::Done(response) => {
                     return Some(response.bytes.clone());
                 }
+                Download::Local(bytes) => {
+                    return Some(bytes.clone());
+                }
             };
         }
         if !request_config.is_initialized() {
@@ -133,6 +146,19 @@ impl DownloadItem {
         self.download(request_config);
         None
     }
+
+    /// Reads the file from the media store instead of downloading it, e.g. the media of an imported deck.
+    pub fn load_stored(&mut self, key: &str) {
+        let download_store = self.download.clone();
+        *download_store.lock().unwrap() = Download::InProgress;
+        media_store::load(key, move |bytes| {
+            *download_store.lock().unwrap() = match bytes {
+                Some(bytes) => Download::Local(bytes),
+                None => Download::Failed(Err("The media file is missing".to_string()), Date::now()),
+            };
+        });
+    }
+
     fn download(&mut self, request_config: &RequestConfig) {
         let credentials;
 
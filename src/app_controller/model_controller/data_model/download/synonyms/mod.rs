@@ -0,0 +1,43 @@
+use super::DownloadItem;
+use crate::app_controller::model_controller::data_model::request_model::RequestConfig;
+use crate::app_controller::model_controller::data_model::synonyms::Synonyms;
+
+use std::sync::Arc;
+use std::sync::RwLock;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+#[serde(default)]
+pub struct SynonymsItem {
+    download_item: DownloadItem,
+}
+
+impl Default for SynonymsItem {
+    fn default() -> Self {
+        SynonymsItem::new("synonyms.json")
+    }
+}
+
+impl SynonymsItem {
+    pub fn new(url: &str) -> SynonymsItem {
+        SynonymsItem {
+            download_item: DownloadItem::new(url),
+        }
+    }
+
+    pub fn fetch_synonyms(
+        &mut self,
+        request_config: &Arc<RwLock<RequestConfig>>,
+    ) -> Result<Option<Synonyms>, super::DownloadState> {
+        match if let Ok(conf) = request_config.read() {
+            self.download_item.try_fetch_download(&conf)
+        } else {
+            Err(super::DownloadState::None)
+        } {
+            Err(err) => Err(err),
+            Ok(vec) => match std::str::from_utf8(&vec[..]) {
+                Ok(v) => Ok(Synonyms::parse(v)),
+                Err(_e) => Ok(None),
+            },
+        }
+    }
+}
@@ -0,0 +1,109 @@
+// Progressive hints for typed answers, each level reveals a bit more of the label:
+// 1. the word shapes: "_ _ _ _ / _ _ _ _ _"
+// 2. the first letter of every word: "t _ _ _ / k _ _ _ _"
+// 3. and more: the words themselves, one at a time: "terima / k _ _ _ _"
+
+use super::token_diff::tokenize;
+
+/// Number of hint levels of a label, the last one reveals the whole label.
+pub fn hint_levels(label: &str) -> usize {
+    match tokenize(label).len() {
+        0 => 0,
+        words => 2 + words,
+    }
+}
+
+/// The hint shown after `level` hints were used, empty for level 0.
+pub fn hint(label: &str, level: usize) -> String {
+    if level == 0 {
+        return String::new();
+    }
+    tokenize(label)
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            if level >= 3 + i {
+                word.to_owned()
+            } else {
+                word_shape(word, level >= 2)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" / ")
+}
+
+/// Hides the letters and digits of a word, punctuation stays visible.
+///
+/// The first letter of a single character word (e.g. a CJK character) would be the whole word, it stays hidden.
+fn word_shape(word: &str, show_first_letter: bool) -> String {
+    let show_first_letter = show_first_letter && word.chars().count() > 1;
+    word.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if !c.is_alphanumeric() || (show_first_letter && i == 0) {
+                c.to_string()
+            } else {
+                "_".to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hints(label: &str) -> Vec<String> {
+        (0..=hint_levels(label)).map(|level| hint(label, level)).collect()
+    }
+
+    #[test]
+    fn words_are_revealed_one_at_a_time() {
+        assert_eq!(
+            hints("terima kasih"),
+            vec![
+                "",
+                "_ _ _ _ _ _ / _ _ _ _ _",
+                "t _ _ _ _ _ / k _ _ _ _",
+                "terima / k _ _ _ _",
+                "terima / kasih",
+            ]
+        );
+        assert_eq!(hint("terima kasih", 10), "terima / kasih");
+    }
+
+    #[test]
+    fn punctuation_and_digits() {
+        assert_eq!(
+            hints("it's 24, ok!"),
+            vec![
+                "",
+                "_ _ ' _ / _ _ , / _ _ !",
+                "i _ ' _ / 2 _ , / o _ !",
+                "it's / 2 _ , / o _ !",
+                "it's / 24, / o _ !",
+                "it's / 24, / ok!",
+            ]
+        );
+    }
+
+    #[test]
+    fn other_scripts() {
+        assert_eq!(hint("привет мир", 2), "п _ _ _ _ _ / м _ _");
+        assert_eq!(hint("ευχαριστώ", 1), "_ _ _ _ _ _ _ _ _");
+        assert_eq!(hint("ευχαριστώ", 2), "ε _ _ _ _ _ _ _ _");
+        // CJK characters are words of their own, the first letter would give them away.
+        assert_eq!(
+            hints("谢谢你"),
+            vec!["", "_ / _ / _", "_ / _ / _", "谢 / _ / _", "谢 / 谢 / _", "谢 / 谢 / 你"]
+        );
+    }
+
+    #[test]
+    fn empty_labels_have_no_hints() {
+        assert_eq!(hint_levels(""), 0);
+        assert_eq!(hint_levels("  "), 0);
+        assert_eq!(hint("", 1), "");
+    }
+}
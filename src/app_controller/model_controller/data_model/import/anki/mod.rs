@@ -0,0 +1,549 @@
+// Imports the decks exported by Anki (.apkg): a zip archive with the collection as SQLite database
+// ("collection.anki21" or "collection.anki2"), the media files named "0", "1", ... and a "media" file
+// mapping these names to the original file names, e.g. {"0": "hola.mp3"}.
+//
+// Every note becomes a card file, its Anki cards become the variants of the card file and keep their review history.
+
+use super::sqlite::{Database, Table};
+use super::{slug, ImportedDeck};
+use crate::app_controller::model_controller::data_model::card_model::{
+    Card, CardDirection, CardFile, CardKind, CardVariant,
+};
+use crate::app_controller::model_controller::data_model::learning_steps::LearningStep;
+use crate::app_controller::model_controller::data_model::spaced_repetition::Grade;
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::io::Read;
+
+/// Which note fields become the texts of a card, counted from 0. A cloze note always uses its cloze field.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct AnkiFieldMapping {
+    pub context_field: usize,
+    pub label_field: usize,
+    /// None uses the same question for every card.
+    pub question_field: Option<usize>,
+}
+
+impl Default for AnkiFieldMapping {
+    fn default() -> Self {
+        Self {
+            context_field: 0,
+            label_field: 1,
+            question_field: None,
+        }
+    }
+}
+
+const DEFAULT_QUESTION: &str = "What is on the back of the card?";
+const DEFAULT_PLACEHOLDER: &str = "Type the answer";
+
+/// Note types of the "cloze" kind, all others are standard note types.
+const CLOZE_NOTE_TYPE: i64 = 1;
+
+struct NoteType {
+    is_cloze: bool,
+}
+
+/// Scheduling state of an Anki card.
+struct AnkiCard {
+    id: i64,
+    deck_id: i64,
+    /// Index of the template (or cloze number - 1) the card was generated from.
+    ord: i64,
+    /// 0 new, 1 learning, 2 review, 3 relearning
+    kind: i64,
+    /// -1 suspended
+    queue: i64,
+    lapses: i64,
+}
+
+pub fn parse_apkg(
+    bytes: &[u8],
+    file_name: &str,
+    mapping: &AnkiFieldMapping,
+) -> Result<ImportedDeck, String> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))
+        .map_err(|err| format!("{} is not an Anki deck: {}", file_name, err))?;
+
+    let has_entry = |archive: &zip::ZipArchive<_>, name: &str| archive.file_names().any(|entry| entry == name);
+    // newer versions of Anki also add a collection.anki2 that only asks to update Anki.
+    let collection_name = if has_entry(&archive, "collection.anki21") {
+        "collection.anki21"
+    } else if has_entry(&archive, "collection.anki21b") {
+        return Err(format!(
+            "{} uses the newest Anki format, export it again with \"Support older Anki versions\" checked",
+            file_name
+        ));
+    } else {
+        "collection.anki2"
+    };
+    let collection = read_entry(&mut archive, collection_name)?
+        .ok_or_else(|| format!("{} contains no Anki collection", file_name))?;
+    let media_names: HashMap<String, String> = match read_entry(&mut archive, "media")? {
+        Some(media) => serde_json::from_slice(&media).unwrap_or_default(),
+        None => HashMap::new(),
+    };
+
+    let database = Database::open(&collection)?;
+    let col = database.table("col")?;
+    let col_row = col.rows.first().ok_or("The Anki collection is empty")?;
+    let models: serde_json::Value = col
+        .get(col_row, "models")
+        .as_str()
+        .and_then(|models| serde_json::from_str(models).ok())
+        .unwrap_or(serde_json::Value::Null);
+    if models.as_object().map(|models| models.is_empty()).unwrap_or(true)
+        && database.has_table("notetypes")
+    {
+        return Err(format!(
+            "{} uses the newest Anki format, export it again with \"Support older Anki versions\" checked",
+            file_name
+        ));
+    }
+    let note_types: HashMap<i64, NoteType> = models
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(id, model)| {
+            Some((
+                id.parse().ok()?,
+                NoteType {
+                    is_cloze: model["type"].as_i64() == Some(CLOZE_NOTE_TYPE),
+                },
+            ))
+        })
+        .collect();
+    let deck_names: HashMap<i64, String> = col
+        .get(col_row, "decks")
+        .as_str()
+        .and_then(|decks| serde_json::from_str::<serde_json::Value>(decks).ok())
+        .and_then(|decks| {
+            decks.as_object().map(|decks| {
+                decks
+                    .iter()
+                    .filter_map(|(id, deck)| Some((id.parse().ok()?, deck["name"].as_str()?.to_string())))
+                    .collect()
+            })
+        })
+        .unwrap_or_default();
+
+    let cards = database.table("cards")?;
+    let mut cards_of_note: HashMap<i64, Vec<AnkiCard>> = HashMap::new();
+    for row in &cards.rows {
+        let value = |name: &str| cards.get(row, name).as_i64().unwrap_or(0);
+        cards_of_note.entry(value("nid")).or_default().push(AnkiCard {
+            id: value("id"),
+            deck_id: value("did"),
+            ord: value("ord"),
+            kind: value("type"),
+            queue: value("queue"),
+            lapses: value("lapses"),
+        });
+    }
+    let reviews = reviews_by_card(&database.table("revlog")?);
+
+    let id = format!("anki-{}", slug(file_name));
+    let mut imported_cards = Vec::new();
+    let mut referenced_media = Vec::new();
+    let mut skipped = Vec::new();
+    let mut card_count_of_deck: HashMap<i64, usize> = HashMap::new();
+
+    let markup = Markup::new();
+    let notes = database.table("notes")?;
+    for (index, row) in notes.rows.iter().enumerate() {
+        let note_id = notes.get(row, "id").as_i64().unwrap_or(0);
+        let fields: Vec<&str> = notes
+            .get(row, "flds")
+            .as_str()
+            .unwrap_or_default()
+            .split('\u{1f}')
+            .collect();
+        let note_type = notes
+            .get(row, "mid")
+            .as_i64()
+            .and_then(|mid| note_types.get(&mid));
+        let anki_cards = match cards_of_note.get(&note_id) {
+            Some(anki_cards) => anki_cards,
+            None => continue,
+        };
+        let tags: Vec<String> = notes
+            .get(row, "tags")
+            .as_str()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|tag| tag.to_string())
+            .collect();
+        let is_leech = tags.iter().any(|tag| tag.eq_ignore_ascii_case("leech"));
+
+        let card_file = match note_card_file(&fields, note_type, anki_cards, mapping, &markup) {
+            Ok(mut card_file) => {
+                card_file.id = notes
+                    .get(row, "guid")
+                    .as_str()
+                    .map(|guid| guid.to_string())
+                    .or_else(|| Some(note_id.to_string()));
+                card_file.tags = tags;
+                card_file
+            }
+            Err(reason) => {
+                skipped.push(format!("note {}: {}", index + 1, reason));
+                continue;
+            }
+        };
+        referenced_media.extend(card_file.audio_item.iter().cloned());
+        referenced_media.extend(card_file.image_item.iter().flatten().cloned());
+
+        for mut card in Card::from_card_file(&card_file, &id, false, false) {
+            let ord = match card.meta_data.variant {
+                CardVariant::Forward => 0,
+                CardVariant::Reverse => 1,
+                CardVariant::Cloze(number) => number as i64 - 1,
+                CardVariant::Dictation => continue,
+            };
+            let anki_card = match anki_cards.iter().find(|anki_card| anki_card.ord == ord) {
+                Some(anki_card) => anki_card,
+                None => continue,
+            };
+            *card_count_of_deck.entry(anki_card.deck_id).or_insert(0) += 1;
+
+            let meta_data = &mut card.meta_data;
+            if let Some(reviews) = reviews.get(&anki_card.id) {
+                meta_data.timestamps = reviews.iter().map(|(timestamp, _)| *timestamp).collect();
+                meta_data.scores = reviews.iter().map(|(_, score)| *score).collect();
+            }
+            meta_data.learning_step = match anki_card.kind {
+                2 if !meta_data.timestamps.is_empty() => None,
+                3 => Some(LearningStep::Relearning(0)),
+                _ => Some(LearningStep::Learning(0)),
+            };
+            meta_data.lapses = anki_card.lapses.max(0) as usize;
+            meta_data.suspended = anki_card.queue == -1;
+            meta_data.leech = is_leech;
+            imported_cards.push(card);
+        }
+    }
+
+    // the media files are stored with the deck, only the ones used by the cards are kept.
+    let mut media = HashMap::new();
+    for (entry, name) in media_names {
+        if referenced_media.contains(&name) {
+            if let Some(bytes) = read_entry(&mut archive, &entry)? {
+                media.insert(name, bytes);
+            }
+        }
+    }
+
+    let name = card_count_of_deck
+        .iter()
+        .max_by_key(|(_, count)| **count)
+        .and_then(|(deck_id, _)| deck_names.get(deck_id))
+        .map(|name| name.replace("::", " / "))
+        .unwrap_or_else(|| slug(file_name));
+
+    Ok(ImportedDeck {
+        name,
+        id,
+        cards: imported_cards,
+        media,
+        skipped,
+    })
+}
+
+fn read_entry<R: std::io::Read + std::io::Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> Result<Option<Vec<u8>>, String> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(err) => return Err(format!("{}: {}", name, err)),
+    };
+    let mut bytes = Vec::new();
+    entry
+        .read_to_end(&mut bytes)
+        .map_err(|err| format!("{}: {}", name, err))?;
+    Ok(Some(bytes))
+}
+
+fn note_card_file(
+    fields: &[&str],
+    note_type: Option<&NoteType>,
+    anki_cards: &[AnkiCard],
+    mapping: &AnkiFieldMapping,
+    markup: &Markup,
+) -> Result<CardFile, String> {
+    let is_cloze = note_type.map(|note_type| note_type.is_cloze).unwrap_or(false);
+    let field = |index: usize| fields.get(index).map(|field| markup.text(field)).unwrap_or_default();
+
+    let mut card_file = CardFile {
+        question_text: mapping
+            .question_field
+            .map(field)
+            .filter(|question| !question.is_empty())
+            .unwrap_or_else(|| DEFAULT_QUESTION.to_string()),
+        context_text: "".to_string(),
+        label_text: Vec::new(),
+        placeholder_text: DEFAULT_PLACEHOLDER.to_string(),
+        audio_item: fields.iter().find_map(|field| markup.sounds(field).into_iter().next()),
+        image_item: Some(fields.iter().flat_map(|field| markup.images(field)).collect::<Vec<String>>())
+            .filter(|images| !images.is_empty()),
+        id: None,
+        directions: None,
+        reverse_question_text: None,
+        reverse_placeholder_text: None,
+        kind: CardKind::Translation,
+        choices: None,
+        match_overrides: None,
+        tags: Vec::new(),
+    };
+
+    if is_cloze {
+        // the cloze field is the first one of the note type.
+        card_file.context_text = field(0);
+        if !card_file.context_text.contains("{{c") {
+            return Err("the cloze field has no cloze deletion".to_string());
+        }
+        return Ok(card_file);
+    }
+
+    card_file.context_text = field(mapping.context_field);
+    let label = field(mapping.label_field);
+    if card_file.context_text.is_empty() || label.is_empty() {
+        return Err(format!(
+            "field {} or field {} is empty",
+            mapping.context_field + 1,
+            mapping.label_field + 1
+        ));
+    }
+    card_file.label_text = vec![label];
+    // the first template asks for the back of the note, the second one (if any) for its front.
+    let directions: Vec<CardDirection> = [(0, CardDirection::Forward), (1, CardDirection::Reverse)]
+        .into_iter()
+        .filter(|(ord, _)| anki_cards.iter().any(|anki_card| anki_card.ord == *ord))
+        .map(|(_, direction)| direction)
+        .collect();
+    if directions.is_empty() {
+        return Err("only cards of the third or later template".to_string());
+    }
+    card_file.directions = Some(directions);
+    Ok(card_file)
+}
+
+/// Timestamps (in ms, as the ids of the review log) and scores of the reviews of each card, oldest first.
+fn reviews_by_card(revlog: &Table) -> HashMap<i64, Vec<(f64, f64)>> {
+    let mut reviews: HashMap<i64, Vec<(f64, f64)>> = HashMap::new();
+    for row in &revlog.rows {
+        let value = |name: &str| revlog.get(row, name).as_i64().unwrap_or(0);
+        // rescheduling by hand (type 4, or ease 0) is no review.
+        let grade = match value("ease") {
+            1 => Grade::Again,
+            2 => Grade::Hard,
+            3 => Grade::Good,
+            4 => Grade::Easy,
+            _ => continue,
+        };
+        if value("type") == 4 {
+            continue;
+        }
+        reviews
+            .entry(value("cid"))
+            .or_default()
+            .push((value("id") as f64, grade.score()));
+    }
+    for reviews in reviews.values_mut() {
+        reviews.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+    reviews
+}
+
+/// Media tags and HTML of the note fields, compiled once per import.
+struct Markup {
+    sound: Regex,
+    image: Regex,
+    line_break: Regex,
+    tag: Regex,
+    entity: Regex,
+}
+
+impl Markup {
+    fn new() -> Markup {
+        Markup {
+            sound: Regex::new(r"\[sound:([^\]]*)\]").unwrap(),
+            image: Regex::new(r#"(?i)<img[^>]*\ssrc\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap(),
+            line_break: Regex::new(r"(?i)<br\s*/?>|</?(div|p|li)[^>]*>").unwrap(),
+            tag: Regex::new(r"<[^>]*>").unwrap(),
+            entity: Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").unwrap(),
+        }
+    }
+
+    /// File names of the [sound:...] tags of a field.
+    fn sounds(&self, field: &str) -> Vec<String> {
+        self.sound
+            .captures_iter(field)
+            .map(|captures| captures[1].trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+
+    /// File names of the <img src="..."> tags of a field.
+    fn images(&self, field: &str) -> Vec<String> {
+        self.image
+            .captures_iter(field)
+            .filter_map(|captures| {
+                captures
+                    .get(1)
+                    .or_else(|| captures.get(2))
+                    .or_else(|| captures.get(3))
+                    .map(|src| self.decode_entities(src.as_str()))
+            })
+            .collect()
+    }
+
+    /// The text of a field without markup, media tags and line breaks.
+    fn text(&self, field: &str) -> String {
+        let text = self.sound.replace_all(field, "");
+        let text = self.line_break.replace_all(&text, " ");
+        let text = self.tag.replace_all(&text, "");
+        self.decode_entities(&text)
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    fn decode_entities(&self, text: &str) -> String {
+        self.entity
+            .replace_all(text, |captures: &regex::Captures| {
+                let name = &captures[1];
+                let c = if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+                } else if let Some(decimal) = name.strip_prefix('#') {
+                    decimal.parse().ok().and_then(char::from_u32)
+                } else {
+                    match name {
+                        "nbsp" => Some(' '),
+                        "amp" => Some('&'),
+                        "lt" => Some('<'),
+                        "gt" => Some('>'),
+                        "quot" => Some('"'),
+                        "apos" => Some('\''),
+                        _ => None,
+                    }
+                };
+                c.map(|c| c.to_string())
+                    .unwrap_or_else(|| captures[0].to_string())
+            })
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // collection.anki2 with 512 byte pages, decks "Default" (1) and "Bahasa::Basics" (1700):
+    //   note 10 "kucing-guid", basic and reversed, tags " animals leech ":
+    //     '<b>kucing</b> [sound:kucing.mp3]' / 'cat &amp; kitten <img src="cat.png">'
+    //     card 100: ord 0, review (type 2), 3 lapses, reviewed with ease 1, 3 and 4 and rescheduled by hand once
+    //     card 101: ord 1, new and suspended (queue -1)
+    //   note 20 "terima-guid", cloze: '{{c1::Terima}} {{c2::kasih}}' / 'thank you'
+    //     card 200: ord 0, relearning (type 3), 1 lapse, reviewed with ease 2
+    //     card 201: ord 1, learning (type 1) in the deck "Default", reviewed with ease 3
+    //   note 30 "anjing-guid", basic: 'anjing' / '' with card 300
+    // media: {"0": "kucing.mp3", "1": "cat.png", "2": "unused.jpg"}
+    const FIXTURE: &[u8] = include_bytes!("fixture.apkg");
+
+    fn imported_deck() -> ImportedDeck {
+        parse_apkg(FIXTURE, "fixture.apkg", &AnkiFieldMapping::default()).unwrap()
+    }
+
+    fn card<'a>(deck: &'a ImportedDeck, id: &str, variant: CardVariant) -> &'a Card {
+        deck.cards
+            .iter()
+            .find(|card| card.meta_data.id == id && card.meta_data.variant == variant)
+            .unwrap()
+    }
+
+    #[test]
+    fn notes_become_card_files_of_the_deck_with_most_cards() {
+        let deck = imported_deck();
+        assert_eq!(deck.name, "Bahasa / Basics");
+        assert_eq!(deck.id, "anki-fixture");
+        let keys: Vec<(&str, CardVariant)> = deck
+            .cards
+            .iter()
+            .map(|card| (card.meta_data.id.as_str(), card.meta_data.variant))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("anki-fixture/kucing-guid", CardVariant::Forward),
+                ("anki-fixture/kucing-guid", CardVariant::Reverse),
+                ("anki-fixture/terima-guid", CardVariant::Cloze(1)),
+                ("anki-fixture/terima-guid", CardVariant::Cloze(2)),
+            ]
+        );
+        assert_eq!(deck.skipped, vec!["note 3: field 1 or field 2 is empty"]);
+    }
+
+    #[test]
+    fn fields_lose_their_markup() {
+        let deck = imported_deck();
+        let forward = card(&deck, "anki-fixture/kucing-guid", CardVariant::Forward);
+        assert_eq!(forward.display_data.context_text, "kucing");
+        assert_eq!(forward.display_data.label_text, "cat & kitten");
+        assert_eq!(forward.meta_data.tags, vec!["animals", "leech"]);
+        let reverse = card(&deck, "anki-fixture/kucing-guid", CardVariant::Reverse);
+        assert_eq!(reverse.display_data.label_text, "kucing");
+        let cloze = card(&deck, "anki-fixture/terima-guid", CardVariant::Cloze(2));
+        assert_eq!(cloze.display_data.label_text, "kasih");
+    }
+
+    #[test]
+    fn cards_keep_their_review_history() {
+        let deck = imported_deck();
+        let review = &card(&deck, "anki-fixture/kucing-guid", CardVariant::Forward).meta_data;
+        // oldest first, without the rescheduling by hand.
+        assert_eq!(review.timestamps, vec![1600000000000.0, 1600000100000.0, 1600000200000.0]);
+        assert_eq!(
+            review.scores,
+            vec![Grade::Again.score(), Grade::Good.score(), Grade::Easy.score()]
+        );
+        assert_eq!(review.learning_step, None);
+        assert_eq!(review.lapses, 3);
+        assert!(!review.suspended);
+        assert!(review.leech);
+
+        let new = &card(&deck, "anki-fixture/kucing-guid", CardVariant::Reverse).meta_data;
+        assert!(new.timestamps.is_empty());
+        assert_eq!(new.learning_step, Some(LearningStep::Learning(0)));
+        assert!(new.suspended);
+
+        let relearning = &card(&deck, "anki-fixture/terima-guid", CardVariant::Cloze(1)).meta_data;
+        assert_eq!(relearning.timestamps, vec![1600000400000.0]);
+        assert_eq!(relearning.scores, vec![Grade::Hard.score()]);
+        assert_eq!(relearning.learning_step, Some(LearningStep::Relearning(0)));
+        assert_eq!(relearning.lapses, 1);
+        assert!(!relearning.leech);
+
+        let learning = &card(&deck, "anki-fixture/terima-guid", CardVariant::Cloze(2)).meta_data;
+        assert_eq!(learning.timestamps, vec![1600000500000.0]);
+        assert_eq!(learning.learning_step, Some(LearningStep::Learning(0)));
+    }
+
+    #[test]
+    fn only_referenced_media_is_carried_over() {
+        let deck = imported_deck();
+        let mut names: Vec<&str> = deck.media.keys().map(|name| name.as_str()).collect();
+        names.sort_unstable();
+        assert_eq!(names, vec!["cat.png", "kucing.mp3"]);
+        assert_eq!(deck.media["kucing.mp3"], b"ID3 kucing");
+        assert_eq!(deck.media["cat.png"], b"\x89PNG cat");
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let err = parse_apkg(b"not a zip archive", "notes.apkg", &AnkiFieldMapping::default()).unwrap_err();
+        assert!(err.starts_with("notes.apkg is not an Anki deck"));
+    }
+}
@@ -0,0 +1,528 @@
+// Imports vocabulary kept in a spreadsheet and exported as CSV or TSV, every row becomes a card file, e.g.
+// context,label,tags
+// "Berapa harga sarapan ini?",How much is this breakfast?|What does this breakfast cost?,food
+//
+// Cells may be quoted ("a, b" or "say ""hi"""), quoted cells may span several lines. Several accepted labels
+// or image paths are separated by "|" within a cell, tags by spaces or commas.
+
+use super::{slug, ImportedDeck};
+use crate::app_controller::model_controller::data_model::card_model::cloze::parse_cloze;
+use crate::app_controller::model_controller::data_model::card_model::{Card, CardFile, CardKind};
+
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Which columns fill the fields of the card files, counted from 0. None leaves the field empty.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct CsvColumnMapping {
+    pub context_column: usize,
+    /// May be empty in rows whose context has cloze deletions.
+    pub label_column: usize,
+    pub question_column: Option<usize>,
+    pub placeholder_column: Option<usize>,
+    /// Paths relative to the server of the deck, e.g. "audio/harga.mp3".
+    pub audio_column: Option<usize>,
+    pub image_column: Option<usize>,
+    pub tags_column: Option<usize>,
+    /// The first row names the columns instead of holding a card.
+    pub has_header: bool,
+}
+
+impl Default for CsvColumnMapping {
+    fn default() -> Self {
+        Self {
+            context_column: 0,
+            label_column: 1,
+            question_column: None,
+            placeholder_column: None,
+            audio_column: None,
+            image_column: None,
+            tags_column: None,
+            has_header: true,
+        }
+    }
+}
+
+const DEFAULT_QUESTION: &str = "Type the translation";
+const DEFAULT_CLOZE_QUESTION: &str = "Type the missing word";
+
+/// Number of rows shown before the file is imported, see CsvPreview.
+const PREVIEW_ROWS: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct CsvRow {
+    /// Line of the file the row starts on, counted from 1.
+    pub line: usize,
+    pub cells: Vec<String>,
+}
+
+/// The rows of a CSV or TSV file, read once and mapped to card files as often as the mapping changes.
+#[derive(Debug)]
+pub struct CsvTable {
+    pub file_name: String,
+    pub rows: Vec<CsvRow>,
+    /// Rows that could not be read by line, e.g. (7, "the quote is never closed").
+    malformed: Vec<(usize, String)>,
+}
+
+/// The first rows of a picked file, shown next to the column mapping before it is imported.
+#[derive(Debug, Clone)]
+pub struct CsvPreview {
+    pub file_name: String,
+    /// The header (if any) and the first rows.
+    pub rows: Vec<CsvRow>,
+    pub row_count: usize,
+    pub column_count: usize,
+}
+
+impl CsvTable {
+    pub fn parse(file_name: &str, bytes: &[u8]) -> Result<CsvTable, String> {
+        let text = match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_string(),
+            // spreadsheets may export Windows-1252, which agrees with Latin-1 for letters.
+            Err(_) => bytes.iter().map(|byte| *byte as char).collect(),
+        };
+        let text = text.trim_start_matches('\u{feff}');
+        let separator = if file_name.to_lowercase().ends_with(".tsv") {
+            '\t'
+        } else {
+            detect_separator(text)
+        };
+        let (rows, malformed) = read_rows(text, separator);
+        if rows.is_empty() {
+            return Err(format!("{} has no rows", file_name));
+        }
+        Ok(CsvTable {
+            file_name: file_name.to_string(),
+            rows,
+            malformed,
+        })
+    }
+
+    pub fn preview(&self) -> CsvPreview {
+        CsvPreview {
+            file_name: self.file_name.to_owned(),
+            rows: self.rows.iter().take(PREVIEW_ROWS + 1).cloned().collect(),
+            row_count: self.rows.len(),
+            column_count: self.column_count(),
+        }
+    }
+
+    /// Cells of the first row, rows with more cells are malformed.
+    fn column_count(&self) -> usize {
+        self.rows.first().map(|row| row.cells.len()).unwrap_or(0)
+    }
+
+    /// e.g. "Spanish Vocabulary.csv" -> "Spanish Vocabulary"
+    pub fn deck_name(&self) -> String {
+        match self.file_name.rfind('.') {
+            Some(index) if index > 0 => self.file_name[..index].to_string(),
+            _ => self.file_name.to_owned(),
+        }
+    }
+
+    /// The card files of the rows, and why the other rows were skipped, e.g. "line 12: the label (column 2) is empty".
+    pub fn card_files(&self, mapping: &CsvColumnMapping) -> (Vec<CardFile>, Vec<String>) {
+        let column_count = self.column_count();
+        let mut card_files = Vec::new();
+        let mut skipped = self.malformed.to_owned();
+        let mut line_of_card: HashMap<(String, String, Vec<String>), usize> = HashMap::new();
+
+        let rows = self.rows.iter().skip(if mapping.has_header { 1 } else { 0 });
+        for row in rows {
+            if row.cells.len() > column_count {
+                skipped.push((
+                    row.line,
+                    format!(
+                        "{} cells instead of {}, is a separator missing its quotes?",
+                        row.cells.len(),
+                        column_count
+                    ),
+                ));
+                continue;
+            }
+            match row_card_file(row, mapping) {
+                Ok(card_file) => {
+                    let content = (
+                        card_file.question_text.to_owned(),
+                        card_file.context_text.to_owned(),
+                        card_file.label_text.to_owned(),
+                    );
+                    if let Some(line) = line_of_card.get(&content) {
+                        skipped.push((row.line, format!("the same card as line {}", line)));
+                        continue;
+                    }
+                    line_of_card.insert(content, row.line);
+                    card_files.push(card_file);
+                }
+                Err(reason) => skipped.push((row.line, reason)),
+            }
+        }
+
+        skipped.sort_by_key(|(line, _)| *line);
+        let skipped = skipped
+            .into_iter()
+            .map(|(line, reason)| format!("line {}: {}", line, reason))
+            .collect();
+        (card_files, skipped)
+    }
+
+    /// Cards to be studied without a server, see AppData::add_imported_deck.
+    pub fn imported_deck(&self, mapping: &CsvColumnMapping) -> ImportedDeck {
+        let id = format!("csv-{}", slug(&self.file_name));
+        let (card_files, skipped) = self.card_files(mapping);
+        ImportedDeck {
+            name: self.deck_name(),
+            cards: card_files
+                .iter()
+                .flat_map(|card_file| Card::from_card_file(card_file, &id, false, false))
+                .collect(),
+            id,
+            media: HashMap::new(),
+            skipped,
+        }
+    }
+}
+
+/// A zip archive with a folder of card_0.json, card_1.json, ... and a deck.json listing them,
+/// ready to be placed in file_server/static.
+pub fn card_folder(folder_name: &str, title: &str, card_files: &[CardFile]) -> Result<Vec<u8>, String> {
+    let manifest = serde_json::json!({
+        "id": folder_name,
+        "title": title,
+        "card_count": card_files.len(),
+    });
+    let mut files = vec![("deck.json".to_string(), manifest)];
+    for (index, card_file) in card_files.iter().enumerate() {
+        let mut json = serde_json::to_value(card_file).map_err(|err| err.to_string())?;
+        // fields without value are left out, as in the card files written by hand.
+        if let Some(object) = json.as_object_mut() {
+            object.retain(|_, value| {
+                !(value.is_null() || value.as_array().map(|array| array.is_empty()).unwrap_or(false))
+            });
+        }
+        files.push((format!("card_{}.json", index), json));
+    }
+
+    let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, json) in files {
+        let text = serde_json::to_string_pretty(&json).map_err(|err| err.to_string())?;
+        archive
+            .start_file(format!("{}/{}", folder_name, name), options)
+            .map_err(|err| err.to_string())?;
+        archive
+            .write_all(text.as_bytes())
+            .map_err(|err| err.to_string())?;
+    }
+    let cursor = archive.finish().map_err(|err| err.to_string())?;
+    Ok(cursor.into_inner())
+}
+
+fn row_card_file(row: &CsvRow, mapping: &CsvColumnMapping) -> Result<CardFile, String> {
+    let cell = |column: Option<usize>| {
+        column
+            .and_then(|column| row.cells.get(column))
+            .map(|cell| cell.trim().to_string())
+            .unwrap_or_default()
+    };
+    let list = |column: Option<usize>| -> Vec<String> {
+        cell(column)
+            .split('|')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect()
+    };
+
+    let context_text = cell(Some(mapping.context_column));
+    if context_text.is_empty() {
+        return Err(format!(
+            "the context (column {}) is empty",
+            mapping.context_column + 1
+        ));
+    }
+    let is_cloze = parse_cloze(&context_text).is_some();
+    let label_text = list(Some(mapping.label_column));
+    if label_text.is_empty() && !is_cloze {
+        return Err(format!(
+            "the label (column {}) is empty",
+            mapping.label_column + 1
+        ));
+    }
+    let default_question = if is_cloze {
+        DEFAULT_CLOZE_QUESTION
+    } else {
+        DEFAULT_QUESTION
+    };
+    let question_text = Some(cell(mapping.question_column))
+        .filter(|question| !question.is_empty())
+        .unwrap_or_else(|| default_question.to_string());
+    let placeholder_text = Some(cell(mapping.placeholder_column))
+        .filter(|placeholder| !placeholder.is_empty())
+        .unwrap_or_else(|| default_question.to_string());
+
+    Ok(CardFile {
+        question_text,
+        context_text,
+        label_text,
+        placeholder_text,
+        audio_item: Some(cell(mapping.audio_column)).filter(|audio| !audio.is_empty()),
+        image_item: Some(list(mapping.image_column)).filter(|images| !images.is_empty()),
+        id: None,
+        directions: None,
+        reverse_question_text: None,
+        reverse_placeholder_text: None,
+        kind: CardKind::Translation,
+        choices: None,
+        match_overrides: None,
+        tags: cell(mapping.tags_column)
+            .split(|c: char| c.is_whitespace() || c == ',' || c == '|')
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| tag.to_string())
+            .collect(),
+    })
+}
+
+/// The most frequent of tab, semicolon and comma in the first line, spreadsheets in some languages use semicolons.
+fn detect_separator(text: &str) -> char {
+    let first_line = text.lines().next().unwrap_or_default();
+    ['\t', ';', ',']
+        .into_iter()
+        .max_by_key(|separator| first_line.matches(*separator).count())
+        .filter(|separator| first_line.contains(*separator))
+        .unwrap_or(',')
+}
+
+/// The rows of the file and the lines of the rows that could not be read, blank rows are left out.
+fn read_rows(text: &str, separator: char) -> (Vec<CsvRow>, Vec<(usize, String)>) {
+    let mut rows = Vec::new();
+    let mut malformed = Vec::new();
+
+    let mut line = 1;
+    let mut row_line = 1;
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut in_quotes = false;
+    let mut cell_was_quoted = false;
+    let mut problem: Option<String> = None;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                '"' => in_quotes = false,
+                '\r' => {}
+                '\n' => {
+                    line += 1;
+                    cell.push('\n');
+                }
+                c => cell.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if cell.trim().is_empty() && !cell_was_quoted => {
+                cell.clear();
+                in_quotes = true;
+                cell_was_quoted = true;
+            }
+            c if c == separator => {
+                cells.push(std::mem::take(&mut cell));
+                cell_was_quoted = false;
+            }
+            '\r' => {}
+            '\n' => {
+                cells.push(std::mem::take(&mut cell));
+                end_row(&mut rows, &mut malformed, row_line, std::mem::take(&mut cells), problem.take());
+                cell_was_quoted = false;
+                line += 1;
+                row_line = line;
+            }
+            c => {
+                if cell_was_quoted && !c.is_whitespace() && problem.is_none() {
+                    problem = Some("text after the closing quote of a cell".to_string());
+                }
+                cell.push(c);
+            }
+        }
+    }
+
+    if in_quotes {
+        malformed.push((
+            row_line,
+            "the quote of this row is never closed, the rest of the file was not read".to_string(),
+        ));
+    } else {
+        cells.push(cell);
+        end_row(&mut rows, &mut malformed, row_line, cells, problem);
+    }
+    (rows, malformed)
+}
+
+fn end_row(
+    rows: &mut Vec<CsvRow>,
+    malformed: &mut Vec<(usize, String)>,
+    line: usize,
+    cells: Vec<String>,
+    problem: Option<String>,
+) {
+    if cells.iter().all(|cell| cell.trim().is_empty()) {
+        return;
+    }
+    match problem {
+        Some(problem) => malformed.push((line, problem)),
+        None => rows.push(CsvRow { line, cells }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn table(text: &str) -> CsvTable {
+        CsvTable::parse("vocabulary.csv", text.as_bytes()).unwrap()
+    }
+
+    fn lines(table: &CsvTable) -> Vec<usize> {
+        table.rows.iter().map(|row| row.line).collect()
+    }
+
+    #[test]
+    fn quoted_cells_keep_separators_quotes_and_line_breaks() {
+        let table = table(
+            "context,label\n\"Berapa harga, Pak?\",How much?\n\"say \"\"hi\"\"\",hi\n\"Selamat\npagi\",good morning\r\nterima kasih,thank you\n",
+        );
+        assert_eq!(table.rows[1].cells, vec!["Berapa harga, Pak?", "How much?"]);
+        assert_eq!(table.rows[2].cells, vec!["say \"hi\"", "hi"]);
+        assert_eq!(table.rows[3].cells, vec!["Selamat\npagi", "good morning"]);
+        // the line break within the quotes still counts for the rows afterwards.
+        assert_eq!(lines(&table), vec![1, 2, 3, 4, 6]);
+        assert_eq!(table.rows[4].cells, vec!["terima kasih", "thank you"]);
+    }
+
+    #[test]
+    fn an_unclosed_quote_ends_the_file() {
+        let table = table("context,label\nkucing,cat\n\"anjing,dog\nburung,bird\n");
+        assert_eq!(lines(&table), vec![1, 2]);
+        let (card_files, skipped) = table.card_files(&CsvColumnMapping::default());
+        assert_eq!(card_files.len(), 1);
+        assert_eq!(
+            skipped,
+            vec!["line 3: the quote of this row is never closed, the rest of the file was not read"]
+        );
+    }
+
+    #[test]
+    fn text_after_a_closing_quote_is_malformed() {
+        let table = table("context,label\n\"kucing\" besar,big cat\nanjing,dog\n");
+        assert_eq!(lines(&table), vec![1, 3]);
+        let (_, skipped) = table.card_files(&CsvColumnMapping::default());
+        assert_eq!(skipped, vec!["line 2: text after the closing quote of a cell"]);
+    }
+
+    #[test]
+    fn rows_with_too_many_cells_are_skipped() {
+        let table = table("context,label\nBerapa harga, Pak?,How much?\nkucing,cat\n");
+        let (card_files, skipped) = table.card_files(&CsvColumnMapping::default());
+        assert_eq!(card_files.len(), 1);
+        assert_eq!(
+            skipped,
+            vec!["line 2: 3 cells instead of 2, is a separator missing its quotes?"]
+        );
+    }
+
+    #[test]
+    fn separators_are_detected() {
+        let semicolons = table("context;label\nBerapa harga, Pak?;How much?\n");
+        assert_eq!(semicolons.rows[1].cells, vec!["Berapa harga, Pak?", "How much?"]);
+        let tabs = table("context\tlabel\nsatu, dua\tone, two\n");
+        assert_eq!(tabs.rows[1].cells, vec!["satu, dua", "one, two"]);
+        // a .tsv file is split at tabs even if its first line has more commas.
+        let tsv = CsvTable::parse("vocabulary.tsv", b"a, b, c\td\n").unwrap();
+        assert_eq!(tsv.rows[0].cells, vec!["a, b, c", "d"]);
+    }
+
+    #[test]
+    fn files_that_are_not_utf8_are_read_as_latin1() {
+        let table = CsvTable::parse("spanish.csv", b"context,label\nni\xf1o,child\n\xbfqu\xe9?,what?\n").unwrap();
+        assert_eq!(table.rows[1].cells, vec!["niño", "child"]);
+        assert_eq!(table.rows[2].cells, vec!["¿qué?", "what?"]);
+        let with_bom = CsvTable::parse("spanish.csv", "\u{feff}niño,child\n".as_bytes()).unwrap();
+        assert_eq!(with_bom.rows[0].cells, vec!["niño", "child"]);
+    }
+
+    #[test]
+    fn the_header_row_is_skipped() {
+        let table = table("context,label\nkucing,cat\n");
+        let (card_files, _) = table.card_files(&CsvColumnMapping::default());
+        assert_eq!(card_files.len(), 1);
+        assert_eq!(card_files[0].context_text, "kucing");
+        let mapping = CsvColumnMapping {
+            has_header: false,
+            ..CsvColumnMapping::default()
+        };
+        let (card_files, _) = table.card_files(&mapping);
+        assert_eq!(card_files.len(), 2);
+        assert_eq!(card_files[0].context_text, "context");
+    }
+
+    #[test]
+    fn duplicate_and_empty_rows_are_reported() {
+        let table = table("context,label,tags\nkucing,cat|kitty,animals\n\n anjing ,,\nkucing,cat|kitty,pets\n{{c1::Terima}} kasih,,\n");
+        let mapping = CsvColumnMapping {
+            tags_column: Some(2),
+            ..CsvColumnMapping::default()
+        };
+        let (card_files, skipped) = table.card_files(&mapping);
+        assert_eq!(
+            skipped,
+            vec!["line 4: the label (column 2) is empty", "line 5: the same card as line 2"]
+        );
+        assert_eq!(card_files.len(), 2);
+        assert_eq!(card_files[0].label_text, vec!["cat", "kitty"]);
+        assert_eq!(card_files[0].tags, vec!["animals"]);
+        // cloze rows need no label.
+        assert_eq!(card_files[1].question_text, DEFAULT_CLOZE_QUESTION);
+    }
+
+    #[test]
+    fn card_folder_holds_a_manifest_and_the_card_files() {
+        let table = table("context,label,image\nkucing,cat,cat.png|cat2.png\nanjing,dog,\n");
+        let mapping = CsvColumnMapping {
+            image_column: Some(2),
+            ..CsvColumnMapping::default()
+        };
+        let (card_files, _) = table.card_files(&mapping);
+        let bytes = card_folder("vocabulary", "Vocabulary", &card_files).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            vec!["vocabulary/card_0.json", "vocabulary/card_1.json", "vocabulary/deck.json"]
+        );
+        let mut read = |name: &str| {
+            let mut text = String::new();
+            archive.by_name(name).unwrap().read_to_string(&mut text).unwrap();
+            text
+        };
+        let manifest: serde_json::Value = serde_json::from_str(&read("vocabulary/deck.json")).unwrap();
+        assert_eq!(
+            manifest,
+            serde_json::json!({"id": "vocabulary", "title": "Vocabulary", "card_count": 2})
+        );
+        let card_file: serde_json::Value = serde_json::from_str(&read("vocabulary/card_0.json")).unwrap();
+        assert_eq!(card_file["image_item"], serde_json::json!(["cat.png", "cat2.png"]));
+        assert!(card_file.get("audio_item").is_none());
+        // the card files are read back like the ones written by hand.
+        let cards = Card::parse(&read("vocabulary/card_1.json"), "vocabulary", false, false).unwrap();
+        assert_eq!(cards[0].display_data.label_text, "dog");
+    }
+}
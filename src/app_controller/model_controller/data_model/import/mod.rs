@@ -0,0 +1,35 @@
+// Decks read from a local file instead of a file server, they work offline.
+
+use super::card_model::Card;
+
+use std::collections::HashMap;
+
+pub mod anki;
+pub mod csv;
+pub mod sqlite;
+
+/// Cards read from a local file, added as a deck of their own, see AppData::add_imported_deck.
+#[derive(Debug)]
+pub struct ImportedDeck {
+    pub name: String,
+    /// Identifies the deck in card ids, see deck::Deck::id.
+    pub id: String,
+    pub cards: Vec<Card>,
+    /// Media referenced by the cards, by file name.
+    pub media: HashMap<String, Vec<u8>>,
+    /// Why some entries of the file did not become cards, e.g. "note 12: the back field is empty".
+    pub skipped: Vec<String>,
+}
+
+/// e.g. "Spanish Vocabulary (2).apkg" -> "spanish-vocabulary-2"
+pub fn slug(file_name: &str) -> String {
+    let stem = match file_name.rfind('.') {
+        Some(index) if index > 0 => &file_name[..index],
+        _ => file_name,
+    };
+    stem.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
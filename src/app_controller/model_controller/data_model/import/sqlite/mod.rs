@@ -0,0 +1,465 @@
+// Reads the tables of an SQLite database file, just enough to import the collection of an Anki deck.
+//
+// Only the table b-trees are walked (no indexes, no journal), see https://www.sqlite.org/fileformat.html
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(val) => Some(*val),
+            Value::Real(val) => Some(*val as i64),
+            Value::Text(val) => val.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Text(val) => Some(val),
+            _ => None,
+        }
+    }
+}
+
+/// The rows of a table, an `integer primary key` column holds the rowid.
+#[derive(Debug)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.eq_ignore_ascii_case(name))
+    }
+
+    /// Value of the column in the row, Null if the table has no such column.
+    pub fn get<'a>(&self, row: &'a [Value], name: &str) -> &'a Value {
+        self.column(name)
+            .and_then(|index| row.get(index))
+            .unwrap_or(&Value::Null)
+    }
+}
+
+pub struct Database<'a> {
+    bytes: &'a [u8],
+    page_size: usize,
+    usable_size: usize,
+    /// name -> (root page, create statement)
+    tables: HashMap<String, (usize, String)>,
+}
+
+impl<'a> Database<'a> {
+    pub fn open(bytes: &'a [u8]) -> Result<Database<'a>, String> {
+        if bytes.len() < 100 || !bytes.starts_with(b"SQLite format 3\0") {
+            return Err("Not an SQLite database".to_string());
+        }
+        let page_size = match u16::from_be_bytes([bytes[16], bytes[17]]) {
+            1 => 65536,
+            page_size => page_size as usize,
+        };
+        if page_size < 512 {
+            return Err("Invalid SQLite page size".to_string());
+        }
+        if bytes[56..60] != [0, 0, 0, 1] && bytes[56..60] != [0, 0, 0, 0] {
+            return Err("Only UTF-8 SQLite databases are supported".to_string());
+        }
+        let mut database = Database {
+            bytes,
+            page_size,
+            usable_size: page_size - bytes[20] as usize,
+            tables: HashMap::new(),
+        };
+
+        // sqlite_schema(type, name, tbl_name, rootpage, sql) is the table at page 1.
+        let mut rows = Vec::new();
+        database.read_table(1, &mut rows, &mut HashSet::new(), 0)?;
+        for (_, record) in rows {
+            if let [Value::Text(kind), Value::Text(name), _, root_page, Value::Text(sql), ..] =
+                &record[..]
+            {
+                if kind == "table" {
+                    if let Some(root_page) = root_page.as_i64() {
+                        database
+                            .tables
+                            .insert(name.to_lowercase(), (root_page as usize, sql.to_owned()));
+                    }
+                }
+            }
+        }
+        Ok(database)
+    }
+
+    pub fn has_table(&self, name: &str) -> bool {
+        self.tables.contains_key(&name.to_lowercase())
+    }
+
+    pub fn table(&self, name: &str) -> Result<Table, String> {
+        let (root_page, sql) = self
+            .tables
+            .get(&name.to_lowercase())
+            .ok_or_else(|| format!("The table {} is missing", name))?;
+        let (columns, rowid_column) = parse_columns(sql);
+        let mut rows = Vec::new();
+        self.read_table(*root_page, &mut rows, &mut HashSet::new(), 0)?;
+        Ok(Table {
+            rows: rows
+                .into_iter()
+                .map(|(rowid, mut record)| {
+                    record.resize(columns.len(), Value::Null);
+                    if let Some(index) = rowid_column {
+                        record[index] = Value::Integer(rowid);
+                    }
+                    record
+                })
+                .collect(),
+            columns,
+        })
+    }
+
+    fn page(&self, number: usize) -> Result<&'a [u8], String> {
+        let start = number
+            .checked_sub(1)
+            .and_then(|index| index.checked_mul(self.page_size))
+            .filter(|start| start + self.page_size <= self.bytes.len())
+            .ok_or_else(|| format!("The SQLite page {} is missing", number))?;
+        Ok(&self.bytes[start..start + self.page_size])
+    }
+
+    /// `visited` holds the pages read so far, a corrupt file could link a page into the b-tree more than once.
+    fn read_table(
+        &self,
+        number: usize,
+        rows: &mut Vec<(i64, Vec<Value>)>,
+        visited: &mut HashSet<usize>,
+        depth: usize,
+    ) -> Result<(), String> {
+        if depth > 64 {
+            return Err("The SQLite b-tree is too deep".to_string());
+        }
+        if !visited.insert(number) {
+            return Err(format!("The SQLite page {} is linked twice", number));
+        }
+        let page = self.page(number)?;
+        let header = if number == 1 { 100 } else { 0 };
+        let kind = *page.get(header).ok_or("Corrupt SQLite page")?;
+        let cell_count = read_u16(page, header + 3)?;
+        let cell_pointers = header + if kind == 0x05 { 12 } else { 8 };
+        if cell_pointers + 2 * cell_count > page.len() {
+            return Err("Corrupt SQLite page".to_string());
+        }
+
+        for i in 0..cell_count {
+            let cell = read_u16(page, cell_pointers + 2 * i)?;
+            match kind {
+                // interior page: left child and key.
+                0x05 => {
+                    let child = u32::from_be_bytes(read_array(page, cell)?) as usize;
+                    self.read_table(child, rows, visited, depth + 1)?;
+                }
+                // leaf page: payload size, rowid and payload.
+                0x0d => {
+                    let (payload_size, n) = read_varint(page, cell)?;
+                    let (rowid, m) = read_varint(page, cell + n)?;
+                    let payload = self.payload(page, cell + n + m, payload_size as usize)?;
+                    rows.push((rowid as i64, parse_record(&payload)?));
+                }
+                _ => return Err(format!("Unexpected SQLite page type {}", kind)),
+            }
+        }
+        if kind == 0x05 {
+            let right_child = u32::from_be_bytes(read_array(page, header + 8)?) as usize;
+            self.read_table(right_child, rows, visited, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// The payload of a cell, which continues on overflow pages if it does not fit.
+    fn payload(&self, page: &[u8], start: usize, size: usize) -> Result<Vec<u8>, String> {
+        // no payload is larger than the file, a corrupt size must not allocate or loop beyond it.
+        if size > self.bytes.len() {
+            return Err("Corrupt SQLite cell".to_string());
+        }
+        let usable = self.usable_size;
+        let max_local = usable - 35;
+        let min_local = (usable - 12) * 32 / 255 - 23;
+        let local = if size <= max_local {
+            size
+        } else {
+            let local = min_local + (size - min_local) % (usable - 4);
+            if local <= max_local {
+                local
+            } else {
+                min_local
+            }
+        };
+        let mut payload = page
+            .get(start..start + local)
+            .ok_or("Corrupt SQLite cell")?
+            .to_vec();
+        if local < size {
+            let mut overflow = u32::from_be_bytes(read_array(page, start + local)?) as usize;
+            while payload.len() < size {
+                if overflow == 0 {
+                    return Err("Corrupt SQLite overflow page".to_string());
+                }
+                let overflow_page = self.page(overflow)?;
+                let take = (size - payload.len()).min(usable - 4);
+                payload.extend_from_slice(&overflow_page[4..4 + take]);
+                overflow = u32::from_be_bytes(read_array(overflow_page, 0)?) as usize;
+            }
+        }
+        Ok(payload)
+    }
+}
+
+fn read_u16(bytes: &[u8], start: usize) -> Result<usize, String> {
+    bytes
+        .get(start..start + 2)
+        .map(|slice| u16::from_be_bytes([slice[0], slice[1]]) as usize)
+        .ok_or_else(|| "Corrupt SQLite page".to_string())
+}
+
+fn read_array(bytes: &[u8], start: usize) -> Result<[u8; 4], String> {
+    bytes
+        .get(start..start + 4)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or_else(|| "Corrupt SQLite page".to_string())
+}
+
+/// Returns the value and the number of bytes it takes.
+fn read_varint(bytes: &[u8], start: usize) -> Result<(u64, usize), String> {
+    let mut value: u64 = 0;
+    for i in 0..9 {
+        let byte = *bytes.get(start + i).ok_or("Corrupt SQLite varint")?;
+        if i == 8 {
+            return Ok(((value << 8) | byte as u64, 9));
+        }
+        value = (value << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    unreachable!()
+}
+
+fn parse_record(payload: &[u8]) -> Result<Vec<Value>, String> {
+    let (header_size, n) = read_varint(payload, 0)?;
+    let mut serial_types = Vec::new();
+    let mut position = n;
+    while position < header_size as usize {
+        let (serial_type, n) = read_varint(payload, position)?;
+        serial_types.push(serial_type);
+        position += n;
+    }
+
+    let mut values = Vec::new();
+    let mut position = header_size as usize;
+    for serial_type in serial_types {
+        let size = match serial_type {
+            0 | 8 | 9 => 0,
+            1 => 1,
+            2 => 2,
+            3 => 3,
+            4 => 4,
+            5 => 6,
+            6 | 7 => 8,
+            n if n >= 12 => ((n - 12) / 2) as usize,
+            n => return Err(format!("Unknown SQLite serial type {}", n)),
+        };
+        let bytes = position
+            .checked_add(size)
+            .and_then(|end| payload.get(position..end))
+            .ok_or("Corrupt SQLite record")?;
+        position += size;
+        values.push(match serial_type {
+            0 => Value::Null,
+            8 => Value::Integer(0),
+            9 => Value::Integer(1),
+            7 => Value::Real(f64::from_be_bytes(bytes.try_into().unwrap_or([0; 8]))),
+            1..=6 => {
+                // big-endian two's complement of 1 to 8 bytes.
+                let mut value: i64 = if bytes[0] & 0x80 != 0 { -1 } else { 0 };
+                for byte in bytes {
+                    value = (value << 8) | *byte as i64;
+                }
+                Value::Integer(value)
+            }
+            n if n % 2 == 0 => Value::Blob(bytes.to_vec()),
+            _ => Value::Text(String::from_utf8_lossy(bytes).into_owned()),
+        });
+    }
+    Ok(values)
+}
+
+/// Column names of a `create table` statement and the index of its `integer primary key` column.
+fn parse_columns(sql: &str) -> (Vec<String>, Option<usize>) {
+    let body = match (sql.find('('), sql.rfind(')')) {
+        (Some(start), Some(end)) if start < end => &sql[start + 1..end],
+        _ => return (Vec::new(), None),
+    };
+    let mut definitions = Vec::new();
+    let mut depth = 0;
+    let mut definition = String::new();
+    for c in body.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                definitions.push(std::mem::take(&mut definition));
+                continue;
+            }
+            _ => {}
+        }
+        definition.push(c);
+    }
+    definitions.push(definition);
+
+    let mut columns = Vec::new();
+    let mut rowid_column = None;
+    for definition in definitions {
+        let definition = definition.trim();
+        let lowercase = definition.to_lowercase();
+        // table constraints are no columns.
+        if ["primary", "unique", "check", "foreign", "constraint"]
+            .iter()
+            .any(|keyword| lowercase.starts_with(keyword))
+        {
+            continue;
+        }
+        let name = definition
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .trim_matches(|c| c == '"' || c == '`' || c == '[' || c == ']');
+        if name.is_empty() {
+            continue;
+        }
+        let words: Vec<&str> = lowercase.split_whitespace().collect();
+        if words.get(1) == Some(&"integer") && lowercase.contains("primary key") {
+            rowid_column = Some(columns.len());
+        }
+        columns.push(name.to_string());
+    }
+    (columns, rowid_column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 512 byte pages: the notes table has an interior root page, one note spills onto overflow pages.
+    //   create table notes (id integer primary key, flds text not null, due integer, ease real, data blob)
+    //   notes 1 to 40: ('kata {i}\x1fword {i}', i * 1000, 2.5, null)
+    //   note 100: ('panjang ' * 400, -1, -0.5, bytes 0 to 255 three times)
+    //   notes 101 to 105: ('negative {due}', due) for due -2, -300, -70000, -2^40 and i64::MIN
+    //   create table col (id integer primary key, name text, unique(name)) with (1, 'Default')
+    const FIXTURE: &[u8] = include_bytes!("fixture.sqlite");
+
+    fn note(notes: &Table, id: i64) -> &[Value] {
+        notes
+            .rows
+            .iter()
+            .find(|row| notes.get(row, "id").as_i64() == Some(id))
+            .unwrap()
+    }
+
+    #[test]
+    fn reads_the_schema() {
+        let database = Database::open(FIXTURE).unwrap();
+        assert!(database.has_table("notes"));
+        assert!(database.has_table("COL"));
+        assert!(!database.has_table("sqlite_autoindex_col_1"));
+        assert!(database.table("revlog").is_err());
+        let col = database.table("col").unwrap();
+        assert_eq!(col.columns, vec!["id", "name"]);
+        assert_eq!(col.rows, vec![vec![Value::Integer(1), Value::Text("Default".to_string())]]);
+    }
+
+    #[test]
+    fn reads_all_leaves_below_an_interior_page() {
+        let notes = Database::open(FIXTURE).unwrap().table("notes").unwrap();
+        assert_eq!(notes.rows.len(), 46);
+        let ids: Vec<i64> = notes.rows.iter().filter_map(|row| notes.get(row, "id").as_i64()).collect();
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(ids, sorted);
+        let row = note(&notes, 40);
+        assert_eq!(notes.get(row, "flds").as_str(), Some("kata 40\x1fword 40"));
+        assert_eq!(notes.get(row, "due"), &Value::Integer(40000));
+        assert_eq!(notes.get(row, "ease"), &Value::Real(2.5));
+        assert_eq!(notes.get(row, "data"), &Value::Null);
+        assert_eq!(notes.get(row, "missing"), &Value::Null);
+    }
+
+    #[test]
+    fn reads_payloads_from_overflow_pages() {
+        let notes = Database::open(FIXTURE).unwrap().table("notes").unwrap();
+        let row = note(&notes, 100);
+        assert_eq!(notes.get(row, "flds").as_str(), Some("panjang ".repeat(400).as_str()));
+        let data: Vec<u8> = (0..3).flat_map(|_| 0..=255u8).collect();
+        assert_eq!(notes.get(row, "data"), &Value::Blob(data));
+        assert_eq!(notes.get(row, "ease"), &Value::Real(-0.5));
+    }
+
+    #[test]
+    fn reads_negative_integers() {
+        let notes = Database::open(FIXTURE).unwrap().table("notes").unwrap();
+        for (id, due) in [
+            (100, -1),
+            (101, -2),
+            (102, -300),
+            (103, -70000),
+            (104, -(1 << 40)),
+            (105, i64::MIN),
+        ] {
+            assert_eq!(notes.get(note(&notes, id), "due"), &Value::Integer(due));
+        }
+    }
+
+    #[test]
+    fn truncated_files_are_errors() {
+        assert!(Database::open(&FIXTURE[..99]).is_err());
+        for length in (100..FIXTURE.len()).step_by(64) {
+            let result = Database::open(&FIXTURE[..length]).and_then(|database| database.table("notes"));
+            assert!(result.is_err(), "truncated to {} bytes", length);
+        }
+    }
+
+    #[test]
+    fn corrupt_files_do_not_panic() {
+        for position in 100..FIXTURE.len() {
+            for byte in [0x00, 0x05, 0xff] {
+                let mut bytes = FIXTURE.to_vec();
+                bytes[position] = byte;
+                if let Ok(database) = Database::open(&bytes) {
+                    let _ = database.table("notes");
+                    let _ = database.table("col");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parses_column_definitions() {
+        assert_eq!(
+            parse_columns("CREATE TABLE \"cards\" (\"id\" integer primary key, [nid] integer not null, due int, CHECK (due > 0))"),
+            (vec!["id".to_string(), "nid".to_string(), "due".to_string()], Some(0))
+        );
+        assert_eq!(parse_columns("create table t (a text, b decimal(10, 2))").0, vec!["a", "b"]);
+        assert_eq!(parse_columns("create table t"), (Vec::new(), None));
+    }
+}
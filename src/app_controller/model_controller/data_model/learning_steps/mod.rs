@@ -0,0 +1,151 @@
+// Anki-style learning steps: new cards (and cards that were forgotten) are repeated at short, fixed
+// delays and only graduate into the long-term scheduler after passing the last step.
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum LearningStep {
+    Learning(usize),
+    Relearning(usize),
+}
+
+/// Delays in milliseconds.
+#[derive(Debug, Clone, Default)]
+pub struct LearningSteps {
+    pub learning: Vec<f64>,
+    pub relearning: Vec<f64>,
+}
+
+impl LearningSteps {
+    pub fn parse(learning: &str, relearning: &str) -> LearningSteps {
+        LearningSteps {
+            learning: parse_steps(learning),
+            relearning: parse_steps(relearning),
+        }
+    }
+
+    /// The delay before the card is due again, None if the card is not (or no longer) in a learning step.
+    pub fn delay(&self, learning_step: &Option<LearningStep>) -> Option<f64> {
+        match learning_step {
+            Some(LearningStep::Learning(index)) => self.learning.get(*index).cloned(),
+            Some(LearningStep::Relearning(index)) => self.relearning.get(*index).cloned(),
+            None => None,
+        }
+    }
+
+    /// Returns the learning step after a review, None means the card graduated (or stays graduated).
+    pub fn next(&self, learning_step: &Option<LearningStep>, success: bool) -> Option<LearningStep> {
+        match learning_step {
+            Some(LearningStep::Learning(index)) => {
+                next_step(&self.learning, *index, success).map(LearningStep::Learning)
+            }
+            Some(LearningStep::Relearning(index)) => {
+                next_step(&self.relearning, *index, success).map(LearningStep::Relearning)
+            }
+            None => {
+                // a lapse sends a graduated card back to the relearning steps.
+                if !success && !self.relearning.is_empty() {
+                    Some(LearningStep::Relearning(0))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+fn next_step(steps: &[f64], index: usize, success: bool) -> Option<usize> {
+    if steps.is_empty() {
+        None
+    } else if !success {
+        Some(0)
+    } else if index + 1 < steps.len() {
+        Some(index + 1)
+    } else {
+        None
+    }
+}
+
+/// Parses whitespace or comma separated durations such as "1m 10m 1h", invalid entries are skipped.
+///
+/// Units: s (seconds), m (minutes), h (hours), d (days), a number without unit is read as minutes.
+pub fn parse_steps(steps: &str) -> Vec<f64> {
+    steps
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|step| !step.is_empty())
+        .filter_map(|step| {
+            let (value, unit) = match step.find(|c: char| c.is_alphabetic()) {
+                Some(index) => step.split_at(index),
+                None => (step, "m"),
+            };
+            let millis = match unit {
+                "s" => 1000.0,
+                "m" => 1000.0 * 60.0,
+                "h" => 1000.0 * 60.0 * 60.0,
+                "d" => 1000.0 * 60.0 * 60.0 * 24.0,
+                _ => return None,
+            };
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|value| *value > 0.0)
+                .map(|value| value * millis)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: f64 = 1000.0 * 60.0;
+
+    #[test]
+    fn parse_steps_reads_units() {
+        assert_eq!(
+            parse_steps("30s 1m, 10 1h 2d"),
+            vec![0.5 * MINUTE, MINUTE, 10.0 * MINUTE, 60.0 * MINUTE, 2.0 * 24.0 * 60.0 * MINUTE]
+        );
+    }
+
+    #[test]
+    fn parse_steps_skips_invalid_entries() {
+        assert_eq!(parse_steps("1x 0m -5m abc 1.5m"), vec![1.5 * MINUTE]);
+        assert!(parse_steps("").is_empty());
+    }
+
+    #[test]
+    fn learning_cards_move_through_the_steps_and_graduate() {
+        let steps = LearningSteps::parse("1m 10m", "10m");
+        let first = Some(LearningStep::Learning(0));
+        assert_eq!(steps.delay(&first), Some(MINUTE));
+
+        let second = steps.next(&first, true);
+        assert_eq!(second, Some(LearningStep::Learning(1)));
+        assert_eq!(steps.delay(&second), Some(10.0 * MINUTE));
+        assert_eq!(steps.next(&second, true), None);
+    }
+
+    #[test]
+    fn a_failure_restarts_the_steps() {
+        let steps = LearningSteps::parse("1m 10m", "10m");
+        assert_eq!(
+            steps.next(&Some(LearningStep::Learning(1)), false),
+            Some(LearningStep::Learning(0))
+        );
+    }
+
+    #[test]
+    fn a_lapse_sends_graduated_cards_to_relearning() {
+        let steps = LearningSteps::parse("1m 10m", "10m");
+        assert_eq!(steps.next(&None, false), Some(LearningStep::Relearning(0)));
+        assert_eq!(steps.next(&None, true), None);
+        assert_eq!(steps.next(&Some(LearningStep::Relearning(0)), true), None);
+        assert_eq!(steps.delay(&None), None);
+    }
+
+    #[test]
+    fn without_steps_cards_graduate_right_away() {
+        let steps = LearningSteps::parse("", "");
+        assert_eq!(steps.next(&Some(LearningStep::Learning(0)), false), None);
+        assert_eq!(steps.next(&None, false), None);
+    }
+}
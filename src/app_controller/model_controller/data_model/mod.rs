@@ -1,32 +1,63 @@
 // Everything NON-UI
 
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::RwLock;
 use std::sync::{Arc, Mutex};
 
 pub mod card_model;
+pub mod deck;
 pub mod download;
+pub mod hint;
+pub mod import;
+pub mod learning_steps;
+pub mod normalization;
 pub mod request_model;
+pub mod review_queue;
 pub mod spaced_repetition;
 pub mod static_audio;
 pub mod static_fonts;
+pub mod synonyms;
+pub mod token_diff;
+pub mod typo_tolerance;
 
 use card_model::Card;
+use card_model::CardKey;
 use card_model::CardDisplayData;
 use card_model::CardMetaData;
+use deck::Deck;
+use deck::DeckMetadata;
+use deck::DeckSettings;
 use download::card::CardItem;
+use download::local_file::LocalFile;
+use download::media_store;
+use download::media_store::media_key;
+use import::csv::{CsvPreview, CsvTable};
+use import::ImportedDeck;
+use learning_steps::LearningSteps;
+use review_queue::DailyStats;
+use spaced_repetition::fsrs::FSRSOptimizer;
+use spaced_repetition::Scheduler;
 use spaced_repetition::SpacedRepetition;
 use static_audio::StaticAudio;
+use synonyms::Synonyms;
 
 use crate::app_controller::model_controller::data_model::download::DownloadItem;
 use crate::app_controller::model_controller::data_model::download::DownloadState;
 use crate::app_controller::model_controller::data_model::request_model::RequestConfig;
 
 use egui_extras::RetainedImage;
+use rand::seq::SliceRandom;
+
+/// Number of wrong options shown next to the label of a multiple-choice card.
+const MULTIPLE_CHOICE_DISTRACTORS: usize = 3;
+
+/// Number of insert buttons derived from the labels when the deck does not list its special characters.
+const MAXIMUM_SPECIAL_CHARACTERS: usize = 24;
 
 #[derive(serde::Deserialize, serde::Serialize, derivative::Derivative)]
 #[derivative(Debug)]
 pub struct AppData {
-    pub request_config: Arc<RwLock<RequestConfig>>,
     pub ai_request_config: Arc<RwLock<RequestConfig>>,
 
     #[serde(skip)]
@@ -39,21 +70,69 @@ pub struct AppData {
     #[derivative(Debug = "ignore")]
     pub space_repetition_model: SpacedRepetition,
 
-    pub card_download: Option<CardItem>,
+    #[serde(skip)]
+    #[derivative(Debug = "ignore")]
+    pub fsrs_optimizer: Option<FSRSOptimizer>,
+
+    /// When the current card was shown, used to measure the answer time.
+    #[serde(skip)]
+    pub review_start_timestamp: Option<f64>,
+
+    /// Hints used for the current card.
+    #[serde(skip)]
+    pub hints_used: usize,
+
+    /// What has been studied in all decks, each deck counts its own as well.
+    #[serde(default)]
+    pub daily_stats: DailyStats,
+
+    #[serde(default)]
+    pub decks: Vec<Deck>,
+
+    /// Key of the deck whose server is edited in the API settings.
+    #[serde(default)]
+    pub active_deck: u32,
+
+    // the single deck saved before decks existed, moved into decks by migrate_decks.
+    #[serde(default, skip_serializing, rename = "request_config")]
+    legacy_request_config: Option<Arc<RwLock<RequestConfig>>>,
+    #[serde(default, skip_serializing, rename = "card_download")]
+    legacy_card_download: Option<CardItem>,
+    #[serde(default, skip_serializing, rename = "synonyms")]
+    legacy_synonyms: Option<Synonyms>,
+    #[serde(default, skip_serializing, rename = "deck_metadata")]
+    legacy_deck_metadata: Option<DeckMetadata>,
+    #[serde(default, skip_serializing, rename = "added_card_files")]
+    legacy_added_card_files: Option<Vec<String>>,
+
+    /// The card currently shown to the user.
+    #[serde(skip)]
+    pub current_card_key: Option<CardKey>,
 
     #[serde(skip)]
     download_item_test: Option<DownloadItem>,
 
     #[serde(skip)]
     ai_download_item_test: Option<DownloadItem>,
+
+    #[serde(skip)]
+    #[derivative(Debug = "ignore")]
+    local_file: Option<LocalFile>,
+
+    /// The picked CSV or TSV file while its columns are mapped, see AppData::read_csv_table.
+    #[serde(skip)]
+    #[derivative(Debug = "ignore")]
+    csv_table: Option<CsvTable>,
 }
 impl AppData {
 
     pub fn play_audio_of_card(&mut self, index: usize) -> bool {
-        if self.card_list.len() <= index {
-            return false;
-        }
-        if !self.card_list[index].display_data.play_audio(self.request_config.clone()) {
+        let request_config = match self.deck_of_card(index) {
+            Some(deck) => deck.request_config.clone(),
+            None => return false,
+        };
+        self.load_local_media(index);
+        if !self.card_list[index].display_data.play_audio(request_config) {
             return false;
         }else{
             return true;
@@ -62,13 +141,15 @@ impl AppData {
     }
 
     pub fn load_audio_of_card(&mut self, index: usize){
-        if self.card_list.len() <= index {
-            return;
-        }
+        let request_config = match self.deck_of_card(index) {
+            Some(deck) => deck.request_config.clone(),
+            None => return,
+        };
+        self.load_local_media(index);
 
         let current_card = &mut self.card_list[index];
         if current_card.display_data.has_audio() {
-            current_card.display_data.load_audio(self.request_config.clone());
+            current_card.display_data.load_audio(request_config);
         }        
     }
 
@@ -85,17 +166,31 @@ impl AppData {
     }
 
     pub fn get_image_of_card(&mut self, index: usize) -> Option<Arc<Mutex<RetainedImage>>> {
-        if self.card_list.len() <= index {
-            return None;
-        }
+        let request_config = self.deck_of_card(index)?.request_config.clone();
+        self.load_local_media(index);
         let current_card = &mut self.card_list[index];
 
         if current_card.display_data.has_image() {
-             return current_card.display_data.get_image(self.request_config.clone());
+             return current_card.display_data.get_image(request_config);
         } 
         None
     }
 
+    /// Imported decks keep their media instead of a server to download it from.
+    fn load_local_media(&mut self, index: usize) {
+        let deck_key = match self.card_list.get(index) {
+            Some(card) => card.meta_data.deck,
+            None => return,
+        };
+        if let Some(deck) = self.decks.iter().find(|deck| deck.key == deck_key) {
+            if !deck.media_files.is_empty() {
+                self.card_list[index]
+                    .display_data
+                    .load_local_media(|name| deck.media_key(name));
+            }
+        }
+    }
+
     // TODO
     pub fn get_dalle_image_for_card(&mut self, index: usize) -> Option<Arc<Mutex<RetainedImage>>> {
         if self.card_list.len() <= index {
@@ -124,6 +219,383 @@ impl AppData {
         current_card.display_data.get_image(self.ai_request_config.clone())
     }
 
+    /// Sorts card_list so that the cards due at `now` come first (earliest first), returns the number of due cards.
+    ///
+    /// Cards in a learning step are due after the step's delay, all others when their scheduler says so.
+    /// Suspended and buried cards and the cards of paused decks are never due and go to the end of the list.
+    pub fn update_review_queue(&mut self, now: f64, learning_steps: &LearningSteps) -> usize {
+        let mut next_session_timestamps: HashMap<CardKey, f64> = HashMap::new();
+        let studied_decks: HashSet<u32> = self
+            .decks
+            .iter()
+            .filter(|deck| deck.selected && !deck.review_limit_reached())
+            .map(|deck| deck.key)
+            .collect();
+
+        for card in &self.card_list {
+            if card.meta_data.is_hidden(now) || !studied_decks.contains(&card.meta_data.deck) {
+                next_session_timestamps.insert(card.meta_data.key(), f64::INFINITY);
+                continue;
+            }
+            let learning_step_due = card.meta_data.timestamps.last().and_then(|last_timestamp| {
+                learning_steps
+                    .delay(&card.meta_data.learning_step)
+                    .map(|delay| last_timestamp + delay)
+            });
+            next_session_timestamps.insert(
+                card.meta_data.key(),
+                learning_step_due.unwrap_or_else(|| {
+                    self.space_repetition_model
+                        .schedule(&card.meta_data, now)
+                        .next_session_timestamp
+                }),
+            );
+        }
+
+        self.card_list.sort_by(|a, b| {
+            next_session_timestamps
+                .get(&a.meta_data.key())
+                .partial_cmp(&next_session_timestamps.get(&b.meta_data.key()))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        self.card_list
+            .iter()
+            .filter(|card| {
+                next_session_timestamps
+                    .get(&card.meta_data.key())
+                    .map(|t| *t <= now)
+                    .unwrap_or(false)
+            })
+            .count()
+    }
+
+    /// Returns the shuffled options of a multiple-choice card, None if it is not one or there is nothing to choose from.
+    ///
+    /// Without choices in the card file the distractors are labels of other cards of the same deck and variant.
+    pub fn multiple_choice_options(&self, index: usize) -> Option<Vec<String>> {
+        let card = self.card_list.get(index)?;
+        if !card.display_data.is_multiple_choice() {
+            return None;
+        }
+        let label = card.display_data.get_label();
+        let accepted_labels = card.display_data.get_accepted_labels();
+        let mut rng = rand::thread_rng();
+
+        let mut options: Vec<String> = match card.display_data.choices {
+            Some(ref choices) => choices.to_owned(),
+            None => {
+                let variant = std::mem::discriminant(&card.meta_data.variant);
+                let mut labels: Vec<String> = self
+                    .card_list
+                    .iter()
+                    .filter(|other| other.meta_data.deck == card.meta_data.deck)
+                    .filter(|other| std::mem::discriminant(&other.meta_data.variant) == variant)
+                    .map(|other| other.display_data.get_label())
+                    .filter(|other_label| !accepted_labels.contains(other_label))
+                    .collect();
+                labels.sort();
+                labels.dedup();
+                labels.shuffle(&mut rng);
+                labels.truncate(MULTIPLE_CHOICE_DISTRACTORS);
+                labels
+            }
+        };
+        if !options.contains(&label) {
+            options.push(label);
+        }
+        if options.len() < 2 {
+            return None;
+        }
+        options.shuffle(&mut rng);
+        Some(options)
+    }
+
+    /// All answers accepted for the card: its labels and their synonym substitutions in its deck.
+    pub fn accepted_labels(&self, index: usize) -> Vec<String> {
+        let (mut labels, deck) = match (self.card_list.get(index), self.deck_of_card(index)) {
+            (Some(card), Some(deck)) => (card.display_data.get_accepted_labels(), deck),
+            _ => return Vec::new(),
+        };
+        for label in labels.clone() {
+            for alternative in deck.synonyms.alternatives(&label) {
+                if !labels.contains(&alternative) {
+                    labels.push(alternative);
+                }
+            }
+        }
+        labels
+    }
+
+    /// Downloads the synonyms of all decks, pending as long as one of them is.
+    pub fn try_fetch_synonyms(&mut self, restart: bool) -> Result<(), DownloadState> {
+        let results: Vec<Result<(), DownloadState>> = self
+            .decks
+            .iter_mut()
+            .map(|deck| deck.try_fetch_synonyms(restart))
+            .collect();
+        combine_download_results(results)
+    }
+
+    /// Downloads the metadata of all decks, pending as long as one of them is.
+    pub fn try_fetch_deck_metadata(&mut self, restart: bool) -> Result<(), DownloadState> {
+        let results: Vec<Result<(), DownloadState>> = self
+            .decks
+            .iter_mut()
+            .map(|deck| deck.try_fetch_deck_metadata(restart))
+            .collect();
+        combine_download_results(results)
+    }
+
+    pub fn deck(&self, key: u32) -> Option<&Deck> {
+        self.decks.iter().find(|deck| deck.key == key)
+    }
+
+    pub fn deck_mut(&mut self, key: u32) -> Option<&mut Deck> {
+        self.decks.iter_mut().find(|deck| deck.key == key)
+    }
+
+    pub fn deck_of_card(&self, index: usize) -> Option<&Deck> {
+        self.deck(self.card_list.get(index)?.meta_data.deck)
+    }
+
+    pub fn deck_of_card_mut(&mut self, index: usize) -> Option<&mut Deck> {
+        let key = self.card_list.get(index)?.meta_data.deck;
+        self.deck_mut(key)
+    }
+
+    /// The deck whose server is edited in the API settings.
+    pub fn active_deck(&self) -> Option<&Deck> {
+        self.deck(self.active_deck)
+    }
+
+    /// Adds an empty deck and makes it the active one, returns its key.
+    pub fn add_deck(&mut self) -> u32 {
+        let key = self.decks.iter().map(|deck| deck.key + 1).max().unwrap_or(0);
+        self.decks.push(Deck::new(key));
+        self.active_deck = key;
+        key
+    }
+
+    /// Removes the deck and all its cards, the last deck is kept.
+    pub fn remove_deck(&mut self, key: u32) {
+        if self.decks.len() <= 1 {
+            return;
+        }
+        if let Some(deck) = self.deck(key) {
+            media_store::remove(
+                deck.media_files
+                    .iter()
+                    .map(|name| media_key(&deck.id(), name))
+                    .collect(),
+            );
+        }
+        self.decks.retain(|deck| deck.key != key);
+        self.card_list.retain(|card| card.meta_data.deck != key);
+        if self.active_deck == key {
+            self.active_deck = self.decks[0].key;
+        }
+    }
+
+    /// Opens the file dialog, see AppData::try_read_local_file.
+    pub fn pick_local_file(&mut self, filter_name: &str, extensions: &[&str]) {
+        self.local_file = Some(LocalFile::pick(filter_name, extensions));
+    }
+
+    /// Returns the name and content of the picked file once it was read.
+    pub fn try_read_local_file(&mut self) -> Result<(String, Vec<u8>), DownloadState> {
+        let result = match &self.local_file {
+            Some(local_file) => local_file.try_read(),
+            None => Err(DownloadState::Null),
+        };
+        if !matches!(result, Err(DownloadState::InProgress)) {
+            self.local_file = None;
+        }
+        result
+    }
+
+    /// Keeps the rows of the file until they are imported, returns the rows to preview.
+    pub fn read_csv_table(&mut self, file_name: &str, bytes: &[u8]) -> Result<CsvPreview, String> {
+        let csv_table = CsvTable::parse(file_name, bytes)?;
+        let preview = csv_table.preview();
+        self.csv_table = Some(csv_table);
+        Ok(preview)
+    }
+
+    pub fn csv_table(&self) -> Option<&CsvTable> {
+        self.csv_table.as_ref()
+    }
+
+    pub fn close_csv_table(&mut self) {
+        self.csv_table = None;
+    }
+
+    /// Adds the cards of a local file as a new deck without server. Returns the number of cards added, and why
+    /// entries of the file were skipped including the cards whose id is used already.
+    pub fn add_imported_deck(&mut self, imported_deck: ImportedDeck) -> Result<(usize, Vec<String>), String> {
+        if self.decks.iter().any(|deck| deck.id() == imported_deck.id) {
+            return Err(format!("{} was imported already", imported_deck.name));
+        }
+        let key = self.decks.iter().map(|deck| deck.key + 1).max().unwrap_or(0);
+        let mut deck = Deck::new(key);
+        deck.name = imported_deck.name;
+        deck.metadata.id = Some(imported_deck.id);
+        deck.media_files = imported_deck.media.keys().cloned().collect();
+        let deck_id = deck.resolve_id();
+        media_store::store(
+            imported_deck
+                .media
+                .into_iter()
+                .map(|(name, bytes)| (media_key(&deck_id, &name), bytes))
+                .collect(),
+        );
+
+        let mut skipped = imported_deck.skipped;
+        let mut card_count = 0;
+        // reviewed cards are scheduled right away, new cards wait for the daily limit of new cards.
+        for mut card in imported_deck.cards {
+            let card_key = card.meta_data.key();
+            let pending_cards = self
+                .decks
+                .iter()
+                .chain(std::iter::once(&deck))
+                .flat_map(|deck| deck.pending_cards.iter());
+            if self
+                .card_list
+                .iter()
+                .chain(pending_cards)
+                .any(|other| other.meta_data.key() == card_key)
+            {
+                skipped.push(format!(
+                    "the card id {} ({:?}) is used already",
+                    card_key.0, card_key.1
+                ));
+                continue;
+            }
+            card_count += 1;
+            card.meta_data.deck = key;
+            if card.meta_data.timestamps.is_empty() {
+                deck.pending_cards.push(card);
+            } else {
+                self.card_list.push(card);
+            }
+        }
+        self.decks.push(deck);
+        Ok((card_count, skipped))
+    }
+
+    pub fn deck_settings(&self) -> Vec<DeckSettings> {
+        self.decks
+            .iter()
+            .map(|deck| DeckSettings {
+                key: deck.key,
+                name: deck.name.to_owned(),
+                display_name: deck.display_name(),
+                selected: deck.selected,
+                new_cards_per_day: deck.new_cards_per_day,
+                maximum_reviews_per_day: deck.maximum_reviews_per_day,
+                summary: format!(
+                    "{}, today {} new cards and {} reviews",
+                    deck.summary(self.card_file_count(deck.key)),
+                    deck.daily_stats.new_cards,
+                    deck.daily_stats.reviews
+                ),
+                active: deck.key == self.active_deck,
+            })
+            .collect()
+    }
+
+    /// Applies the changes made in the deck list of the settings.
+    pub fn update_decks(&mut self, deck_settings: &[DeckSettings]) {
+        for settings in deck_settings {
+            if let Some(deck) = self.deck_mut(settings.key) {
+                deck.name = settings.name.to_owned();
+                deck.selected = settings.selected;
+                deck.new_cards_per_day = settings.new_cards_per_day;
+                deck.maximum_reviews_per_day = settings.maximum_reviews_per_day;
+            }
+        }
+    }
+
+    /// Resets the daily stats of all decks if `now` belongs to a new study day.
+    pub fn roll_over_daily_stats(&mut self, now: f64, rollover_hour: u32) {
+        self.daily_stats.roll_over(now, rollover_hour);
+        for deck in self.decks.iter_mut() {
+            deck.daily_stats.roll_over(now, rollover_hour);
+        }
+    }
+
+    pub fn get_card_mut(&mut self, card_key: &CardKey) -> Option<&mut Card> {
+        self.card_list
+            .iter_mut()
+            .find(|card| card.meta_data.key() == *card_key)
+    }
+
+    /// Returns the key and context text of all suspended cards.
+    pub fn suspended_cards(&self) -> Vec<(CardKey, String)> {
+        self.card_list
+            .iter()
+            .filter(|card| card.meta_data.suspended)
+            .map(|card| {
+                (
+                    card.meta_data.key(),
+                    format!(
+                        "{}{}",
+                        if card.meta_data.leech { "🐛 " } else { "" },
+                        card.display_data.get_context()
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    /// Characters offered as insert buttons next to the answer field of the card, the ones listed in the deck.json
+    /// of its deck or else the most frequent non-ASCII letters of the labels in its deck.
+    pub fn special_characters(&self, index: usize) -> Vec<String> {
+        let deck = match self.deck_of_card(index) {
+            Some(deck) => deck,
+            None => return Vec::new(),
+        };
+        if let Some(special_characters) = &deck.metadata.special_characters {
+            return special_characters.to_owned();
+        }
+        let mut counts: HashMap<char, usize> = HashMap::new();
+        for card in self
+            .card_list
+            .iter()
+            .filter(|card| card.meta_data.deck == deck.key)
+        {
+            for label in card.display_data.get_accepted_labels() {
+                for c in label.chars().filter(|c| !c.is_ascii() && c.is_alphabetic()) {
+                    for c in c.to_lowercase() {
+                        *counts.entry(c).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        let mut characters: Vec<(char, usize)> = counts.into_iter().collect();
+        characters.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        characters.truncate(MAXIMUM_SPECIAL_CHARACTERS);
+        characters.sort();
+        characters.into_iter().map(|(c, _)| c.to_string()).collect()
+    }
+
+    /// Starts fitting the FSRS parameters to the review history of all cards, returns false if there is nothing to learn from.
+    pub fn start_fsrs_optimizer(&mut self, parameters: &[f64]) -> bool {
+        let histories = self
+            .card_list
+            .iter()
+            .map(|card| {
+                (
+                    card.meta_data.timestamps.clone(),
+                    card.meta_data.scores.clone(),
+                )
+            })
+            .collect();
+        self.fsrs_optimizer = FSRSOptimizer::new(histories, parameters);
+        self.fsrs_optimizer.is_some()
+    }
+
     pub fn custom_server_connection_status(&mut self) -> DownloadState {
         self.download_item_test
             .as_ref()
@@ -131,12 +603,15 @@ impl AppData {
             .unwrap_or(DownloadState::Null)
     }
 
+    /// Tests the server of the active deck.
     pub fn test_custom_server_connection(&mut self) {
-        if let Ok(conf) = self.request_config.read() {
-            let mut download_item_test = DownloadItem::new("card_0.json");
-            download_item_test.fetch_download(&conf);
-            self.download_item_test = Some(download_item_test);
+        let mut download_item_test = DownloadItem::new("card_0.json");
+        if let Some(deck) = self.active_deck() {
+            if let Ok(conf) = deck.request_config.read() {
+                download_item_test.fetch_download(&conf);
+            }
         }
+        self.download_item_test = Some(download_item_test);
     }
 
     pub fn ai_server_connection_status(&mut self) -> DownloadState {
@@ -159,60 +634,264 @@ impl AppData {
         }
     }
 
-    pub fn try_add_new_card(&mut self) -> Result<(), DownloadState> {
-        let card_id: u16 = self.card_list.len() as u16;
+    /// Number of card files of the deck added so far, every direction of a card file is its own card.
+    fn card_file_count(&self, deck_key: u32) -> usize {
+        self.card_list
+            .iter()
+            .filter(|card| card.meta_data.deck == deck_key)
+            .map(|card| card.meta_data.id.as_str())
+            .collect::<HashSet<&str>>()
+            .len()
+    }
 
-        match self.fetch_card(card_id) {
-            Ok(card) => {
-                self.card_list.insert(0, card);
-                Ok(())
+    /// Media files saved with imported decks are moved into the media store.
+    pub fn migrate_media(&mut self) {
+        for deck in self.decks.iter_mut() {
+            let deck_id = deck.id();
+            let mut files = Vec::new();
+            for (name, media) in std::mem::take(&mut deck.legacy_media) {
+                if let Ok(bytes) = base64::decode(media) {
+                    files.push((media_key(&deck_id, &name), bytes));
+                    deck.media_files.push(name);
+                }
+            }
+            if !files.is_empty() {
+                media_store::store(files);
             }
-            Err(err) => Err(err),
         }
     }
 
-    fn fetch_card(&mut self, card_id: u16) -> Result<Card, DownloadState> {
-        {
-            if let Some(ref mut card_download) = &mut self.card_download {
-                return match card_download.fetch_card(&self.request_config) {
-                    Ok(Some(c)) => {
-                        self.card_download = None;
-                        Ok(c)
-                    }
-                    Err(err) => Err(err),
-                    Ok(None) => Err(DownloadState::ParseError),
-                };
-            } else {
-                let card_url = format!("card_{}.json", card_id);
-                let mut card_download = CardItem::new(&card_url);
-                return match card_download.fetch_card(&self.request_config) {
-                    Ok(Some(c)) => Ok(c),
-                    Ok(None) => {
-                        self.card_download = Some(card_download);
-                        Err(DownloadState::ParseError)
-                    }
-                    Err(err) => {
-                        self.card_download = Some(card_download);
-                        Err(err)
+    /// Saved states from before decks existed hold the settings of a single deck, which becomes the first deck.
+    pub fn migrate_decks(&mut self) {
+        let legacy_request_config = self.legacy_request_config.take();
+        let legacy_card_download = self.legacy_card_download.take();
+        let legacy_synonyms = self.legacy_synonyms.take();
+        let legacy_deck_metadata = self.legacy_deck_metadata.take();
+        let legacy_added_card_files = self.legacy_added_card_files.take();
+        if !self.decks.is_empty() {
+            return;
+        }
+        let mut deck = Deck::new(0);
+        if let Some(request_config) = legacy_request_config {
+            deck.request_config = request_config;
+        }
+        deck.card_download = legacy_card_download;
+        deck.synonyms = legacy_synonyms.unwrap_or_default();
+        deck.metadata = legacy_deck_metadata.unwrap_or_default();
+        deck.added_card_files = legacy_added_card_files.unwrap_or_default();
+        deck.daily_stats = self.daily_stats.clone();
+        for card in self.card_list.iter_mut() {
+            card.meta_data.deck = deck.key;
+        }
+        self.active_deck = deck.key;
+        self.decks.push(deck);
+    }
+
+    /// Card ids used to be numbers unique within a single deck, and card files were probed as `card_{n}.json`
+    /// with n the number of cards added so far. Decks saved before Deck::resolved_id keep the id of their cards.
+    pub fn migrate_card_ids(&mut self) {
+        for deck_index in 0..self.decks.len() {
+            let deck_key = self.decks[deck_index].key;
+            if self.decks[deck_index].resolved_id.is_none()
+                && self.card_list.iter().any(|card| card.meta_data.deck == deck_key)
+            {
+                self.decks[deck_index].resolve_id();
+            }
+            let has_legacy_ids = self
+                .card_list
+                .iter()
+                .filter(|card| card.meta_data.deck == deck_key)
+                .any(|card| !card.meta_data.id.contains('/'));
+            if !has_legacy_ids {
+                continue;
+            }
+            if self.decks[deck_index].added_card_files.is_empty() {
+                self.decks[deck_index].added_card_files = (0..self.card_file_count(deck_key))
+                    .map(|i| format!("card_{}.json", i))
+                    .collect();
+            }
+            let deck_id = self.decks[deck_index].id();
+            for card in self
+                .card_list
+                .iter_mut()
+                .filter(|card| card.meta_data.deck == deck_key)
+            {
+                if !card.meta_data.id.contains('/') {
+                    card.meta_data.id = format!("{}/{}", deck_id, card.meta_data.id);
+                }
+            }
+        }
+    }
+
+    /// One line per deck, see Deck::summary.
+    pub fn deck_summary(&self) -> String {
+        self.decks
+            .iter()
+            .map(|deck| deck.summary(self.card_file_count(deck.key)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Selected decks with a server (or imported cards not added yet) that may add a new card today.
+    fn decks_taking_new_cards(&self) -> impl Iterator<Item = &Deck> {
+        self.decks.iter().filter(|deck| {
+            deck.selected
+                && (deck.is_initialized() || !deck.pending_cards.is_empty())
+                && !deck.new_card_limit_reached()
+                && !deck.review_limit_reached()
+        })
+    }
+
+    pub fn can_add_new_card(&self) -> bool {
+        self.decks_taking_new_cards().next().is_some()
+    }
+
+    /// The deck the next new card comes from: the one with a card download in progress, or else the one that got
+    /// the fewest new cards today so new cards of the selected decks are interleaved.
+    fn next_new_card_deck(&self) -> Option<u32> {
+        let decks: Vec<&Deck> = self.decks_taking_new_cards().collect();
+        if let Some(deck) = decks.iter().find(|deck| deck.card_download.is_some()) {
+            return Some(deck.key);
+        }
+        decks
+            .iter()
+            .filter(|deck| {
+                !deck.pending_cards.is_empty()
+                    || deck.is_downloading_metadata()
+                    || deck.next_card_file().is_some()
+            })
+            .min_by_key(|deck| deck.daily_stats.new_cards)
+            .map(|deck| deck.key)
+    }
+
+    /// Returns the number of cards added, they are counted in the daily stats of their deck.
+    pub fn try_add_new_card(
+        &mut self,
+        add_reverse_cards: bool,
+        add_dictation_cards: bool,
+    ) -> Result<usize, DownloadState> {
+        if !self.can_add_new_card() {
+            return Err(DownloadState::Null);
+        }
+        let deck_key = match self.next_new_card_deck() {
+            Some(deck_key) => deck_key,
+            None => {
+                return Err(DownloadState::Failed(
+                    "All cards of the selected decks were added".to_string(),
+                ))
+            }
+        };
+        if let Some(deck) = self.deck_mut(deck_key) {
+            if let Some(cards) = deck.take_pending_cards() {
+                let count = cards.len();
+                deck.daily_stats.new_cards += count;
+                for card in cards.into_iter().rev() {
+                    self.card_list.insert(0, card);
+                }
+                return Ok(count);
+            }
+        }
+        let deck = match self.deck(deck_key) {
+            Some(deck) => deck,
+            None => return Err(DownloadState::Null),
+        };
+        // the manifest decides which card file comes next.
+        if deck.is_downloading_metadata() {
+            return Err(DownloadState::InProgress);
+        }
+        let card_file = match deck.next_card_file() {
+            Some(card_file) => card_file,
+            None => {
+                return Err(DownloadState::Failed(format!(
+                    "All cards of {} were added",
+                    deck.display_name()
+                )))
+            }
+        };
+
+        let fetched_cards = match self.deck_mut(deck_key) {
+            Some(deck) => deck.fetch_card(&card_file, add_reverse_cards, add_dictation_cards),
+            None => Err(DownloadState::Null),
+        };
+        match fetched_cards {
+            Ok(mut cards) => {
+                // a card with the same id would share (and overwrite) the review history of the other one, the
+                // variants of a card file all have its id, so a matching id in any variant is a duplicate.
+                let duplicate_id = cards
+                    .iter()
+                    .map(|card| &card.meta_data.id)
+                    .find(|id| self.card_list.iter().any(|card| card.meta_data.id == **id))
+                    .cloned();
+                let deck = self.deck_mut(deck_key).ok_or(DownloadState::Null)?;
+                deck.added_card_files.push(card_file.to_owned());
+                if let Some(duplicate_id) = duplicate_id {
+                    return Err(DownloadState::Failed(format!(
+                        "{} was not added, the card id {} is used already",
+                        card_file, duplicate_id
+                    )));
+                }
+
+                let count = cards.len();
+                deck.daily_stats.new_cards += count;
+                for card in cards.iter_mut() {
+                    card.meta_data.deck = deck_key;
+                }
+                for card in cards.into_iter().rev() {
+                    self.card_list.insert(0, card);
+                }
+                Ok(count)
+            }
+            Err(err) => {
+                // a card file that can not be read is skipped instead of retried forever, see Deck::skip_failed_card_file.
+                if let DownloadState::Failed(..) | DownloadState::ParseError | DownloadState::Null = err {
+                    if let Some(deck) = self.deck_mut(deck_key) {
+                        deck.card_download = None;
+                        deck.skip_failed_card_file(&card_file, &err);
                     }
-                };
+                }
+                Err(err)
             }
         }
-        Err(DownloadState::Failed("Missing Config".to_string()))
     }
 }
 
+/// Pending as long as one of the downloads is, or else the first failure.
+fn combine_download_results(results: Vec<Result<(), DownloadState>>) -> Result<(), DownloadState> {
+    if results
+        .iter()
+        .any(|result| matches!(result, Err(DownloadState::None) | Err(DownloadState::InProgress)))
+    {
+        return Err(DownloadState::InProgress);
+    }
+    results
+        .into_iter()
+        .find(|result| result.is_err())
+        .unwrap_or(Ok(()))
+}
+
 impl Default for AppData {
     fn default() -> Self {
         Self {
-            request_config: Arc::new(RwLock::new(RequestConfig::default())),
             ai_request_config: Arc::new(RwLock::new(RequestConfig::default())),
             static_audio: StaticAudio::new(),
             card_list: Vec::new(),
             space_repetition_model: SpacedRepetition::default(),
-            card_download: None::<CardItem>,
+            fsrs_optimizer: None,
+            review_start_timestamp: None,
+            hints_used: 0,
+            daily_stats: DailyStats::default(),
+            decks: vec![Deck::new(0)],
+            active_deck: 0,
+            legacy_request_config: None,
+            legacy_card_download: None,
+            legacy_synonyms: None,
+            legacy_deck_metadata: None,
+            legacy_added_card_files: None,
+            current_card_key: None,
             download_item_test: None,
             ai_download_item_test: None,
+            local_file: None,
+            csv_table: None,
         }
     }
 }
@@ -0,0 +1,406 @@
+// Answers and labels go through the same normalisation steps before they are compared.
+//
+// In a deck file the steps are written as e.g. ["nfc", "lowercase", {"replace": {"pattern": "^to ", "replacement": ""}}]
+
+use super::typo_tolerance::TypoTolerance;
+use any_ascii::any_ascii;
+use regex::Regex;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NormalizationStep {
+    /// Canonical composition, "é" typed as "e" + combining accent equals "é".
+    Nfc,
+    /// Compatibility composition, also folds full-width forms and ligatures ("ｶ" → "カ", "ﬁ" → "fi").
+    Nfkc,
+    /// Removes diacritics but keeps the script ("việt" → "viet", "ça" → "ca").
+    FoldAccents,
+    /// Transliterates everything to ASCII ("привет" → "privet").
+    Transliterate,
+    Lowercase,
+    /// Removes all Unicode punctuation, e.g. . , ? ! 、 。 ！ ？ « »
+    RemovePunctuation,
+    /// Trims and replaces runs of whitespace with a single space.
+    CollapseWhitespace,
+    /// A user-defined regular expression substitution, `replacement` may refer to groups ($1).
+    Replace {
+        pattern: String,
+        replacement: String,
+    },
+}
+
+impl NormalizationStep {
+    pub const ALL: [NormalizationStep; 7] = [
+        NormalizationStep::Nfc,
+        NormalizationStep::Nfkc,
+        NormalizationStep::FoldAccents,
+        NormalizationStep::Transliterate,
+        NormalizationStep::Lowercase,
+        NormalizationStep::RemovePunctuation,
+        NormalizationStep::CollapseWhitespace,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NormalizationStep::Nfc => "Unicode NFC",
+            NormalizationStep::Nfkc => "Unicode NFKC",
+            NormalizationStep::FoldAccents => "Ignore accents",
+            NormalizationStep::Transliterate => "Transliterate to ASCII",
+            NormalizationStep::Lowercase => "Ignore case",
+            NormalizationStep::RemovePunctuation => "Ignore punctuation",
+            NormalizationStep::CollapseWhitespace => "Collapse whitespace",
+            NormalizationStep::Replace { .. } => "Replace (regex)",
+        }
+    }
+
+    /// Compiles the regular expression of a RemovePunctuation or Replace step, the other steps need none.
+    pub fn compile(&self) -> Result<Option<Regex>, String> {
+        match self {
+            NormalizationStep::RemovePunctuation => Ok(Some(Regex::new(r"\p{P}").expect("valid punctuation pattern"))),
+            NormalizationStep::Replace { pattern, .. } => Regex::new(pattern)
+                .map(Some)
+                .map_err(|err| format!("Invalid pattern \"{}\": {}", pattern, err)),
+            _ => Ok(None),
+        }
+    }
+
+    /// `regex` is the compiled expression of the step, see compile.
+    fn apply(&self, regex: Option<&Regex>, text: &str) -> String {
+        match self {
+            NormalizationStep::Nfc => text.nfc().collect(),
+            NormalizationStep::Nfkc => text.nfkc().collect(),
+            NormalizationStep::FoldAccents => fold_accents(text),
+            NormalizationStep::Transliterate => any_ascii(text),
+            NormalizationStep::Lowercase => text.to_lowercase(),
+            NormalizationStep::RemovePunctuation => match regex {
+                Some(regex) => regex.replace_all(text, "").into_owned(),
+                None => text.to_string(),
+            },
+            NormalizationStep::CollapseWhitespace => {
+                text.split_whitespace().collect::<Vec<&str>>().join(" ")
+            }
+            NormalizationStep::Replace { replacement, .. } => match regex {
+                Some(regex) => regex.replace_all(text, replacement.as_str()).into_owned(),
+                None => text.to_string(),
+            },
+        }
+    }
+}
+
+/// Combining diacritical marks used by Latin, Greek and Cyrillic, other marks (e.g. the Japanese dakuten) are kept.
+fn is_diacritic(c: char) -> bool {
+    is_combining_mark(c)
+        && matches!(c as u32, 0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F)
+}
+
+/// Letters with a stroke have no decomposition, they are folded explicitly.
+pub fn fold_accents(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !is_diacritic(*c))
+        .map(|c| match c {
+            'đ' => 'd',
+            'Đ' => 'D',
+            'ł' => 'l',
+            'Ł' => 'L',
+            'ø' => 'o',
+            'Ø' => 'O',
+            'ħ' => 'h',
+            'Ħ' => 'H',
+            c => c,
+        })
+        .nfc()
+        .collect()
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct NormalizationPipeline {
+    pub steps: Vec<NormalizationStep>,
+}
+
+impl Default for NormalizationPipeline {
+    fn default() -> Self {
+        NormalizationPipeline {
+            steps: vec![
+                NormalizationStep::Nfc,
+                NormalizationStep::Lowercase,
+                NormalizationStep::RemovePunctuation,
+                NormalizationStep::CollapseWhitespace,
+            ],
+        }
+    }
+}
+
+impl NormalizationPipeline {
+    /// The pipeline equivalent to the former "Match ASCII", "Match case" and "Ignore sentence punctuation symbols" options.
+    pub fn from_options(match_ascii: bool, match_case: bool, ignore_punctuation: bool) -> Self {
+        let mut steps = vec![NormalizationStep::Nfc];
+        if match_ascii {
+            steps.push(NormalizationStep::Transliterate);
+        }
+        if !match_case {
+            steps.push(NormalizationStep::Lowercase);
+        }
+        if ignore_punctuation {
+            steps.push(NormalizationStep::RemovePunctuation);
+        }
+        steps.push(NormalizationStep::CollapseWhitespace);
+        NormalizationPipeline { steps }
+    }
+
+    /// Adds (before a trailing whitespace step) or removes a step.
+    pub fn set(&mut self, step: NormalizationStep, enabled: bool) {
+        if !enabled {
+            self.steps.retain(|s| *s != step);
+        } else if !self.steps.contains(&step) {
+            let position = match self.steps.last() {
+                Some(NormalizationStep::CollapseWhitespace) => self.steps.len() - 1,
+                _ => self.steps.len(),
+            };
+            self.steps.insert(position, step);
+        }
+    }
+
+    /// Compiles the regular expressions once for all the texts to compare, steps with an invalid pattern are
+    /// left out and reported in CompiledPipeline::errors.
+    pub fn compile(&self) -> CompiledPipeline {
+        let mut compiled = CompiledPipeline {
+            steps: Vec::new(),
+            errors: Vec::new(),
+        };
+        for step in &self.steps {
+            match step.compile() {
+                Ok(regex) => compiled.steps.push((step.to_owned(), regex)),
+                Err(err) => compiled.errors.push(err),
+            }
+        }
+        compiled
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompiledPipeline {
+    steps: Vec<(NormalizationStep, Option<Regex>)>,
+    pub errors: Vec<String>,
+}
+
+impl CompiledPipeline {
+    pub fn apply(&self, text: &str) -> String {
+        self.steps
+            .iter()
+            .fold(text.to_string(), |text, (step, regex)| step.apply(regex.as_ref(), &text))
+    }
+}
+
+/// Answer matching chosen by a deck author, written as `"match"` in a card file or in deck.json, e.g.
+/// {"match": {"match_case": true, "typo_tolerance": 0}}
+///
+/// Unset fields keep the value of the user (or of the deck, for a card).
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct MatchOverrides {
+    /// Permitted typos in percent of the answer, see TypoTolerance.
+    pub typo_tolerance: Option<f32>,
+    /// Permitted typos per answer, replaced by typo_tolerance. Still read from older decks and converted to a
+    /// percentage of the label, typo_tolerance wins if both are set.
+    pub spelling_correction_threshold: Option<usize>,
+    pub typo_tolerance_per_word: Option<bool>,
+    /// Accepts the right words in a different order, see typo_tolerance::count_typos_any_order.
+    pub word_order_tolerant: Option<bool>,
+    pub match_case: Option<bool>,
+    /// Compares the transliterated (ASCII) answer, so diacritics do not matter.
+    pub match_ascii: Option<bool>,
+    #[serde(alias = "ignore_sentence_punctuation_symbols")]
+    pub ignore_punctuation: Option<bool>,
+    /// Replaces the whole pipeline, the flags above are applied afterwards.
+    pub normalization: Option<Vec<NormalizationStep>>,
+}
+
+impl MatchOverrides {
+    /// The first invalid pattern of the normalisation, a deck or card file with one is rejected.
+    pub fn error(&self) -> Option<String> {
+        self.normalization
+            .iter()
+            .flatten()
+            .find_map(|step| step.compile().err())
+    }
+
+    /// `label` is the expected answer, it relates the former spelling_correction_threshold to its length.
+    pub fn apply(
+        &self,
+        pipeline: &mut NormalizationPipeline,
+        typo_tolerance: &mut TypoTolerance,
+        word_order_tolerant: &mut bool,
+        label: &str,
+    ) {
+        if let Some(steps) = &self.normalization {
+            pipeline.steps = steps.to_owned();
+        }
+        if let Some(match_case) = self.match_case {
+            pipeline.set(NormalizationStep::Lowercase, !match_case);
+        }
+        if let Some(match_ascii) = self.match_ascii {
+            pipeline.set(NormalizationStep::Transliterate, match_ascii);
+            if !match_ascii {
+                pipeline.set(NormalizationStep::FoldAccents, false);
+            }
+        }
+        if let Some(ignore_punctuation) = self.ignore_punctuation {
+            pipeline.set(NormalizationStep::RemovePunctuation, ignore_punctuation);
+        }
+        if let Some(threshold) = self.spelling_correction_threshold {
+            typo_tolerance.percentage = TypoTolerance::threshold_percentage(threshold, Some(label));
+        }
+        if let Some(percentage) = self.typo_tolerance {
+            typo_tolerance.percentage = percentage;
+        }
+        if let Some(per_word) = self.typo_tolerance_per_word {
+            typo_tolerance.per_word = per_word;
+        }
+        if let Some(tolerant) = self.word_order_tolerant {
+            *word_order_tolerant = tolerant;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(steps: Vec<NormalizationStep>, text: &str) -> String {
+        NormalizationPipeline { steps }.compile().apply(text)
+    }
+
+    #[test]
+    fn steps_are_read_from_a_deck_file() {
+        let steps: Vec<NormalizationStep> = serde_json::from_str(
+            r#"["nfc", "fold_accents", {"replace": {"pattern": "^to ", "replacement": ""}}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                NormalizationStep::Nfc,
+                NormalizationStep::FoldAccents,
+                NormalizationStep::Replace {
+                    pattern: "^to ".to_string(),
+                    replacement: String::new()
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn unicode_forms_are_composed() {
+        assert_eq!(normalize(vec![NormalizationStep::Nfc], "e\u{301}"), "é");
+        assert_eq!(normalize(vec![NormalizationStep::Nfkc], "ｶﬁ"), "カfi");
+    }
+
+    #[test]
+    fn accents_are_folded_but_the_script_is_kept() {
+        assert_eq!(fold_accents("việt ça Đà Łódź"), "viet ca Da Lodz");
+        assert_eq!(fold_accents("привет"), "привет");
+        assert_eq!(fold_accents("が"), "が");
+        assert_eq!(normalize(vec![NormalizationStep::Transliterate], "привет"), "privet");
+    }
+
+    #[test]
+    fn the_default_pipeline_ignores_case_punctuation_and_spacing() {
+        let pipeline = NormalizationPipeline::default().compile();
+        assert_eq!(pipeline.apply("  Hello,   World! "), "hello world");
+        assert_eq!(pipeline.apply("«¿Qué?»、はい。"), "quéはい");
+    }
+
+    #[test]
+    fn replace_steps_may_refer_to_groups() {
+        let replace = NormalizationStep::Replace {
+            pattern: r"^to (\w+)".to_string(),
+            replacement: "$1".to_string(),
+        };
+        assert_eq!(normalize(vec![replace], "to eat"), "eat");
+    }
+
+    #[test]
+    fn invalid_patterns_are_reported_and_left_out() {
+        let pipeline = NormalizationPipeline {
+            steps: vec![
+                NormalizationStep::Lowercase,
+                NormalizationStep::Replace {
+                    pattern: "(".to_string(),
+                    replacement: String::new(),
+                },
+            ],
+        }
+        .compile();
+        assert_eq!(pipeline.errors.len(), 1);
+        assert!(pipeline.errors[0].starts_with("Invalid pattern \"(\""));
+        assert_eq!(pipeline.apply("ABC("), "abc(");
+    }
+
+    #[test]
+    fn options_map_to_steps() {
+        assert_eq!(
+            NormalizationPipeline::from_options(true, false, true).steps,
+            vec![
+                NormalizationStep::Nfc,
+                NormalizationStep::Transliterate,
+                NormalizationStep::Lowercase,
+                NormalizationStep::RemovePunctuation,
+                NormalizationStep::CollapseWhitespace
+            ]
+        );
+        let mut pipeline = NormalizationPipeline::from_options(false, true, false);
+        pipeline.set(NormalizationStep::FoldAccents, true);
+        assert_eq!(
+            pipeline.steps,
+            vec![
+                NormalizationStep::Nfc,
+                NormalizationStep::FoldAccents,
+                NormalizationStep::CollapseWhitespace
+            ]
+        );
+        pipeline.set(NormalizationStep::Nfc, false);
+        assert_eq!(pipeline.steps.len(), 2);
+    }
+
+    #[test]
+    fn match_overrides_change_only_their_fields() {
+        let match_overrides: MatchOverrides =
+            serde_json::from_str(r#"{"match_case": true, "typo_tolerance": 0}"#).unwrap();
+        let mut pipeline = NormalizationPipeline::default();
+        let mut typo_tolerance = TypoTolerance::default();
+        let mut word_order_tolerant = true;
+        match_overrides.apply(&mut pipeline, &mut typo_tolerance, &mut word_order_tolerant, "kucing");
+        assert!(!pipeline.steps.contains(&NormalizationStep::Lowercase));
+        assert!(pipeline.steps.contains(&NormalizationStep::RemovePunctuation));
+        assert_eq!(typo_tolerance.percentage, 0.0);
+        assert!(!typo_tolerance.per_word);
+        assert!(word_order_tolerant);
+        assert_eq!(match_overrides.error(), None);
+    }
+
+    #[test]
+    fn the_former_threshold_is_a_percentage_of_the_label() {
+        let match_overrides: MatchOverrides =
+            serde_json::from_str(r#"{"spelling_correction_threshold": 2}"#).unwrap();
+        let mut pipeline = NormalizationPipeline::default();
+        let mut typo_tolerance = TypoTolerance::default();
+        let mut word_order_tolerant = false;
+        match_overrides.apply(&mut pipeline, &mut typo_tolerance, &mut word_order_tolerant, "kucing");
+        assert!((typo_tolerance.permitted_mistakes("kucing") - 2.0).abs() < 1e-6);
+
+        let match_overrides: MatchOverrides =
+            serde_json::from_str(r#"{"spelling_correction_threshold": 2, "typo_tolerance": 10}"#).unwrap();
+        match_overrides.apply(&mut pipeline, &mut typo_tolerance, &mut word_order_tolerant, "kucing");
+        assert_eq!(typo_tolerance.percentage, 10.0);
+    }
+
+    #[test]
+    fn match_overrides_with_an_invalid_pattern_are_reported() {
+        let match_overrides: MatchOverrides =
+            serde_json::from_str(r#"{"normalization": [{"replace": {"pattern": "[", "replacement": ""}}]}"#)
+                .unwrap();
+        assert!(match_overrides.error().is_some());
+    }
+}
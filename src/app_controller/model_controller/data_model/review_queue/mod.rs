@@ -0,0 +1,96 @@
+use js_sys::Date;
+use wasm_bindgen::JsValue;
+
+use super::spaced_repetition::ONE_DAY;
+
+const ONE_HOUR: f64 = 1000.0 * 60.0 * 60.0;
+const ONE_MINUTE: f64 = 1000.0 * 60.0;
+
+/// Returns the start of the study day `now` belongs to, a study day begins at `rollover_hour` local time.
+pub fn study_day_start(now: f64, rollover_hour: u32) -> f64 {
+    let timezone_offset = Date::new(&JsValue::from_f64(now)).get_timezone_offset();
+    day_start(now, rollover_hour, timezone_offset)
+}
+
+/// See study_day_start, `timezone_offset` is the local time zone at `now` in minutes behind UTC as given by
+/// Date::getTimezoneOffset, e.g. -60 for UTC+1.
+pub fn day_start(now: f64, rollover_hour: u32, timezone_offset: f64) -> f64 {
+    let offset = timezone_offset * ONE_MINUTE;
+    let rollover = rollover_hour.min(23) as f64 * ONE_HOUR;
+    let local_now = now - offset;
+    ((local_now - rollover) / ONE_DAY).floor() * ONE_DAY + rollover + offset
+}
+
+/// What has been studied since the start of the current study day.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Clone)]
+pub struct DailyStats {
+    pub day_start: f64,
+    pub new_cards: usize,
+    pub reviews: usize,
+}
+
+impl DailyStats {
+    /// Resets the counters if `now` belongs to a new study day.
+    pub fn roll_over(&mut self, now: f64, rollover_hour: u32) {
+        self.start_day(study_day_start(now, rollover_hour));
+    }
+
+    /// Resets the counters unless `day_start` is the start of the current study day.
+    fn start_day(&mut self, day_start: f64) {
+        if day_start != self.day_start {
+            *self = DailyStats {
+                day_start,
+                new_cards: 0,
+                reviews: 0,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-01-02 00:00 UTC
+    const JANUARY_2: f64 = 1_704_153_600_000.0;
+
+    #[test]
+    fn study_days_start_at_the_rollover_hour() {
+        let start = JANUARY_2 + 4.0 * ONE_HOUR;
+        assert_eq!(day_start(start, 4, 0.0), start);
+        assert_eq!(day_start(start + 20.0 * ONE_HOUR, 4, 0.0), start);
+        // before 4:00 the previous study day is not over yet.
+        assert_eq!(day_start(start - 1.0, 4, 0.0), start - ONE_DAY);
+        assert_eq!(day_start(JANUARY_2, 4, 0.0), start - ONE_DAY);
+        assert_eq!(day_start(start + ONE_DAY, 4, 0.0), start + ONE_DAY);
+        // midnight, and hours past the end of the day are the last hour.
+        assert_eq!(day_start(start, 0, 0.0), JANUARY_2);
+        assert_eq!(day_start(start, 30, 0.0), JANUARY_2 - ONE_HOUR);
+    }
+
+    #[test]
+    fn study_days_start_at_local_time() {
+        // 4:00 in UTC+1 is 3:00 UTC, 4:00 in UTC-5 is 9:00 UTC.
+        let now = JANUARY_2 + 8.0 * ONE_HOUR;
+        assert_eq!(day_start(now, 4, -60.0), JANUARY_2 + 3.0 * ONE_HOUR);
+        assert_eq!(day_start(now, 4, 300.0), JANUARY_2 - 15.0 * ONE_HOUR);
+        // UTC+5:30
+        assert_eq!(day_start(now, 0, -330.0), JANUARY_2 - 5.5 * ONE_HOUR);
+    }
+
+    #[test]
+    fn counters_reset_across_the_day_boundary() {
+        let start = JANUARY_2 + 4.0 * ONE_HOUR;
+        let mut daily_stats = DailyStats::default();
+        daily_stats.start_day(day_start(start + ONE_HOUR, 4, 0.0));
+        daily_stats.new_cards = 5;
+        daily_stats.reviews = 20;
+
+        daily_stats.start_day(day_start(start + ONE_DAY - 1.0, 4, 0.0));
+        assert_eq!((daily_stats.new_cards, daily_stats.reviews), (5, 20));
+
+        daily_stats.start_day(day_start(start + ONE_DAY, 4, 0.0));
+        assert_eq!(daily_stats.day_start, start + ONE_DAY);
+        assert_eq!((daily_stats.new_cards, daily_stats.reviews), (0, 0));
+    }
+}
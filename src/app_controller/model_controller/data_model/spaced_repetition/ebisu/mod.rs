@@ -0,0 +1,548 @@
+// Rust port of ebisu.js (https://github.com/fasiha/ebisu.js), formerly loaded from assets/ebisu.min.es6.js.
+//
+// A model is `[alpha, beta, t]`: the recall probability at time `t` after the last review
+// is Beta(alpha, beta) distributed. All times are in the same unit (milliseconds in this app).
+
+use super::{is_success, Schedule, Scheduler, PASSING_SCORE};
+use crate::app_controller::model_controller::data_model::card_model::{CardKey, CardMetaData};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+pub type EbisuModel = [f64; 3];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EbisuError {
+    NumericalInstability,
+    InvalidPercentile,
+    FailedToBracket,
+    FailedToConverge,
+}
+
+const GAMMA_LN_COEFFICIENTS: [f64; 15] = [
+    0.999_999_999_999_997_1,
+    57.156_235_665_862_92,
+    -59.597_960_355_475_49,
+    14.136_097_974_741_746,
+    -0.491_913_816_097_620_2,
+    3.399_464_998_481_189e-5,
+    4.652_362_892_704_858e-5,
+    -9.837_447_530_487_956e-5,
+    1.580_887_032_249_125e-4,
+    -2.102_644_417_241_048_8e-4,
+    2.174_396_181_152_126_5e-4,
+    -1.643_181_065_367_639e-4,
+    8.441_822_398_385_275e-5,
+    -2.619_083_840_158_140_8e-5,
+    3.689_918_265_953_162_5e-6,
+];
+
+/// Natural logarithm of the gamma function (Lanczos approximation, same coefficients as the `gamma` npm package).
+pub fn gammaln(x: f64) -> f64 {
+    if x < 0.0 {
+        return f64::NAN;
+    }
+    let mut a = GAMMA_LN_COEFFICIENTS[0];
+    for i in (1..GAMMA_LN_COEFFICIENTS.len()).rev() {
+        a += GAMMA_LN_COEFFICIENTS[i] / (x + i as f64);
+    }
+    let t = x + 4.742_187_5 + 0.5;
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + a.ln() - x.ln()
+}
+
+/// Natural logarithm of the beta function.
+pub fn betaln(a: f64, b: f64) -> f64 {
+    gammaln(a) + gammaln(b) - gammaln(a + b)
+}
+
+/// Returns `(ln|sum(b_i * exp(a_i))|, sign)`.
+fn logsumexp(a: &[f64], b: &[f64]) -> (f64, f64) {
+    let max = a.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let mut sum = 0.0;
+    for i in (0..a.len()).rev() {
+        sum += b[i] * (a[i] - max).exp();
+    }
+    let sign = if sum > 0.0 {
+        1.0
+    } else if sum < 0.0 {
+        -1.0
+    } else {
+        0.0
+    };
+    ((sum * sign).ln() + max, sign)
+}
+
+fn mean_var_to_beta(mean: f64, var: f64) -> (f64, f64) {
+    let tmp = mean * (1.0 - mean) / var - 1.0;
+    (mean * tmp, (1.0 - mean) * tmp)
+}
+
+/// The model ebisu.js would create via `defaultModel(t, a, b)`.
+pub fn default_model(t: f64, a: f64, b: f64) -> EbisuModel {
+    [a, b, t]
+}
+
+/// Log-probability (or probability if `exact`) of recall `tnow` after the last review.
+pub fn predict_recall(prior: &EbisuModel, tnow: f64, exact: bool) -> f64 {
+    let [alpha, beta, t] = *prior;
+    let dt = tnow / t;
+    let ret = gammaln(alpha + dt) - gammaln(alpha + dt + beta) + gammaln(alpha + beta) - gammaln(alpha);
+    if exact {
+        ret.exp()
+    } else {
+        ret
+    }
+}
+
+/// Updates the model after a quiz with `successes` out of `total` trials, `tnow` after the last review.
+///
+/// `rebalance` moves the model's time horizon close to its half-life when alpha and beta drift apart.
+pub fn update_recall(
+    prior: &EbisuModel,
+    successes: u32,
+    total: u32,
+    tnow: f64,
+    rebalance: bool,
+    tback: Option<f64>,
+) -> Result<EbisuModel, EbisuError> {
+    let [alpha, beta, t] = *prior;
+    let tback = tback.unwrap_or(t);
+    let dt = tnow / t;
+    let et = tback / tnow;
+    let n = total.saturating_sub(successes);
+
+    let binomlns: Vec<f64> = (0..=n)
+        .map(|i| -betaln(1.0 + (n - i) as f64, 1.0 + i as f64) - ((n + 1) as f64).ln())
+        .collect();
+    let signs: Vec<f64> = (0..=n)
+        .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+        .collect();
+
+    let logs: Vec<f64> = (0..3)
+        .map(|m| {
+            let a: Vec<f64> = (0..=n)
+                .map(|i| {
+                    binomlns[i as usize]
+                        + betaln(
+                            beta,
+                            alpha + dt * (successes + i) as f64 + m as f64 * dt * et,
+                        )
+                })
+                .collect();
+            logsumexp(&a, &signs).0
+        })
+        .collect();
+
+    let mean = (logs[1] - logs[0]).exp();
+    let m2 = (logs[2] - logs[0]).exp();
+    let sig2 = m2 - (2.0 * (logs[1] - logs[0])).exp();
+
+    if ![mean, m2, sig2].iter().all(|x| x.is_finite() && *x >= 0.0) {
+        return Err(EbisuError::NumericalInstability);
+    }
+
+    let (new_alpha, new_beta) = mean_var_to_beta(mean, sig2);
+    let proposed = [new_alpha, new_beta, tback];
+
+    if rebalance && (new_alpha > 2.0 * new_beta || new_beta > 2.0 * new_alpha) {
+        let rough_half_life = model_to_percentile_decay(&proposed, 0.5, true, 1e-4)?;
+        return update_recall(prior, successes, total, tnow, false, Some(rough_half_life));
+    }
+    Ok(proposed)
+}
+
+/// Updates the model after a noisy-binary quiz (ebisu v2.1), `result` between 0.0 and 1.0.
+///
+/// A result above 0.5 counts as a success, the distance from 0.5 expresses how sure we are about it.
+/// For results of exactly 0.0 and 1.0 this is the same as `update_recall` with a single trial.
+pub fn update_recall_fractional(
+    prior: &EbisuModel,
+    result: f64,
+    tnow: f64,
+    rebalance: bool,
+    tback: Option<f64>,
+) -> Result<EbisuModel, EbisuError> {
+    let [alpha, beta, t] = *prior;
+    let tback = tback.unwrap_or(t);
+    let z = result > 0.5;
+    let q1 = if z { result } else { 1.0 - result };
+    let q0 = 1.0 - q1;
+    let dt = tnow / t;
+    let et = tback / tnow;
+
+    let (c, d) = if z { (q1 - q0, q0) } else { (q0 - q1, 1.0 - q0) };
+    let weights = [c, d];
+
+    let log_den = logsumexp(&[betaln(alpha + dt, beta), betaln(alpha, beta)], &weights).0;
+    let log_moment = |n: f64| {
+        logsumexp(
+            &[
+                betaln(alpha + dt + n * dt * et, beta),
+                betaln(alpha + n * dt * et, beta),
+            ],
+            &weights,
+        )
+        .0 - log_den
+    };
+
+    let mean = log_moment(1.0).exp();
+    let m2 = log_moment(2.0).exp();
+    let sig2 = m2 - mean * mean;
+
+    if ![mean, m2, sig2].iter().all(|x| x.is_finite() && *x >= 0.0) {
+        return Err(EbisuError::NumericalInstability);
+    }
+
+    let (new_alpha, new_beta) = mean_var_to_beta(mean, sig2);
+    let proposed = [new_alpha, new_beta, tback];
+
+    if rebalance && (new_alpha > 2.0 * new_beta || new_beta > 2.0 * new_alpha) {
+        let rough_half_life = model_to_percentile_decay(&proposed, 0.5, true, 1e-4)?;
+        return update_recall_fractional(prior, result, tnow, false, Some(rough_half_life));
+    }
+    Ok(proposed)
+}
+
+/// Maps the recall quality of a review onto the result of a noisy-binary quiz.
+///
+/// Passing scores (from PASSING_SCORE, as in is_success) become 0.75 to 1.0 and failing ones 0.0 to 0.25,
+/// a review that barely passed still is a clear success for EBISU instead of a coin toss around 0.5.
+pub fn quiz_result(score: f64) -> f64 {
+    let score = score.clamp(0.0, 1.0);
+    if is_success(score) {
+        0.75 + 0.25 * (score - PASSING_SCORE) / (1.0 - PASSING_SCORE)
+    } else {
+        0.25 * score / PASSING_SCORE
+    }
+}
+
+/// Time after the last review at which the recall probability drops to `percentile`.
+pub fn model_to_percentile_decay(
+    model: &EbisuModel,
+    percentile: f64,
+    coarse: bool,
+    tolerance: f64,
+) -> Result<f64, EbisuError> {
+    if !(0.0..=1.0).contains(&percentile) {
+        return Err(EbisuError::InvalidPercentile);
+    }
+    let [alpha, beta, t0] = *model;
+    let log_bab = betaln(alpha, beta);
+    let log_percentile = percentile.ln();
+    let f = |lndelta: f64| betaln(alpha + lndelta.exp(), beta) - log_bab - log_percentile;
+
+    let bracket_width = if coarse { 1.0 } else { 6.0 };
+    let mut blow = -bracket_width / 2.0;
+    let mut bhigh = bracket_width / 2.0;
+    let mut flow = f(blow);
+    let mut fhigh = f(bhigh);
+    while flow > 0.0 && fhigh > 0.0 {
+        blow = bhigh;
+        flow = fhigh;
+        bhigh += bracket_width;
+        fhigh = f(bhigh);
+    }
+    while flow < 0.0 && fhigh < 0.0 {
+        bhigh = blow;
+        fhigh = flow;
+        blow -= bracket_width;
+        flow = f(blow);
+    }
+    if !(flow > 0.0 && fhigh < 0.0) {
+        return Err(EbisuError::FailedToBracket);
+    }
+    if coarse {
+        return Ok((blow.exp() + bhigh.exp()) / 2.0 * t0);
+    }
+    match golden_section_minimize(|x| f(x).abs(), blow, bhigh, tolerance, 100) {
+        Some(sol) => Ok(sol.exp() * t0),
+        None => Err(EbisuError::FailedToConverge),
+    }
+}
+
+/// Port of `minimize-golden-section-1d` with fixed bounds. Returns `None` if it did not converge.
+fn golden_section_minimize<F>(
+    f: F,
+    lower: f64,
+    upper: f64,
+    tolerance: f64,
+    max_iterations: usize,
+) -> Option<f64>
+where
+    F: Fn(f64) -> f64,
+{
+    let phi_ratio = 2.0 / (1.0 + 5f64.sqrt());
+    let (mut xl, mut xu) = (lower, upper);
+    let mut iteration = 0;
+    let mut x1 = xu - phi_ratio * (xu - xl);
+    let mut x2 = xl + phi_ratio * (xu - xl);
+    let mut f1 = f(x1);
+    let mut f2 = f(x2);
+    let fl = f(xl);
+    let fu = f(xu);
+
+    loop {
+        iteration += 1;
+        if !(iteration < max_iterations && (xu - xl).abs() > tolerance) {
+            break;
+        }
+        if f2 > f1 {
+            xu = x2;
+            x2 = x1;
+            f2 = f1;
+            x1 = xu - phi_ratio * (xu - xl);
+            f1 = f(x1);
+        } else {
+            xl = x1;
+            x1 = x2;
+            f1 = f2;
+            x2 = xl + phi_ratio * (xu - xl);
+            f2 = f(x2);
+        }
+    }
+
+    let xf = 0.5 * (xu + xl);
+    let ff = 0.5 * (f1 + f2);
+
+    if f2.is_nan() || f1.is_nan() || iteration == max_iterations {
+        return None;
+    }
+    if fl < ff {
+        Some(lower)
+    } else if fu < ff {
+        Some(upper)
+    } else {
+        Some(xf)
+    }
+}
+
+pub struct EbisuScheduler {
+    model_dict: HashMap<String, EbisuModel>,
+    default_half_life_time: f64,
+}
+
+impl Default for EbisuScheduler {
+    fn default() -> Self {
+        EbisuScheduler::new()
+    }
+}
+
+impl EbisuScheduler {
+    pub fn new() -> EbisuScheduler {
+        EbisuScheduler {
+            model_dict: HashMap::new(),
+            default_half_life_time: 1000.0 * 60.0 * 30.0,
+        }
+    }
+
+    fn transform_ebisu_model(
+        &self,
+        ebisu_model: Option<EbisuModel>,
+        prev_timestamp: f64,
+        timestamp: f64,
+        score: f64,
+    ) -> EbisuModel {
+        match ebisu_model {
+            None => default_model(self.default_half_life_time, 3.0, 3.0),
+            Some(model) => {
+                let elapsed_time = timestamp - prev_timestamp; // assertion: timestamp >= prev_timestamp
+                update_recall_fractional(&model, quiz_result(score), elapsed_time, true, None)
+                    .unwrap_or(model)
+            }
+        }
+    }
+
+    fn get_ebisu_model(&mut self, card_key: CardKey, timestamps: &[f64], scores: &[f64]) -> EbisuModel {
+        // the last timestamp tells apart histories of the same length, e.g. after a checkpoint was loaded.
+        let key = format!(
+            "EBISU_{}_{:?}_{}_{}",
+            card_key.0,
+            card_key.1,
+            timestamps.len(),
+            timestamps.last().copied().unwrap_or(0.0)
+        );
+
+        if let Some(model) = self.model_dict.get(&key) {
+            return *model;
+        }
+        // first creation of model
+        let mut model = self.transform_ebisu_model(None, 0.0, 0.0, scores[0]);
+        for t in 1..timestamps.len().min(scores.len()) {
+            model = self.transform_ebisu_model(
+                Some(model),
+                timestamps[t - 1],
+                timestamps[t],
+                scores[t],
+            );
+        }
+        // save model
+        self.model_dict.insert(key, model);
+        model
+    }
+}
+
+impl Scheduler for EbisuScheduler {
+    /// The card is due once its recall probability has decayed to 50% (the model's half-life).
+    fn schedule(&mut self, meta_data: &CardMetaData, now: f64) -> Schedule {
+        let timestamps = &meta_data.timestamps;
+        if timestamps.is_empty() || meta_data.scores.is_empty() {
+            return Schedule::new_card(now);
+        }
+        let last_timestamp = timestamps[timestamps.len() - 1];
+        let model = self.get_ebisu_model(meta_data.key(), timestamps, &meta_data.scores);
+        let half_life = model_to_percentile_decay(&model, 0.5, false, 1e-4)
+            .unwrap_or(self.default_half_life_time);
+
+        Schedule {
+            next_session_timestamp: last_timestamp + half_life,
+            recall_probability: predict_recall(&model, (now - last_timestamp).max(0.0), true),
+        }
+    }
+}
+
+// Reference values were produced by ebisu.js (assets/ebisu.min.es6.js before the port). That version predates
+// fractional quizzes, so those are checked against the ebisu 2.1 noisy-binary formulas evaluated with an exact lgamma.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HALF_HOUR: f64 = 1000.0 * 60.0 * 30.0;
+
+    fn assert_close(actual: f64, expected: f64, relative_tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= relative_tolerance * expected.abs().max(1.0),
+            "{} is not close to {}",
+            actual,
+            expected
+        );
+    }
+
+    fn assert_model_close(actual: EbisuModel, expected: EbisuModel, relative_tolerance: f64) {
+        for (actual, expected) in actual.iter().zip(expected.iter()) {
+            assert_close(*actual, *expected, relative_tolerance);
+        }
+    }
+
+    #[test]
+    fn gammaln_matches_exact_values() {
+        assert_close(gammaln(1.0), 0.0, 1e-12);
+        assert_close(gammaln(2.0), 0.0, 1e-12);
+        assert_close(gammaln(0.5), 0.572_364_942_924_700_4, 1e-12);
+        assert_close(gammaln(3.5), 1.200_973_602_347_073_8, 1e-12);
+        assert_close(gammaln(1e-8), 18.420_680_738_180_21, 1e-12);
+        assert_close(gammaln(170.5), 704.004_427_734_204_2, 1e-12);
+        assert_close(gammaln(1000.0), 5_905.220_423_209_181, 1e-12);
+    }
+
+    #[test]
+    fn gammaln_outside_its_domain() {
+        assert!(gammaln(-1.0).is_nan());
+        assert_eq!(gammaln(0.0), f64::INFINITY);
+    }
+
+    #[test]
+    fn betaln_matches_exact_values() {
+        assert_close(betaln(1.0, 1.0), 0.0, 1e-12);
+        assert_close(betaln(0.5, 0.5), PI.ln(), 1e-12);
+        assert_close(betaln(3.0, 3.0), -3.401_197_381_662_157, 1e-12);
+        assert_close(betaln(1e-6, 1e-6), 14.508_657_738_522_572, 1e-12);
+        assert_close(betaln(200.0, 300.0), -337.980_113_065_464_6, 1e-12);
+    }
+
+    #[test]
+    fn predict_recall_matches_ebisu_js() {
+        let model = default_model(HALF_HOUR, 3.0, 3.0);
+        assert_close(predict_recall(&model, 2.0 * HALF_HOUR, false), -1.252_762_968_495_367_8, 1e-9);
+        assert_close(predict_recall(&model, 2.0 * HALF_HOUR, true), 0.285_714_285_714_285_75, 1e-9);
+        assert_close(predict_recall(&[4.0, 2.0, 10.0], 3.0, true), 0.877_577_885_037_295_8, 1e-9);
+    }
+
+    #[test]
+    fn update_recall_success_matches_ebisu_js() {
+        let model = default_model(HALF_HOUR, 3.0, 3.0);
+        let updated = update_recall(&model, 1, 1, 2.0 * HALF_HOUR, true, None).unwrap();
+        assert_model_close(updated, [5.000_000_000_000_07, 3.000_000_000_000_029_3, HALF_HOUR], 1e-9);
+    }
+
+    #[test]
+    fn update_recall_failure_matches_ebisu_js() {
+        let model = default_model(HALF_HOUR, 3.0, 3.0);
+        let updated = update_recall(&model, 0, 1, 2.0 * HALF_HOUR, true, None).unwrap();
+        assert_model_close(updated, [3.162_162_162_162_065_5, 3.864_864_864_864_760_6, HALF_HOUR], 1e-9);
+    }
+
+    #[test]
+    fn update_recall_binomial_matches_ebisu_js() {
+        let updated = update_recall(&[4.0, 4.0, 10.0], 2, 3, 20.0, true, None).unwrap();
+        assert_model_close(updated, [8.269_662_921_348_61, 4.943_820_224_719_355, 10.0], 1e-9);
+    }
+
+    #[test]
+    fn update_recall_fractional_matches_noisy_binary_formulas() {
+        let model = default_model(HALF_HOUR, 3.0, 3.0);
+        let cases = [
+            (0.9, [3.819_512_195_121_994_6, 2.687_804_878_048_794_4]),
+            (0.75, [3.230_769_230_769_308_6, 2.692_307_692_307_745_4]),
+            (0.3, [3.017_518_248_175_235, 3.327_007_299_270_128]),
+            (0.1, [3.104_142_011_834_378_8, 3.681_656_804_733_798_5]),
+        ];
+        for (result, [alpha, beta]) in cases {
+            let updated = update_recall_fractional(&model, result, 2.0 * HALF_HOUR, true, None).unwrap();
+            assert_model_close(updated, [alpha, beta, HALF_HOUR], 1e-9);
+        }
+        let updated = update_recall_fractional(&[4.0, 2.0, 10.0], 0.75, 5.0, false, None).unwrap();
+        assert_model_close(updated, [4.274_417_084_166_35, 1.988_403_769_788_450_7, 10.0], 1e-9);
+    }
+
+    #[test]
+    fn update_recall_fractional_with_certain_results_is_binary() {
+        let model = default_model(HALF_HOUR, 3.0, 3.0);
+        for (result, successes) in [(1.0, 1), (0.0, 0)] {
+            let fractional = update_recall_fractional(&model, result, 2.0 * HALF_HOUR, true, None).unwrap();
+            let binary = update_recall(&model, successes, 1, 2.0 * HALF_HOUR, true, None).unwrap();
+            assert_model_close(fractional, binary, 1e-9);
+        }
+    }
+
+    #[test]
+    fn model_to_percentile_decay_matches_ebisu_js() {
+        let model = default_model(HALF_HOUR, 3.0, 3.0);
+        assert_close(model_to_percentile_decay(&model, 0.5, false, 1e-4).unwrap(), 1_799_967.811_638_478_7, 1e-9);
+        assert_close(model_to_percentile_decay(&model, 0.5, true, 1e-4).unwrap(), 2_029_726.737_371_485_2, 1e-9);
+        assert_close(
+            model_to_percentile_decay(&[4.0, 2.0, 10.0], 0.8, false, 1e-4).unwrap(),
+            5.249_266_568_347_405,
+            1e-9,
+        );
+    }
+
+    #[test]
+    fn quiz_result_passes_from_the_passing_score() {
+        assert_eq!(quiz_result(PASSING_SCORE), 0.75);
+        assert!(quiz_result(PASSING_SCORE - 1e-9) < 0.25);
+        assert_eq!(quiz_result(1.0), 1.0);
+        assert_eq!(quiz_result(0.0), 0.0);
+        assert_eq!(quiz_result(1.5), 1.0);
+    }
+
+    #[test]
+    fn a_review_at_the_passing_score_lengthens_the_half_life() {
+        let model = default_model(HALF_HOUR, 3.0, 3.0);
+        let half_life = |model: &EbisuModel| model_to_percentile_decay(model, 0.5, false, 1e-4).unwrap();
+        let passed =
+            update_recall_fractional(&model, quiz_result(PASSING_SCORE), 2.0 * HALF_HOUR, true, None).unwrap();
+        let failed =
+            update_recall_fractional(&model, quiz_result(PASSING_SCORE - 0.01), 2.0 * HALF_HOUR, true, None)
+                .unwrap();
+        assert!(half_life(&passed) > half_life(&model));
+        assert!(half_life(&failed) < half_life(&model));
+    }
+
+    #[test]
+    fn model_to_percentile_decay_rejects_invalid_percentiles() {
+        let model = default_model(HALF_HOUR, 3.0, 3.0);
+        assert_eq!(
+            model_to_percentile_decay(&model, 1.5, false, 1e-4),
+            Err(EbisuError::InvalidPercentile)
+        );
+    }
+}
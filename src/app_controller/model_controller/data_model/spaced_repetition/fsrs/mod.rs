@@ -0,0 +1,445 @@
+// Free Spaced Repetition Scheduler, FSRS v4 (https://github.com/open-spaced-repetition/fsrs4anki/wiki/The-Algorithm)
+//
+// The memory state of a card is its stability S (days until recall drops to 90%) and its difficulty D (1-10).
+
+use super::{is_success, Grade, Schedule, Scheduler, ONE_DAY};
+use crate::app_controller::model_controller::data_model::card_model::CardMetaData;
+
+pub const DEFAULT_PARAMETERS: [f64; 17] = [
+    0.4, 0.6, 2.4, 5.8, 4.93, 0.94, 0.86, 0.01, 1.49, 0.14, 0.94, 2.18, 0.05, 0.34, 1.26, 0.29, 2.61,
+];
+
+const PARAMETER_BOUNDS: [(f64, f64); 17] = [
+    (0.1, 100.0),
+    (0.1, 100.0),
+    (0.1, 100.0),
+    (0.1, 100.0),
+    (1.0, 10.0),
+    (0.1, 5.0),
+    (0.1, 5.0),
+    (0.0, 0.5),
+    (0.0, 3.0),
+    (0.1, 0.8),
+    (0.01, 2.5),
+    (0.5, 5.0),
+    (0.01, 0.2),
+    (0.01, 0.9),
+    (0.01, 2.0),
+    (0.0, 1.0),
+    (1.0, 4.0),
+];
+
+const MINIMUM_STABILITY: f64 = 0.01;
+
+/// Again = 1, Hard = 2, Good = 3, Easy = 4
+fn grade(score: f64) -> f64 {
+    match Grade::from_score(score) {
+        Grade::Again => 1.0,
+        Grade::Hard => 2.0,
+        Grade::Good => 3.0,
+        Grade::Easy => 4.0,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MemoryState {
+    stability: f64,
+    difficulty: f64,
+}
+
+/// Probability of recall `elapsed_days` after the last review.
+fn retrievability(elapsed_days: f64, stability: f64) -> f64 {
+    (1.0 + elapsed_days / (9.0 * stability)).powi(-1)
+}
+
+fn initial_difficulty(w: &[f64], grade: f64) -> f64 {
+    (w[4] - (grade - 3.0) * w[5]).clamp(1.0, 10.0)
+}
+
+fn initial_state(w: &[f64], grade: f64) -> MemoryState {
+    MemoryState {
+        stability: w[grade as usize - 1].max(MINIMUM_STABILITY),
+        difficulty: initial_difficulty(w, grade),
+    }
+}
+
+fn next_state(w: &[f64], state: MemoryState, retrievability: f64, grade: f64) -> MemoryState {
+    let MemoryState {
+        stability: s,
+        difficulty: d,
+    } = state;
+
+    let next_difficulty = d - w[6] * (grade - 3.0);
+    let next_difficulty =
+        (w[7] * initial_difficulty(w, 3.0) + (1.0 - w[7]) * next_difficulty).clamp(1.0, 10.0);
+
+    let next_stability = if grade > 1.0 {
+        let hard_penalty = if grade == 2.0 { w[15] } else { 1.0 };
+        let easy_bonus = if grade == 4.0 { w[16] } else { 1.0 };
+        s * (w[8].exp()
+            * (11.0 - d)
+            * s.powf(-w[9])
+            * ((w[10] * (1.0 - retrievability)).exp() - 1.0)
+            * hard_penalty
+            * easy_bonus
+            + 1.0)
+    } else {
+        w[11] * d.powf(-w[12]) * ((s + 1.0).powf(w[13]) - 1.0) * (w[14] * (1.0 - retrievability)).exp()
+    };
+
+    MemoryState {
+        stability: next_stability.max(MINIMUM_STABILITY),
+        difficulty: next_difficulty,
+    }
+}
+
+/// Replays the review history and returns the memory state after the last review.
+fn memory_state(w: &[f64], timestamps: &[f64], scores: &[f64]) -> Option<MemoryState> {
+    let reviews = timestamps.len().min(scores.len());
+    if reviews == 0 {
+        return None;
+    }
+    let mut state = initial_state(w, grade(scores[0]));
+    for i in 1..reviews {
+        let elapsed_days = (timestamps[i] - timestamps[i - 1]).max(0.0) / ONE_DAY;
+        state = next_state(
+            w,
+            state,
+            retrievability(elapsed_days, state.stability),
+            grade(scores[i]),
+        );
+    }
+    Some(state)
+}
+
+pub struct FSRSScheduler {
+    parameters: Vec<f64>,
+    desired_retention: f64,
+}
+
+impl Default for FSRSScheduler {
+    fn default() -> Self {
+        FSRSScheduler::new(&DEFAULT_PARAMETERS)
+    }
+}
+
+impl FSRSScheduler {
+    pub fn new(parameters: &[f64]) -> FSRSScheduler {
+        FSRSScheduler {
+            parameters: if parameters.len() == DEFAULT_PARAMETERS.len() {
+                parameters.to_vec()
+            } else {
+                DEFAULT_PARAMETERS.to_vec()
+            },
+            desired_retention: 0.9,
+        }
+    }
+}
+
+impl Scheduler for FSRSScheduler {
+    fn schedule(&mut self, meta_data: &CardMetaData, now: f64) -> Schedule {
+        match memory_state(&self.parameters, &meta_data.timestamps, &meta_data.scores) {
+            None => Schedule::new_card(now),
+            Some(state) => {
+                let last_timestamp = meta_data.timestamps[meta_data.timestamps.len() - 1];
+                let interval_days = 9.0 * state.stability * (1.0 / self.desired_retention - 1.0);
+                Schedule {
+                    next_session_timestamp: last_timestamp + interval_days * ONE_DAY,
+                    recall_probability: retrievability(
+                        (now - last_timestamp).max(0.0) / ONE_DAY,
+                        state.stability,
+                    ),
+                }
+            }
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct FSRSOptimizerResult {
+    pub parameters: Vec<f64>,
+    pub review_count: usize,
+    pub initial_log_loss: f64,
+    pub log_loss: f64,
+    /// Mean predicted probability of recall over all reviews in the history.
+    pub predicted_retention: f64,
+    /// Share of reviews in the history that were answered correctly.
+    pub actual_retention: f64,
+    pub progress: f32,
+}
+
+/// Fits the FSRS parameters to the review history with Adam on a numerical gradient of the log loss.
+///
+/// The work is split into `step` calls so it can run across frames without blocking the UI.
+pub struct FSRSOptimizer {
+    histories: Vec<(Vec<f64>, Vec<f64>)>,
+    parameters: Vec<f64>,
+    first_moment: Vec<f64>,
+    second_moment: Vec<f64>,
+    iteration: usize,
+    max_iterations: usize,
+    learning_rate: f64,
+    initial_log_loss: f64,
+}
+
+impl FSRSOptimizer {
+    /// Returns None if there are no reviews following an earlier review to learn from.
+    pub fn new(histories: Vec<(Vec<f64>, Vec<f64>)>, parameters: &[f64]) -> Option<FSRSOptimizer> {
+        let histories: Vec<(Vec<f64>, Vec<f64>)> = histories
+            .into_iter()
+            .filter(|(timestamps, scores)| timestamps.len().min(scores.len()) > 1)
+            .collect();
+        if histories.is_empty() {
+            return None;
+        }
+        let parameters = FSRSScheduler::new(parameters).parameters;
+        let mut optimizer = FSRSOptimizer {
+            histories,
+            first_moment: vec![0.0; parameters.len()],
+            second_moment: vec![0.0; parameters.len()],
+            parameters,
+            iteration: 0,
+            max_iterations: 300,
+            learning_rate: 4e-2,
+            initial_log_loss: 0.0,
+        };
+        optimizer.initial_log_loss = optimizer.evaluate(&optimizer.parameters).0;
+        Some(optimizer)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.iteration >= self.max_iterations
+    }
+
+    /// Returns `(log loss, predicted retention, actual retention, review count)`.
+    fn evaluate(&self, w: &[f64]) -> (f64, f64, f64, usize) {
+        let mut log_loss = 0.0;
+        let mut predicted = 0.0;
+        let mut actual = 0.0;
+        let mut count = 0;
+
+        for (timestamps, scores) in &self.histories {
+            let reviews = timestamps.len().min(scores.len());
+            let mut state = initial_state(w, grade(scores[0]));
+            for i in 1..reviews {
+                let elapsed_days = (timestamps[i] - timestamps[i - 1]).max(0.0) / ONE_DAY;
+                let r = retrievability(elapsed_days, state.stability).clamp(1e-4, 1.0 - 1e-4);
+                let y = if is_success(scores[i]) { 1.0 } else { 0.0 };
+
+                log_loss -= y * r.ln() + (1.0 - y) * (1.0 - r).ln();
+                predicted += r;
+                actual += y;
+                count += 1;
+
+                state = next_state(w, state, r, grade(scores[i]));
+            }
+        }
+        let n = count.max(1) as f64;
+        (log_loss / n, predicted / n, actual / n, count)
+    }
+
+    /// Runs up to `iterations` optimisation steps.
+    pub fn step(&mut self, iterations: usize) {
+        let (beta1, beta2, epsilon, h) = (0.9, 0.999, 1e-8, 1e-4);
+
+        for _ in 0..iterations {
+            if self.is_done() {
+                return;
+            }
+            self.iteration += 1;
+
+            let mut gradient = vec![0.0; self.parameters.len()];
+            for k in 0..self.parameters.len() {
+                let mut w = self.parameters.clone();
+                w[k] = self.parameters[k] + h;
+                let upper = self.evaluate(&w).0;
+                w[k] = self.parameters[k] - h;
+                let lower = self.evaluate(&w).0;
+                gradient[k] = (upper - lower) / (2.0 * h);
+            }
+
+            for k in 0..self.parameters.len() {
+                self.first_moment[k] = beta1 * self.first_moment[k] + (1.0 - beta1) * gradient[k];
+                self.second_moment[k] =
+                    beta2 * self.second_moment[k] + (1.0 - beta2) * gradient[k] * gradient[k];
+                let m_hat = self.first_moment[k] / (1.0 - f64::powi(beta1, self.iteration as i32));
+                let v_hat = self.second_moment[k] / (1.0 - f64::powi(beta2, self.iteration as i32));
+                let (min, max) = PARAMETER_BOUNDS[k];
+                self.parameters[k] = (self.parameters[k]
+                    - self.learning_rate * m_hat / (v_hat.sqrt() + epsilon))
+                    .clamp(min, max);
+            }
+        }
+    }
+
+    pub fn result(&self) -> FSRSOptimizerResult {
+        let (log_loss, predicted_retention, actual_retention, review_count) =
+            self.evaluate(&self.parameters);
+        FSRSOptimizerResult {
+            parameters: self.parameters.clone(),
+            review_count,
+            initial_log_loss: self.initial_log_loss,
+            log_loss,
+            predicted_retention,
+            actual_retention,
+            progress: self.iteration as f32 / self.max_iterations as f32,
+        }
+    }
+}
+
+// Reference values follow the formulas of the FSRS v4 wiki page with DEFAULT_PARAMETERS, evaluated in double precision.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: [f64; 17] = DEFAULT_PARAMETERS;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= 1e-9 * expected.abs().max(1.0),
+            "{} is not close to {}",
+            actual,
+            expected
+        );
+    }
+
+    fn meta_data(timestamps: &[f64], scores: &[f64]) -> CardMetaData {
+        serde_json::from_value(serde_json::json!({
+            "id": "deck/1",
+            "timestamps": timestamps,
+            "scores": scores,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn retrievability_is_90_percent_after_stability_days() {
+        assert_close(retrievability(0.0, 2.4), 1.0);
+        assert_close(retrievability(2.4, 2.4), 0.9);
+        assert_close(retrievability(3.0, 2.4), 0.878_048_780_487_804_9);
+    }
+
+    #[test]
+    fn initial_state_follows_the_first_grade() {
+        let difficulties: Vec<f64> = [1.0, 2.0, 3.0, 4.0]
+            .iter()
+            .map(|grade| initial_state(&W, *grade).difficulty)
+            .collect();
+        for (actual, expected) in difficulties.iter().zip([6.81, 5.87, 4.93, 3.99]) {
+            assert_close(*actual, expected);
+        }
+        let stabilities: Vec<f64> = [1.0, 2.0, 3.0, 4.0]
+            .iter()
+            .map(|grade| initial_state(&W, *grade).stability)
+            .collect();
+        assert_eq!(stabilities, vec![0.4, 0.6, 2.4, 5.8]);
+        // D0 = w4 - (G - 3) * w5 is kept within 1 and 10.
+        let mut w = W;
+        w[5] = 5.0;
+        assert_eq!(initial_difficulty(&w, 1.0), 10.0);
+        assert_eq!(initial_difficulty(&w, 4.0), 1.0);
+    }
+
+    #[test]
+    fn next_state_after_a_successful_review() {
+        let state = initial_state(&W, 3.0);
+        let r = retrievability(3.0, state.stability);
+        for (grade, stability, difficulty) in [
+            (2.0, 4.414_240_818_867_593, 5.7814),
+            (3.0, 9.345_657_996_095_147, 4.93),
+            (4.0, 20.528_167_369_808_333, 4.0786),
+        ] {
+            let next = next_state(&W, state, r, grade);
+            assert_close(next.stability, stability);
+            assert_close(next.difficulty, difficulty);
+        }
+    }
+
+    #[test]
+    fn next_state_after_a_lapse() {
+        let state = initial_state(&W, 3.0);
+        let next = next_state(&W, state, retrievability(3.0, state.stability), 1.0);
+        assert_close(next.stability, 1.211_176_874_484_292);
+        assert_close(next.difficulty, 6.6328);
+    }
+
+    #[test]
+    fn interval_reaches_the_desired_retention() {
+        // Good, Good after 3 days, Again after 10 days: S = 2.4, 9.3457, 2.7918.
+        let timestamps = [0.0, 3.0 * ONE_DAY, 13.0 * ONE_DAY];
+        let scores = [Grade::Good.score(), Grade::Good.score(), Grade::Again.score()];
+        let state = memory_state(&W, &timestamps, &scores).unwrap();
+        assert_close(state.stability, 2.791_814_666_808_358_8);
+        assert_close(state.difficulty, 6.6328);
+
+        // I(r, S) = 9 * S * (1 / r - 1) equals S for r = 90%.
+        let mut scheduler = FSRSScheduler::default();
+        let now = 14.0 * ONE_DAY;
+        let schedule = scheduler.schedule(&meta_data(&timestamps, &scores), now);
+        assert_close(
+            (schedule.next_session_timestamp - timestamps[2]) / ONE_DAY,
+            2.791_814_666_808_358_8,
+        );
+        assert_close(schedule.recall_probability, retrievability(1.0, state.stability));
+
+        scheduler.desired_retention = 0.8;
+        let schedule = scheduler.schedule(&meta_data(&timestamps[..2], &scores[..2]), now);
+        assert_close(
+            (schedule.next_session_timestamp - timestamps[1]) / ONE_DAY,
+            21.027_730_491_214_08,
+        );
+    }
+
+    #[test]
+    fn new_cards_are_due_right_away() {
+        let schedule = FSRSScheduler::default().schedule(&meta_data(&[], &[]), 5.0);
+        assert_eq!(schedule, Schedule::new_card(5.0));
+    }
+
+    /// Cards reviewed every `gap` days, failed at every fifth review of every third card.
+    fn synthetic_histories() -> Vec<(Vec<f64>, Vec<f64>)> {
+        (0..60)
+            .map(|card| {
+                let gap = 2.0 + (card % 7) as f64 * 3.0;
+                let timestamps = (0..8).map(|review| review as f64 * gap * ONE_DAY).collect();
+                let scores = (0..8)
+                    .map(|review| {
+                        if card % 3 == 0 && review % 5 == 4 {
+                            Grade::Again.score()
+                        } else {
+                            Grade::Good.score()
+                        }
+                    })
+                    .collect();
+                (timestamps, scores)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn optimizer_lowers_the_log_loss() {
+        let mut optimizer = FSRSOptimizer::new(synthetic_histories(), &DEFAULT_PARAMETERS).unwrap();
+        optimizer.step(40);
+        let result = optimizer.result();
+        assert!(
+            result.log_loss < result.initial_log_loss,
+            "{} is not below {}",
+            result.log_loss,
+            result.initial_log_loss
+        );
+        assert_eq!(result.review_count, 60 * 7);
+        assert!(result.parameters.iter().all(|parameter| parameter.is_finite()));
+        for (parameter, (min, max)) in result.parameters.iter().zip(PARAMETER_BOUNDS) {
+            assert!((min..=max).contains(parameter));
+        }
+        assert!(!optimizer.is_done());
+        optimizer.step(1000);
+        assert!(optimizer.is_done());
+        assert_eq!(optimizer.result().progress, 1.0);
+    }
+
+    #[test]
+    fn optimizer_needs_repeated_reviews() {
+        assert!(FSRSOptimizer::new(vec![(vec![0.0], vec![1.0])], &DEFAULT_PARAMETERS).is_none());
+    }
+}
@@ -0,0 +1,94 @@
+// Leitner system: a correct answer moves the card up one box, a mistake moves it back to the first box.
+
+use super::{is_success, Schedule, Scheduler, ONE_DAY};
+use crate::app_controller::model_controller::data_model::card_model::CardMetaData;
+
+pub struct LeitnerScheduler {
+    box_intervals: Vec<f64>,
+}
+
+impl Default for LeitnerScheduler {
+    fn default() -> Self {
+        LeitnerScheduler {
+            box_intervals: vec![
+                ONE_DAY,
+                2.0 * ONE_DAY,
+                4.0 * ONE_DAY,
+                8.0 * ONE_DAY,
+                16.0 * ONE_DAY,
+                32.0 * ONE_DAY,
+            ],
+        }
+    }
+}
+
+impl LeitnerScheduler {
+    /// Replays the review history and returns the index of the box the card is in.
+    fn box_index(&self, scores: &[f64]) -> usize {
+        let last_box = self.box_intervals.len() - 1;
+        scores.iter().fold(0, |index, score| {
+            if is_success(*score) {
+                (index + 1).min(last_box)
+            } else {
+                0
+            }
+        })
+    }
+}
+
+impl Scheduler for LeitnerScheduler {
+    fn schedule(&mut self, meta_data: &CardMetaData, now: f64) -> Schedule {
+        match meta_data.timestamps.last() {
+            None => Schedule::new_card(now),
+            Some(last_timestamp) => Schedule::from_interval(
+                *last_timestamp,
+                self.box_intervals[self.box_index(&meta_data.scores)],
+                now,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AGAIN: f64 = 0.0;
+    const GOOD: f64 = 0.8;
+
+    fn box_index(scores: &[f64]) -> usize {
+        LeitnerScheduler::default().box_index(scores)
+    }
+
+    #[test]
+    fn correct_answers_promote_the_card() {
+        assert_eq!(box_index(&[]), 0);
+        assert_eq!(box_index(&[GOOD]), 1);
+        assert_eq!(box_index(&[GOOD, GOOD, GOOD]), 3);
+        // the last box keeps the card.
+        assert_eq!(box_index(&[GOOD; 10]), 5);
+    }
+
+    #[test]
+    fn a_mistake_demotes_the_card_to_the_first_box() {
+        assert_eq!(box_index(&[GOOD, GOOD, GOOD, GOOD, AGAIN]), 0);
+        let mut scores = vec![GOOD; 10];
+        scores.extend([AGAIN, GOOD]);
+        assert_eq!(box_index(&scores), 1);
+        // a score at the passing score counts as correct.
+        assert_eq!(box_index(&[0.5, 0.49]), 0);
+        assert_eq!(box_index(&[0.49, 0.5]), 1);
+    }
+
+    #[test]
+    fn the_box_decides_the_interval() {
+        let meta_data: CardMetaData = serde_json::from_value(serde_json::json!({
+            "id": "deck/1",
+            "timestamps": [0.0, ONE_DAY, 3.0 * ONE_DAY],
+            "scores": [GOOD, GOOD, GOOD],
+        }))
+        .unwrap();
+        let schedule = LeitnerScheduler::default().schedule(&meta_data, 3.0 * ONE_DAY);
+        assert_eq!(schedule.next_session_timestamp, 11.0 * ONE_DAY);
+    }
+}
@@ -1,41 +1,208 @@
+pub mod ebisu;
+pub mod fsrs;
+pub mod leitner;
+pub mod sm2;
+
 use crate::app_controller::model_controller::data_model::card_model::CardMetaData;
-use wasm_bindgen::prelude::*;
+use crate::app_controller::model_controller::data_model::typo_tolerance::Typos;
+use ebisu::EbisuScheduler;
+use fsrs::FSRSScheduler;
+use leitner::LeitnerScheduler;
+use sm2::SM2Scheduler;
+
+pub const ONE_DAY: f64 = 1000.0 * 60.0 * 60.0 * 24.0;
+
+/// The recall probability interval based schedulers aim for when a card becomes due.
+const TARGET_RECALL_PROBABILITY: f64 = 0.9;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    pub next_session_timestamp: f64,
+    pub recall_probability: f64,
+}
+
+impl Schedule {
+    /// A card without any reviews is due right away.
+    pub fn new_card(now: f64) -> Schedule {
+        Schedule {
+            next_session_timestamp: now,
+            recall_probability: 0.0,
+        }
+    }
 
-#[wasm_bindgen(module = "/defined-in-js.js")]
-extern "C" {
+    /// Assumes an exponential forgetting curve that reaches TARGET_RECALL_PROBABILITY after `interval`.
+    pub fn from_interval(last_timestamp: f64, interval: f64, now: f64) -> Schedule {
+        let elapsed = (now - last_timestamp).max(0.0);
+        Schedule {
+            next_session_timestamp: last_timestamp + interval,
+            recall_probability: TARGET_RECALL_PROBABILITY.powf(elapsed / interval),
+        }
+    }
+}
 
-    pub type SpacedRepetition;
+/// A review passed if its score is at least this value.
+pub const PASSING_SCORE: f64 = 0.5;
 
-    #[wasm_bindgen(constructor)]
-    fn new() -> SpacedRepetition;
+/// How much of the score above PASSING_SCORE is kept for each hint used.
+pub const HINT_FACTOR: f64 = 0.6;
+
+pub fn is_success(score: f64) -> bool {
+    score >= PASSING_SCORE
+}
 
-    #[wasm_bindgen(method)]
-    fn calculateEBISU(
-        this: &SpacedRepetition,
-        card_id: u16,
-        timestamps: Vec<f64>,
-        scores: Vec<js_sys::Boolean>,
-    ) -> f64;
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Grade {
+    pub fn from_score(score: f64) -> Grade {
+        if score < PASSING_SCORE {
+            Grade::Again
+        } else if score < 0.7 {
+            Grade::Hard
+        } else if score < 0.9 {
+            Grade::Good
+        } else {
+            Grade::Easy
+        }
+    }
+
+    pub fn score(&self) -> f64 {
+        match self {
+            Grade::Again => 0.0,
+            Grade::Hard => 0.6,
+            Grade::Good => 0.8,
+            Grade::Easy => 1.0,
+        }
+    }
+}
+
+/// Scores a typed answer: 0.0 if its typos are not within the tolerance, otherwise between PASSING_SCORE and 1.0.
+///
+/// The score drops with the weighted edit distance (normalised by the permitted mistakes) and with answers that took
+/// longer than the time needed to type the label at a relaxed pace.
+pub fn recall_quality(typos: &Typos, label_length: usize, answer_time: f64) -> f64 {
+    if !typos.within_tolerance {
+        return 0.0;
+    }
+    let accuracy = (1.0 - typos.distance / (typos.permitted + 1.0)).max(0.0);
+    let expected_answer_time = 3000.0 + 400.0 * label_length as f64;
+    let speed = (expected_answer_time / answer_time.max(1.0)).clamp(0.5, 1.0);
+
+    PASSING_SCORE + (1.0 - PASSING_SCORE) * accuracy * speed
+}
+
+/// Scores a multiple-choice answer, picking the right option is only a weak recall signal.
+///
+/// `weight` (0.0 to 1.0) is how much a correct choice counts compared to a perfect typed answer.
+pub fn recognition_quality(correct: bool, weight: f64) -> f64 {
+    if correct {
+        PASSING_SCORE + (1.0 - PASSING_SCORE) * weight.clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Lowers the quality of an answer typed with the help of hints, see hint::hint.
+///
+/// Every hint scales the part above PASSING_SCORE by HINT_FACTOR, an answer whose hints revealed the whole label counts as forgotten.
+pub fn hinted_quality(quality: f64, hints: usize, hint_levels: usize) -> f64 {
+    if hints == 0 || !is_success(quality) {
+        quality
+    } else if hints >= hint_levels {
+        0.0
+    } else {
+        PASSING_SCORE + (quality - PASSING_SCORE) * HINT_FACTOR.powi(hints as i32)
+    }
+}
+
+pub trait Scheduler {
+    /// Returns when the card should be reviewed next and how likely it is to be recalled at `now`.
+    fn schedule(&mut self, meta_data: &CardMetaData, now: f64) -> Schedule;
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum SchedulerKind {
+    Ebisu,
+    SM2,
+    Leitner,
+    FSRS,
+}
+
+impl Default for SchedulerKind {
+    fn default() -> Self {
+        SchedulerKind::Ebisu
+    }
+}
+
+impl SchedulerKind {
+    pub const ALL: [SchedulerKind; 4] = [
+        SchedulerKind::Ebisu,
+        SchedulerKind::SM2,
+        SchedulerKind::Leitner,
+        SchedulerKind::FSRS,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SchedulerKind::Ebisu => "EBISU",
+            SchedulerKind::SM2 => "SM-2",
+            SchedulerKind::Leitner => "Leitner",
+            SchedulerKind::FSRS => "FSRS",
+        }
+    }
+
+    fn build(&self, fsrs_parameters: &[f64]) -> Box<dyn Scheduler> {
+        match self {
+            SchedulerKind::Ebisu => Box::new(EbisuScheduler::default()),
+            SchedulerKind::SM2 => Box::new(SM2Scheduler::default()),
+            SchedulerKind::Leitner => Box::new(LeitnerScheduler::default()),
+            SchedulerKind::FSRS => Box::new(FSRSScheduler::new(fsrs_parameters)),
+        }
+    }
+}
+
+/// Holds the currently selected scheduler.
+pub struct SpacedRepetition {
+    scheduler_kind: SchedulerKind,
+    fsrs_parameters: Vec<f64>,
+    scheduler: Box<dyn Scheduler>,
 }
 
 impl Default for SpacedRepetition {
     fn default() -> Self {
-        SpacedRepetition::new()
-    }
-}
-
-pub fn estimate_next_session_timestamp(
-    my_class: &mut SpacedRepetition,
-    meta_data: &mut CardMetaData,
-) -> f64 {
-    my_class.calculateEBISU(
-        meta_data.id,
-        meta_data.timestamps.clone(),
-        meta_data
-            .scores
-            .clone()
-            .into_iter()
-            .map(|x| js_sys::Boolean::from(x))
-            .collect(),
-    )
+        SpacedRepetition::new(SchedulerKind::default(), &fsrs::DEFAULT_PARAMETERS)
+    }
+}
+
+impl SpacedRepetition {
+    pub fn new(scheduler_kind: SchedulerKind, fsrs_parameters: &[f64]) -> SpacedRepetition {
+        SpacedRepetition {
+            scheduler_kind,
+            fsrs_parameters: fsrs_parameters.to_vec(),
+            scheduler: scheduler_kind.build(fsrs_parameters),
+        }
+    }
+
+    pub fn set_scheduler_kind(&mut self, scheduler_kind: SchedulerKind) {
+        if self.scheduler_kind != scheduler_kind {
+            *self = SpacedRepetition::new(scheduler_kind, &self.fsrs_parameters);
+        }
+    }
+
+    pub fn set_fsrs_parameters(&mut self, fsrs_parameters: &[f64]) {
+        if self.fsrs_parameters != fsrs_parameters {
+            *self = SpacedRepetition::new(self.scheduler_kind, fsrs_parameters);
+        }
+    }
+}
+
+impl Scheduler for SpacedRepetition {
+    fn schedule(&mut self, meta_data: &CardMetaData, now: f64) -> Schedule {
+        self.scheduler.schedule(meta_data, now)
+    }
 }
@@ -0,0 +1,135 @@
+// SuperMemo 2 (https://super-memory.com/english/ol/sm2.htm)
+
+use super::{is_success, Schedule, Scheduler, ONE_DAY, PASSING_SCORE};
+use crate::app_controller::model_controller::data_model::card_model::CardMetaData;
+
+const INITIAL_EASINESS_FACTOR: f64 = 2.5;
+const MINIMUM_EASINESS_FACTOR: f64 = 1.3;
+
+pub struct SM2Scheduler {
+    first_interval: f64,
+    second_interval: f64,
+}
+
+impl Default for SM2Scheduler {
+    fn default() -> Self {
+        SM2Scheduler {
+            first_interval: ONE_DAY,
+            second_interval: 6.0 * ONE_DAY,
+        }
+    }
+}
+
+impl SM2Scheduler {
+    /// Maps a review score to the SM-2 response quality (0-5), passing scores to 3-5.
+    fn quality(score: f64) -> f64 {
+        if is_success(score) {
+            3.0 + 2.0 * (score.min(1.0) - PASSING_SCORE) / (1.0 - PASSING_SCORE)
+        } else {
+            2.0
+        }
+    }
+
+    /// Replays the review history and returns the current interval.
+    fn interval(&self, scores: &[f64]) -> f64 {
+        let mut easiness_factor = INITIAL_EASINESS_FACTOR;
+        let mut repetitions = 0;
+        let mut interval = self.first_interval;
+
+        for score in scores {
+            let quality = SM2Scheduler::quality(*score);
+            if quality >= 3.0 {
+                interval = match repetitions {
+                    0 => self.first_interval,
+                    1 => self.second_interval,
+                    _ => interval * easiness_factor,
+                };
+                repetitions += 1;
+            } else {
+                repetitions = 0;
+                interval = self.first_interval;
+            }
+            easiness_factor += 0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02);
+            easiness_factor = easiness_factor.max(MINIMUM_EASINESS_FACTOR);
+        }
+        interval
+    }
+}
+
+impl Scheduler for SM2Scheduler {
+    fn schedule(&mut self, meta_data: &CardMetaData, now: f64) -> Schedule {
+        match meta_data.timestamps.last() {
+            None => Schedule::new_card(now),
+            Some(last_timestamp) => {
+                Schedule::from_interval(*last_timestamp, self.interval(&meta_data.scores), now)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_controller::model_controller::data_model::spaced_repetition::Grade;
+
+    const AGAIN: f64 = 0.0;
+    const EASY: f64 = 1.0;
+
+    fn interval_days(scores: &[f64]) -> f64 {
+        SM2Scheduler::default().interval(scores) / ONE_DAY
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} is not close to {}", actual, expected);
+    }
+
+    #[test]
+    fn grades_map_to_response_qualities() {
+        assert_eq!(SM2Scheduler::quality(Grade::Again.score()), 2.0);
+        assert_close(SM2Scheduler::quality(Grade::Hard.score()), 3.4);
+        assert_close(SM2Scheduler::quality(Grade::Good.score()), 4.2);
+        assert_eq!(SM2Scheduler::quality(Grade::Easy.score()), 5.0);
+    }
+
+    #[test]
+    fn intervals_grow_with_the_easiness_factor() {
+        // EF 2.5 grows by 0.1 per perfect answer: 1 day, 6 days, 6 * 2.7, 6 * 2.7 * 2.8
+        assert_close(interval_days(&[EASY]), 1.0);
+        assert_close(interval_days(&[EASY, EASY]), 6.0);
+        assert_close(interval_days(&[EASY, EASY, EASY]), 16.2);
+        assert_close(interval_days(&[EASY, EASY, EASY, EASY]), 45.36);
+        // a good answer (quality 4.2) raises EF by 0.1 - 0.8 * (0.08 + 0.8 * 0.02) = 0.0232 only.
+        let good = Grade::Good.score();
+        assert_close(interval_days(&[good, good, good]), 6.0 * (2.5 + 2.0 * 0.0232));
+    }
+
+    #[test]
+    fn a_failure_restarts_the_repetitions() {
+        assert_close(interval_days(&[EASY, EASY, AGAIN]), 1.0);
+        // EF 2.7 - 0.32 = 2.38, then 2.48 and 2.58 with the next perfect answers.
+        assert_close(interval_days(&[EASY, EASY, AGAIN, EASY]), 1.0);
+        assert_close(interval_days(&[EASY, EASY, AGAIN, EASY, EASY]), 6.0);
+        assert_close(interval_days(&[EASY, EASY, AGAIN, EASY, EASY, EASY]), 6.0 * 2.58);
+    }
+
+    #[test]
+    fn the_easiness_factor_stays_above_its_minimum() {
+        let mut scores = vec![EASY, AGAIN, AGAIN, AGAIN, AGAIN, AGAIN];
+        scores.extend([EASY, EASY, EASY]);
+        // EF reaches 1.3, then 1.4 and 1.5 before the third interval.
+        assert_close(interval_days(&scores), 6.0 * 1.5);
+    }
+
+    #[test]
+    fn schedule_counts_from_the_last_review() {
+        let meta_data: CardMetaData = serde_json::from_value(serde_json::json!({
+            "id": "deck/1",
+            "timestamps": [0.0, 2.0 * ONE_DAY],
+            "scores": [EASY, EASY],
+        }))
+        .unwrap();
+        let schedule = SM2Scheduler::default().schedule(&meta_data, 2.0 * ONE_DAY);
+        assert_eq!(schedule.next_session_timestamp, 8.0 * ONE_DAY);
+        assert_eq!(schedule.recall_probability, 1.0);
+    }
+}
@@ -0,0 +1,98 @@
+// Per-deck synonyms, read from "synonyms.json" next to the card files, e.g.
+// [["thank you", "thanks"], ["eid", "idul fitri"]]
+// Every phrase of a group is accepted in place of any other phrase of the same group.
+
+/// Upper bound of generated alternatives per label, the number grows exponentially with the matched groups.
+const MAXIMUM_ALTERNATIVES: usize = 16;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Clone)]
+pub struct Synonyms {
+    pub groups: Vec<Vec<String>>,
+}
+
+impl Synonyms {
+    pub fn parse(json_str: &str) -> Option<Synonyms> {
+        serde_json::from_str::<Vec<Vec<String>>>(json_str)
+            .ok()
+            .map(|groups| Synonyms {
+                groups: groups
+                    .into_iter()
+                    .map(|group| {
+                        group
+                            .into_iter()
+                            .filter(|phrase| !phrase.trim().is_empty())
+                            .collect::<Vec<String>>()
+                    })
+                    .filter(|group| group.len() > 1)
+                    .collect(),
+            })
+    }
+
+    /// Returns `label` with synonyms substituted, the label itself is not included.
+    pub fn alternatives(&self, label: &str) -> Vec<String> {
+        let mut alternatives = vec![label.to_string()];
+        let mut index = 0;
+        while index < alternatives.len() && alternatives.len() < MAXIMUM_ALTERNATIVES {
+            let current = alternatives[index].to_owned();
+            for group in &self.groups {
+                for phrase in group {
+                    if let Some((start, end)) = find_phrase(&current, phrase) {
+                        for other in group.iter().filter(|other| *other != phrase) {
+                            let alternative =
+                                format!("{}{}{}", &current[..start], other, &current[end..]);
+                            if alternatives.len() < MAXIMUM_ALTERNATIVES
+                                && !alternatives.contains(&alternative)
+                            {
+                                alternatives.push(alternative);
+                            }
+                        }
+                    }
+                }
+            }
+            index += 1;
+        }
+        alternatives.remove(0);
+        alternatives
+    }
+}
+
+/// Case insensitive search for `phrase` as whole words, returns its byte range in `text`.
+fn find_phrase(text: &str, phrase: &str) -> Option<(usize, usize)> {
+    let phrase: Vec<char> = phrase.chars().flat_map(|c| c.to_lowercase()).collect();
+    if phrase.is_empty() {
+        return None;
+    }
+    for (start, _) in text.char_indices() {
+        let mut chars = text[start..].char_indices();
+        let mut matched = 0;
+        let mut end = start;
+        while matched < phrase.len() {
+            match chars.next() {
+                Some((offset, c)) => {
+                    let lowercase: Vec<char> = c.to_lowercase().collect();
+                    if phrase[matched..].starts_with(&lowercase) {
+                        matched += lowercase.len();
+                        end = start + offset + c.len_utf8();
+                    } else {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+        let is_word_start = text[..start]
+            .chars()
+            .last()
+            .map(|c| !c.is_alphanumeric())
+            .unwrap_or(true);
+        let is_word_end = text[end..]
+            .chars()
+            .next()
+            .map(|c| !c.is_alphanumeric())
+            .unwrap_or(true);
+        if matched == phrase.len() && is_word_start && is_word_end {
+            return Some((start, end));
+        }
+    }
+    None
+}
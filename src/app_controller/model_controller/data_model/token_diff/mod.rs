@@ -0,0 +1,332 @@
+// Word level comparison of an answer with its label, used to explain mistakes on the review screen.
+
+use super::typo_tolerance::weighted_edit_distance;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenClass {
+    Correct,
+    /// Typed with a few wrong characters, `text` is the expected token, `given` what was typed.
+    Misspelt,
+    /// Expected but not typed.
+    Missing,
+    /// Typed but not expected.
+    Extra,
+    /// Expected, but typed at a different position.
+    Misordered,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffToken {
+    pub class: TokenClass,
+    pub text: String,
+    pub given: Option<String>,
+}
+
+impl DiffToken {
+    /// CJK tokens are written without a space in between.
+    pub fn is_cjk(&self) -> bool {
+        self.text.chars().all(is_cjk)
+    }
+}
+
+/// Han, Hiragana and Katakana (and their punctuation) are written without spaces, each character is a token.
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3000..=0x303F
+        | 0x3040..=0x309F
+        | 0x30A0..=0x30FF
+        | 0x31F0..=0x31FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xF900..=0xFAFF
+        | 0xFF00..=0xFFEF
+        | 0x20000..=0x2A6DF)
+}
+
+/// Splits on whitespace, CJK characters become tokens of their own.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for word in text.split_whitespace() {
+        let mut current = String::new();
+        for c in word.chars() {
+            if is_cjk(c) {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+                tokens.push(c.to_string());
+            } else {
+                current.push(c);
+            }
+        }
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+    }
+    tokens
+}
+
+/// Index pairs of the longest common subsequence of equal tokens.
+fn longest_common_subsequence(expected: &[String], given: &[String]) -> Vec<(usize, usize)> {
+    let (n, m) = (expected.len(), given.len());
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if expected[i] == given[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if expected[i] == given[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// A token counts as misspelt (rather than wrong) if at most half of its characters differ.
+pub fn is_misspelling(expected: &str, given: &str) -> bool {
+    let tolerance = ((expected.chars().count() + 1) / 2).max(1);
+    weighted_edit_distance(expected, given) <= tolerance as f64
+}
+
+/// Compares the tokens of `given` with the tokens of `expected`, both should already be normalised.
+///
+/// The result follows the order of the given answer, missing tokens are inserted where they were expected.
+pub fn diff_tokens(expected: &str, given: &str) -> Vec<DiffToken> {
+    let expected = tokenize(expected);
+    let given = tokenize(given);
+
+    // pairing of each expected token with a given token.
+    let mut expected_pair: Vec<Option<(usize, TokenClass)>> = vec![None; expected.len()];
+    let mut given_pair: Vec<Option<TokenClass>> = vec![None; given.len()];
+
+    for (i, j) in longest_common_subsequence(&expected, &given) {
+        expected_pair[i] = Some((j, TokenClass::Correct));
+        given_pair[j] = Some(TokenClass::Correct);
+    }
+
+    // equal tokens outside of the common subsequence were typed in the wrong place.
+    for i in 0..expected.len() {
+        if expected_pair[i].is_some() {
+            continue;
+        }
+        if let Some(j) = (0..given.len()).find(|j| given_pair[*j].is_none() && given[*j] == expected[i]) {
+            expected_pair[i] = Some((j, TokenClass::Misordered));
+            given_pair[j] = Some(TokenClass::Misordered);
+        }
+    }
+
+    // the remaining tokens between two correct ones are compared for spelling mistakes.
+    for i in 0..expected.len() {
+        if expected_pair[i].is_some() {
+            continue;
+        }
+        let lower = expected_pair[..i]
+            .iter()
+            .rev()
+            .find_map(|pair| match pair {
+                Some((j, TokenClass::Correct)) => Some(j + 1),
+                _ => None,
+            })
+            .unwrap_or(0);
+        let upper = expected_pair[i + 1..]
+            .iter()
+            .find_map(|pair| match pair {
+                Some((j, TokenClass::Correct)) => Some(*j),
+                _ => None,
+            })
+            .unwrap_or(given.len());
+        if let Some(j) = (lower..upper.max(lower))
+            .find(|j| given_pair[*j].is_none() && is_misspelling(&expected[i], &given[*j]))
+        {
+            expected_pair[i] = Some((j, TokenClass::Misspelt));
+            given_pair[j] = Some(TokenClass::Misspelt);
+        }
+    }
+
+    // sort by position in the given answer, a missing token goes right after the token typed before it.
+    let mut positioned: Vec<(f64, DiffToken)> = Vec::new();
+    for (j, token) in given.iter().enumerate() {
+        match given_pair[j] {
+            Some(TokenClass::Misspelt) => {
+                let i = expected_pair
+                    .iter()
+                    .position(|pair| *pair == Some((j, TokenClass::Misspelt)))
+                    .unwrap_or(0);
+                positioned.push((
+                    j as f64,
+                    DiffToken {
+                        class: TokenClass::Misspelt,
+                        text: expected[i].to_owned(),
+                        given: Some(token.to_owned()),
+                    },
+                ));
+            }
+            Some(class) => positioned.push((
+                j as f64,
+                DiffToken {
+                    class,
+                    text: token.to_owned(),
+                    given: Some(token.to_owned()),
+                },
+            )),
+            None => positioned.push((
+                j as f64,
+                DiffToken {
+                    class: TokenClass::Extra,
+                    text: token.to_owned(),
+                    given: Some(token.to_owned()),
+                },
+            )),
+        }
+    }
+    for i in 0..expected.len() {
+        if expected_pair[i].is_none() {
+            let previous = expected_pair[..i]
+                .iter()
+                .rev()
+                .find_map(|pair| match pair {
+                    Some((j, TokenClass::Correct)) | Some((j, TokenClass::Misspelt)) => {
+                        Some(*j as f64)
+                    }
+                    _ => None,
+                })
+                .unwrap_or(-1.0);
+            positioned.push((
+                previous + 0.5,
+                DiffToken {
+                    class: TokenClass::Missing,
+                    text: expected[i].to_owned(),
+                    given: None,
+                },
+            ));
+        }
+    }
+    // stable, so missing tokens at the same position keep their order.
+    positioned.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    positioned.into_iter().map(|(_, token)| token).collect()
+}
+
+pub fn is_correct(diff: &[DiffToken]) -> bool {
+    diff.iter().all(|token| token.class == TokenClass::Correct)
+}
+
+/// True if the answer has all the right tokens, just not in the right order.
+pub fn is_reordered(diff: &[DiffToken]) -> bool {
+    diff.iter()
+        .any(|token| token.class == TokenClass::Misordered)
+        && diff.iter().all(|token| {
+            token.class == TokenClass::Correct || token.class == TokenClass::Misordered
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classes(diff: &[DiffToken]) -> Vec<(TokenClass, &str)> {
+        diff.iter().map(|token| (token.class, token.text.as_str())).collect()
+    }
+
+    #[test]
+    fn cjk_characters_are_tokens() {
+        assert_eq!(tokenize("我爱 you  too"), vec!["我", "爱", "you", "too"]);
+        assert_eq!(tokenize("abc日本"), vec!["abc", "日", "本"]);
+        assert!(tokenize(" \t").is_empty());
+    }
+
+    #[test]
+    fn equal_answers_are_correct() {
+        let diff = diff_tokens("saya makan nasi", "saya makan nasi");
+        assert!(is_correct(&diff));
+        assert!(!is_reordered(&diff));
+    }
+
+    #[test]
+    fn missing_tokens_go_where_they_were_expected() {
+        let diff = diff_tokens("saya makan nasi", "saya nasi");
+        assert_eq!(
+            classes(&diff),
+            vec![
+                (TokenClass::Correct, "saya"),
+                (TokenClass::Missing, "makan"),
+                (TokenClass::Correct, "nasi")
+            ]
+        );
+        assert_eq!(diff[1].given, None);
+        let diff = diff_tokens("saya makan", "makan");
+        assert_eq!(
+            classes(&diff),
+            vec![(TokenClass::Missing, "saya"), (TokenClass::Correct, "makan")]
+        );
+    }
+
+    #[test]
+    fn extra_tokens_are_marked() {
+        let diff = diff_tokens("saya makan", "saya mau makan");
+        assert_eq!(
+            classes(&diff),
+            vec![
+                (TokenClass::Correct, "saya"),
+                (TokenClass::Extra, "mau"),
+                (TokenClass::Correct, "makan")
+            ]
+        );
+        assert!(!is_correct(&diff));
+    }
+
+    #[test]
+    fn misspelt_tokens_keep_the_expected_text() {
+        let diff = diff_tokens("saya makan nasi", "saya makn nasi");
+        assert_eq!(diff[1].class, TokenClass::Misspelt);
+        assert_eq!(diff[1].text, "makan");
+        assert_eq!(diff[1].given.as_deref(), Some("makn"));
+    }
+
+    #[test]
+    fn misspellings_allow_half_of_the_characters() {
+        assert!(is_misspelling("kucing", "kucinh"));
+        assert!(!is_misspelling("makan", "minum"));
+        assert!(is_misspelling("a", "b"));
+    }
+
+    #[test]
+    fn reordered_tokens_are_misordered() {
+        let diff = diff_tokens("saya makan nasi", "nasi saya makan");
+        assert_eq!(
+            classes(&diff),
+            vec![
+                (TokenClass::Misordered, "nasi"),
+                (TokenClass::Correct, "saya"),
+                (TokenClass::Correct, "makan")
+            ]
+        );
+        assert!(is_reordered(&diff));
+        assert!(!is_reordered(&diff_tokens("saya makan nasi", "nasi saya")));
+    }
+
+    #[test]
+    fn cjk_answers_are_compared_per_character() {
+        let diff = diff_tokens("我爱你", "我你");
+        assert_eq!(
+            classes(&diff),
+            vec![
+                (TokenClass::Correct, "我"),
+                (TokenClass::Missing, "爱"),
+                (TokenClass::Correct, "你")
+            ]
+        );
+        assert!(diff.iter().all(|token| token.is_cjk()));
+    }
+}
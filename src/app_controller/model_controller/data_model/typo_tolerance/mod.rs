@@ -0,0 +1,354 @@
+// Typo tolerance relative to the length of the answer, with a weighted edit distance where likely typos
+// (neighbouring keys, swapped letters, missing accents) cost less than other mistakes.
+
+use super::normalization::fold_accents;
+use super::token_diff::{is_misspelling, tokenize};
+
+/// Cost of typing a key next to the right one on a QWERTY keyboard.
+pub const ADJACENT_KEY_COST: f64 = 0.5;
+/// Cost of swapping two neighbouring characters.
+pub const TRANSPOSITION_COST: f64 = 0.5;
+/// Cost of a character that only differs by its accent, e.g. "é" for "e".
+pub const DIACRITIC_COST: f64 = 0.25;
+/// Cost of a word at the wrong position, see count_typos_any_order.
+pub const WORD_ORDER_COST: f64 = 0.5;
+
+/// Length of an answer the former absolute "spelling correction threshold" is related to when the label is not
+/// known, the former default of one mistake becomes the default of 20 %.
+const THRESHOLD_ANSWER_LENGTH: usize = 5;
+
+const KEYBOARD_ROWS: [&str; 4] = ["1234567890", "qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct TypoTolerance {
+    /// Permitted mistakes in percent of the characters of the answer.
+    pub percentage: f32,
+    /// Applies the percentage to every word on its own, so mistakes cannot pile up in a single word.
+    pub per_word: bool,
+}
+
+impl Default for TypoTolerance {
+    fn default() -> Self {
+        TypoTolerance {
+            percentage: 20.0,
+            per_word: false,
+        }
+    }
+}
+
+impl TypoTolerance {
+    pub fn permitted_mistakes(&self, text: &str) -> f64 {
+        text.chars().count() as f64 * self.percentage.max(0.0) as f64 / 100.0
+    }
+
+    /// The percentage of `label` that permits as many mistakes as the former threshold, a number of mistakes
+    /// per answer.
+    pub fn threshold_percentage(threshold: usize, label: Option<&str>) -> f32 {
+        let length = label
+            .map(|label| label.chars().count())
+            .filter(|length| *length > 0)
+            .unwrap_or(THRESHOLD_ANSWER_LENGTH);
+        (threshold as f32 * 100.0 / length as f32).min(100.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Typos {
+    /// Weighted edit distance between the answer and the label.
+    pub distance: f64,
+    pub permitted: f64,
+    /// False if there are too many mistakes overall (or, per word, in a single word).
+    pub within_tolerance: bool,
+}
+
+/// Counts the typos of `given` compared with `expected`, both should already be normalised.
+///
+/// Per word tolerance needs the same number of words in both, otherwise the whole answer is compared.
+pub fn count_typos(expected: &str, given: &str, tolerance: &TypoTolerance) -> Typos {
+    let expected_words: Vec<&str> = expected.split_whitespace().collect();
+    let given_words: Vec<&str> = given.split_whitespace().collect();
+
+    if tolerance.per_word && expected_words.len() == given_words.len() && expected_words.len() > 1 {
+        let mut typos = Typos {
+            distance: 0.0,
+            permitted: 0.0,
+            within_tolerance: true,
+        };
+        for (expected_word, given_word) in expected_words.iter().zip(given_words.iter()) {
+            let distance = weighted_edit_distance(expected_word, given_word);
+            let permitted = tolerance.permitted_mistakes(expected_word);
+            typos.distance += distance;
+            typos.permitted += permitted;
+            typos.within_tolerance = typos.within_tolerance && distance <= permitted;
+        }
+        typos
+    } else {
+        let distance = weighted_edit_distance(expected, given);
+        let permitted = tolerance.permitted_mistakes(expected);
+        Typos {
+            distance,
+            permitted,
+            within_tolerance: distance <= permitted,
+        }
+    }
+}
+
+/// Like count_typos, but the words may come in any order: every expected word is paired with a given word
+/// (an equal one, or else the closest misspelling) and each word out of place costs WORD_ORDER_COST.
+pub fn count_typos_any_order(expected: &str, given: &str, tolerance: &TypoTolerance) -> Typos {
+    let expected_tokens = tokenize(expected);
+    let given_tokens = tokenize(given);
+    let mut pairs: Vec<Option<usize>> = vec![None; expected_tokens.len()];
+    let mut used = vec![false; given_tokens.len()];
+
+    for (i, expected_token) in expected_tokens.iter().enumerate() {
+        if let Some(j) = (0..given_tokens.len()).find(|j| !used[*j] && given_tokens[*j] == *expected_token) {
+            pairs[i] = Some(j);
+            used[j] = true;
+        }
+    }
+    for (i, expected_token) in expected_tokens.iter().enumerate() {
+        if pairs[i].is_some() {
+            continue;
+        }
+        let closest = (0..given_tokens.len())
+            .filter(|j| !used[*j] && is_misspelling(expected_token, &given_tokens[*j]))
+            .map(|j| (j, weighted_edit_distance(expected_token, &given_tokens[j])))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((j, _)) = closest {
+            pairs[i] = Some(j);
+            used[j] = true;
+        }
+    }
+
+    let mut typos = Typos {
+        distance: 0.0,
+        permitted: tolerance.permitted_mistakes(expected),
+        within_tolerance: true,
+    };
+    for (i, expected_token) in expected_tokens.iter().enumerate() {
+        match pairs[i] {
+            Some(j) => {
+                let distance = weighted_edit_distance(expected_token, &given_tokens[j]);
+                typos.distance += distance;
+                if tolerance.per_word && distance > tolerance.permitted_mistakes(expected_token) {
+                    typos.within_tolerance = false;
+                }
+            }
+            None => {
+                typos.distance += expected_token.chars().count() as f64;
+                typos.within_tolerance = typos.within_tolerance && !tolerance.per_word;
+            }
+        }
+    }
+    for (j, given_token) in given_tokens.iter().enumerate() {
+        if !used[j] {
+            typos.distance += given_token.chars().count() as f64;
+            typos.within_tolerance = typos.within_tolerance && !tolerance.per_word;
+        }
+    }
+
+    let order: Vec<usize> = pairs.iter().flatten().copied().collect();
+    typos.distance += (order.len() - longest_increasing_subsequence(&order)) as f64 * WORD_ORDER_COST;
+    typos.within_tolerance = typos.within_tolerance && typos.distance <= typos.permitted;
+    typos
+}
+
+fn longest_increasing_subsequence(values: &[usize]) -> usize {
+    let mut tails: Vec<usize> = Vec::new();
+    for value in values {
+        match tails.binary_search(value) {
+            Ok(_) => {}
+            Err(position) if position == tails.len() => tails.push(*value),
+            Err(position) => tails[position] = *value,
+        }
+    }
+    tails.len()
+}
+
+fn key_position(c: char) -> Option<(usize, usize)> {
+    KEYBOARD_ROWS
+        .iter()
+        .enumerate()
+        .find_map(|(row, keys)| keys.chars().position(|key| key == c).map(|column| (row, column)))
+}
+
+/// Keys of a row sit half a key to the right of the row above, so a key touches the keys at the same
+/// and at the next column of the row above.
+fn is_adjacent_key(a: char, b: char) -> bool {
+    let a = a.to_ascii_lowercase();
+    let b = b.to_ascii_lowercase();
+    match (key_position(a), key_position(b)) {
+        (Some((row_a, column_a)), Some((row_b, column_b))) => {
+            if row_a == row_b {
+                column_a.abs_diff(column_b) == 1
+            } else if row_a + 1 == row_b {
+                column_b == column_a || column_b + 1 == column_a
+            } else if row_b + 1 == row_a {
+                column_a == column_b || column_a + 1 == column_b
+            } else {
+                false
+            }
+        }
+        _ => false,
+    }
+}
+
+fn substitution_cost(a: char, b: char) -> f64 {
+    if a == b {
+        0.0
+    } else if fold_accents(&a.to_string()) == fold_accents(&b.to_string()) {
+        DIACRITIC_COST
+    } else if is_adjacent_key(a, b) {
+        ADJACENT_KEY_COST
+    } else {
+        1.0
+    }
+}
+
+/// Optimal string alignment distance with the costs above, insertions and deletions cost 1.
+pub fn weighted_edit_distance(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0.0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i as f64;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j as f64;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let mut cost = (d[i - 1][j] + 1.0)
+                .min(d[i][j - 1] + 1.0)
+                .min(d[i - 1][j - 1] + substitution_cost(a[i - 1], b[j - 1]));
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] && a[i - 1] != b[j - 1] {
+                cost = cost.min(d[i - 2][j - 2] + TRANSPOSITION_COST);
+            }
+            d[i][j] = cost;
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SENTENCE: &str = "the quick brown fox jumps over the lazy dog by the old river";
+
+    #[test]
+    fn neighbouring_keys_are_adjacent() {
+        assert!(is_adjacent_key('a', 'q'));
+        assert!(is_adjacent_key('q', 'a'));
+        assert!(is_adjacent_key('a', 's'));
+        assert!(is_adjacent_key('A', 's'));
+        assert!(is_adjacent_key('1', 'q'));
+        assert!(is_adjacent_key('n', 'm'));
+        assert!(!is_adjacent_key('a', 'p'));
+        assert!(!is_adjacent_key('a', 'a'));
+        assert!(!is_adjacent_key('a', 'é'));
+    }
+
+    #[test]
+    fn likely_typos_cost_less() {
+        assert_eq!(weighted_edit_distance("ya", "yq"), ADJACENT_KEY_COST);
+        assert_eq!(weighted_edit_distance("teh", "the"), TRANSPOSITION_COST);
+        assert_eq!(weighted_edit_distance("cafe", "café"), DIACRITIC_COST);
+        assert_eq!(weighted_edit_distance("ya", "yp"), 1.0);
+        assert_eq!(weighted_edit_distance("kucing", "kucing"), 0.0);
+        assert_eq!(weighted_edit_distance("", "abc"), 3.0);
+        assert_eq!(weighted_edit_distance("kucing", "kuching"), 1.0);
+    }
+
+    #[test]
+    fn tolerance_grows_with_the_answer() {
+        let tolerance = TypoTolerance::default();
+        assert_eq!(SENTENCE.chars().count(), 60);
+        assert_eq!(tolerance.permitted_mistakes(SENTENCE), 12.0);
+        let typos = count_typos(SENTENCE, &SENTENCE[..48], &tolerance);
+        assert_eq!(typos.distance, 12.0);
+        assert!(typos.within_tolerance);
+        assert!(!count_typos(SENTENCE, &SENTENCE[..47], &tolerance).within_tolerance);
+        // a short answer permits less than a mistake.
+        assert!(!count_typos("ya", "yq", &tolerance).within_tolerance);
+        assert!(count_typos("the cat", "teh cat", &tolerance).within_tolerance);
+    }
+
+    #[test]
+    fn per_word_tolerance_keeps_mistakes_from_piling_up() {
+        let whole = TypoTolerance::default();
+        let per_word = TypoTolerance {
+            per_word: true,
+            ..TypoTolerance::default()
+        };
+        assert!(count_typos("saya makan", "saya mkn", &whole).within_tolerance);
+        let typos = count_typos("saya makan", "saya mkn", &per_word);
+        assert_eq!(typos.distance, 2.0);
+        assert_eq!(typos.permitted, 1.8);
+        assert!(!typos.within_tolerance);
+        // a different number of words is compared as a whole.
+        assert_eq!(count_typos("saya makan", "sayamakan", &per_word).distance, 1.0);
+    }
+
+    #[test]
+    fn reordered_words_are_mistakes_in_order() {
+        let tolerance = TypoTolerance::default();
+        assert!(!count_typos("saya makan nasi", "nasi saya makan", &tolerance).within_tolerance);
+    }
+
+    #[test]
+    fn reordered_words_cost_once_per_word_out_of_place() {
+        let tolerance = TypoTolerance::default();
+        let typos = count_typos_any_order("saya makan nasi", "nasi saya makan", &tolerance);
+        assert_eq!(typos.distance, WORD_ORDER_COST);
+        assert!(typos.within_tolerance);
+        let typos = count_typos_any_order("saya makan nasi", "nasi makan saya", &tolerance);
+        assert_eq!(typos.distance, 2.0 * WORD_ORDER_COST);
+    }
+
+    #[test]
+    fn reordered_words_may_be_misspelt() {
+        let tolerance = TypoTolerance::default();
+        let typos = count_typos_any_order("saya makan nasi", "nasi saya makn", &tolerance);
+        assert_eq!(typos.distance, 1.0 + WORD_ORDER_COST);
+        assert!(typos.within_tolerance);
+    }
+
+    #[test]
+    fn missing_and_extra_words_cost_their_length() {
+        let whole = TypoTolerance {
+            percentage: 50.0,
+            per_word: false,
+        };
+        let per_word = TypoTolerance {
+            percentage: 50.0,
+            per_word: true,
+        };
+        let typos = count_typos_any_order("saya makan nasi", "nasi saya", &whole);
+        assert_eq!(typos.distance, 5.0 + WORD_ORDER_COST);
+        assert!(typos.within_tolerance);
+        assert!(!count_typos_any_order("saya makan nasi", "nasi saya", &per_word).within_tolerance);
+        let typos = count_typos_any_order("saya makan", "makan saya mau", &whole);
+        assert_eq!(typos.distance, 3.0 + WORD_ORDER_COST);
+        assert!(!count_typos_any_order("saya makan", "makan saya mau", &per_word).within_tolerance);
+    }
+
+    #[test]
+    fn longest_increasing_subsequence_of_word_positions() {
+        assert_eq!(longest_increasing_subsequence(&[]), 0);
+        assert_eq!(longest_increasing_subsequence(&[0, 1, 2]), 3);
+        assert_eq!(longest_increasing_subsequence(&[2, 0, 1]), 2);
+        assert_eq!(longest_increasing_subsequence(&[2, 1, 0]), 1);
+        assert_eq!(longest_increasing_subsequence(&[3, 1, 2, 0, 4]), 3);
+    }
+
+    #[test]
+    fn the_former_threshold_maps_to_a_percentage() {
+        assert_eq!(TypoTolerance::threshold_percentage(1, None), TypoTolerance::default().percentage);
+        assert_eq!(TypoTolerance::threshold_percentage(2, Some("kucing")), 100.0 / 3.0);
+        assert_eq!(TypoTolerance::threshold_percentage(0, Some("kucing")), 0.0);
+        assert_eq!(TypoTolerance::threshold_percentage(10, Some("ya")), 100.0);
+        assert_eq!(TypoTolerance::threshold_percentage(1, Some("")), 20.0);
+    }
+}
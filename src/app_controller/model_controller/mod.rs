@@ -8,29 +8,79 @@ use super::view_controller::view_model_controller::view_model::ViewModel;
 use super::view_controller::view_model_controller::view_model::VolatilePropertieKey;
 use super::view_controller::view_model_controller::view_model::VolatilePropertieValue;
 use crate::app_controller::model_controller::data_model::download::DownloadState;
+use crate::app_controller::model_controller::data_model::hint::{hint, hint_levels};
+use crate::app_controller::model_controller::data_model::download::local_file::save_file;
+use crate::app_controller::model_controller::data_model::import::anki::{parse_apkg, AnkiFieldMapping};
+use crate::app_controller::model_controller::data_model::import::csv::{card_folder, CsvColumnMapping};
+use crate::app_controller::model_controller::data_model::import::slug;
+use crate::app_controller::model_controller::data_model::learning_steps::LearningSteps;
+use crate::app_controller::model_controller::data_model::normalization::NormalizationPipeline;
+use crate::app_controller::model_controller::data_model::review_queue::study_day_start;
+use crate::app_controller::model_controller::data_model::spaced_repetition::ONE_DAY;
 use crate::app_controller::model_controller::data_model::static_audio::StaticSounds;
+use crate::app_controller::model_controller::data_model::token_diff::diff_tokens;
+use crate::app_controller::model_controller::data_model::typo_tolerance::{
+    count_typos, count_typos_any_order, TypoTolerance, Typos,
+};
 use std::collections::HashSet;
 
-use any_ascii::any_ascii;
-use difference::{Changeset, Difference};
 use js_sys::Date;
 
-use crate::app_controller::model_controller::data_model::spaced_repetition::estimate_next_session_timestamp;
+use crate::app_controller::model_controller::data_model::spaced_repetition::fsrs;
+use crate::app_controller::model_controller::data_model::spaced_repetition::hinted_quality;
+use crate::app_controller::model_controller::data_model::spaced_repetition::is_success;
+use crate::app_controller::model_controller::data_model::spaced_repetition::recall_quality;
+use crate::app_controller::model_controller::data_model::spaced_repetition::recognition_quality;
+use crate::app_controller::model_controller::data_model::spaced_repetition::Scheduler;
 use std::collections::HashMap;
 
+/// Number of skipped entries of an imported file listed in the alert.
+const MAXIMUM_REPORTED_ROWS: usize = 10;
+
+/// e.g. " 2 rows were skipped:\nline 4: the label (column 2) is empty\nline 9: ..."
+fn skipped_message(skipped: &[String], entries: &str) -> String {
+    if skipped.is_empty() {
+        return String::new();
+    }
+    let mut message = format!(
+        " {} {} were skipped:\n{}",
+        skipped.len(),
+        entries,
+        skipped
+            .iter()
+            .take(MAXIMUM_REPORTED_ROWS)
+            .cloned()
+            .collect::<Vec<String>>()
+            .join("\n")
+    );
+    if skipped.len() > MAXIMUM_REPORTED_ROWS {
+        message.push_str("\n…");
+    }
+    message
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
 pub struct ModelController {
     app_data: AppData,
 
     checkpoints: HashMap<String,String>,
+
+    #[serde(skip)]
+    last_review_queue_check: f64,
 }
 impl ModelController {
     pub fn restore(&mut self, view_model: &ViewModel) {
+        self.app_data.migrate_decks();
+        self.app_data.migrate_card_ids();
+        self.app_data.migrate_media();
 
         let mut retained_controller_requests = HashSet::new();
 
-        retained_controller_requests.insert(ControllerRequest::RefreshCard); 
+        retained_controller_requests.insert(ControllerRequest::FetchNewCardOrContinue);
         retained_controller_requests.insert(ControllerRequest::RefreshRequestConfig); // other settings are not synchronized (!).
+        retained_controller_requests.insert(ControllerRequest::FetchSynonyms(true));
+        retained_controller_requests.insert(ControllerRequest::FetchDeckMetadata(true));
+        retained_controller_requests.insert(ControllerRequest::RefreshDecks);
 
         if self.checkpoints.is_empty() {
             retained_controller_requests.insert(ControllerRequest::SaveCheckpoint); // default checkpoint.
@@ -42,6 +92,154 @@ impl ModelController {
                 .extend(retained_controller_requests);
         }
     }
+    /// Applies the scheduler settings and sorts the card list, returns the number of due cards.
+    fn update_review_queue(&mut self, view_model: &ViewModel, now: f64) -> usize {
+        view_model.get_property(&PropertieKey::Scheduler, |val| {
+            if let PropertieValue::SchedulerKind(scheduler_kind) = val {
+                self.app_data
+                    .space_repetition_model
+                    .set_scheduler_kind(*scheduler_kind);
+            }
+        });
+        view_model.get_property(&PropertieKey::FSRSParameters, |val| {
+            if let PropertieValue::VecFloat(ref fsrs_parameters) = val {
+                self.app_data
+                    .space_repetition_model
+                    .set_fsrs_parameters(fsrs_parameters);
+            }
+        });
+        let learning_steps = self.learning_steps(view_model);
+        self.app_data.update_review_queue(now, &learning_steps)
+    }
+
+    fn learning_steps(&self, view_model: &ViewModel) -> LearningSteps {
+        let mut learning_steps = "".to_string();
+        view_model.get_property(&PropertieKey::LearningSteps, |val| {
+            if let PropertieValue::String(ref val) = val {
+                learning_steps.push_str(val);
+            }
+        });
+        let mut relearning_steps = "".to_string();
+        view_model.get_property(&PropertieKey::RelearningSteps, |val| {
+            if let PropertieValue::String(ref val) = val {
+                relearning_steps.push_str(val);
+            }
+        });
+        LearningSteps::parse(&learning_steps, &relearning_steps)
+    }
+
+    fn day_rollover_hour(&self, view_model: &ViewModel) -> u32 {
+        let mut day_rollover_hour = 4;
+        view_model.get_property(&PropertieKey::DayRolloverHour, |val| {
+            if let PropertieValue::Usize(ref val) = val {
+                day_rollover_hour = *val;
+            }
+        });
+        day_rollover_hour as u32
+    }
+
+    fn csv_column_mapping(&self, view_model: &ViewModel) -> CsvColumnMapping {
+        let mut mapping = CsvColumnMapping::default();
+        view_model.get_property(&PropertieKey::CsvColumnMapping, |val| {
+            if let PropertieValue::CsvColumnMapping(ref val) = val {
+                mapping = val.to_owned();
+            }
+        });
+        mapping
+    }
+
+    fn roll_over_daily_stats(&mut self, view_model: &ViewModel, now: f64) {
+        let day_rollover_hour = self.day_rollover_hour(view_model);
+        self.app_data.roll_over_daily_stats(now, day_rollover_hour);
+    }
+
+    /// Shows the deck list and what the decks define for themselves.
+    fn refresh_deck_properties(&self, view_model: &ViewModel) {
+        // the normalisation of the first deck that defines one, decks without keep the one of the user.
+        match self
+            .app_data
+            .decks
+            .iter()
+            .find_map(|deck| deck.metadata.normalization_pipeline())
+        {
+            Some(pipeline) => view_model.insert_property(
+                PropertieKey::DeckNormalization,
+                PropertieValue::NormalizationPipeline(pipeline),
+            ),
+            None => view_model.remove_property(&PropertieKey::DeckNormalization),
+        }
+        view_model.insert_property(
+            PropertieKey::DeckSummary,
+            PropertieValue::String(self.app_data.deck_summary()),
+        );
+        view_model.insert_property(
+            PropertieKey::Decks,
+            PropertieValue::DeckList(self.app_data.deck_settings()),
+        );
+    }
+
+    /// Writes a review of the current card into its history and moves it through the learning steps.
+    fn record_review(
+        &mut self,
+        view_model: &ViewModel,
+        quality: f64,
+        now: f64,
+        retained_controller_requests: &mut HashSet<ControllerRequest>,
+    ) {
+        let score = is_success(quality);
+
+        self.app_data.card_list[0]
+            .meta_data
+            .timestamps
+            .push(now);
+        self.app_data.card_list[0].meta_data.scores.push(quality);
+        let reviews = self.app_data.card_list[0].meta_data.timestamps.len();
+        let hints = &mut self.app_data.card_list[0].meta_data.hints;
+        hints.resize(reviews - 1, 0);
+        hints.push(self.app_data.hints_used);
+
+        let mut leech_threshold = 0;
+        view_model.get_property(&PropertieKey::LeechThreshold, |val| {
+            if let PropertieValue::Usize(ref val) = val {
+                leech_threshold = *val;
+            }
+        });
+        let mut suspend_leeches = false;
+        view_model.get_property(&PropertieKey::SuspendLeeches, |val| {
+            if let PropertieValue::Bool(ref val) = val {
+                suspend_leeches = *val;
+            }
+        });
+
+        let learning_steps = self.learning_steps(view_model);
+        let meta_data = &mut self.app_data.card_list[0].meta_data;
+
+        let mut is_new_leech = false;
+        if !score && meta_data.learning_step.is_none() {
+            meta_data.lapses += 1;
+            if leech_threshold > 0 && meta_data.lapses >= leech_threshold {
+                is_new_leech = !meta_data.leech;
+                meta_data.leech = true;
+                meta_data.suspended = meta_data.suspended || suspend_leeches;
+            }
+        }
+        meta_data.learning_step = learning_steps.next(&meta_data.learning_step, score);
+
+        if is_new_leech {
+            view_model.insert_property(
+                PropertieKey::ReviewLeech,
+                PropertieValue::Bool(suspend_leeches),
+            );
+            retained_controller_requests.insert(ControllerRequest::RefreshSuspendedCards);
+        }
+
+        self.roll_over_daily_stats(view_model, now);
+        self.app_data.daily_stats.reviews += 1;
+        if let Some(deck) = self.app_data.deck_of_card_mut(0) {
+            deck.daily_stats.reviews += 1;
+        }
+    }
+
     pub fn update(&mut self, view_model: &ViewModel) {
         let mut controller_requests = HashSet::new();
         if let Ok(mut inner) = view_model.inner.lock() {
@@ -49,6 +247,17 @@ impl ModelController {
         } 
         let mut retained_controller_requests = HashSet::new();
 
+        // while there is nothing to review, check once a minute if a card became due.
+        let now = Date::now();
+        if now - self.last_review_queue_check > 1000.0 * 60.0 {
+            self.last_review_queue_check = now;
+            view_model.get_property(&PropertieKey::DoneForToday, |val| {
+                if let PropertieValue::Bool(true) = val {
+                    retained_controller_requests.insert(ControllerRequest::FetchNewCardOrContinue);
+                }
+            });
+        }
+
         for request in controller_requests {
             match request {
                 ControllerRequest::ResetApp => {
@@ -115,7 +324,11 @@ impl ModelController {
                     view_model.insert_property(PropertieKey::SelectedCheckpoint,PropertieValue::String(new_checkpoint));
                 },
                 ControllerRequest::RefreshRequestConfig => {
-                    if let Ok(mut request_config) = self.app_data.request_config.read() {
+                    let request_config = match self.app_data.active_deck() {
+                        Some(deck) => deck.request_config.clone(),
+                        None => continue,
+                    };
+                    if let Ok(request_config) = request_config.read() {
                         if let Ok(mut inner) = view_model.inner.lock() {
                             if let Some(PropertieValue::String(ref mut endpoint)) =
                                 inner.properties.get_mut(&PropertieKey::CustomServerEndpoint)
@@ -136,7 +349,11 @@ impl ModelController {
                     }
                 },
                 ControllerRequest::UpdateRequestConfig => {
-                    if let Ok(mut request_config) = self.app_data.request_config.write() {
+                    let request_config = match self.app_data.active_deck() {
+                        Some(deck) => deck.request_config.clone(),
+                        None => continue,
+                    };
+                    if let Ok(mut request_config) = request_config.write() {
                         if let Ok(mut inner) = view_model.inner.lock() {
                             if let Some(PropertieValue::String(ref endpoint)) =
                                 inner.properties.get(&PropertieKey::CustomServerEndpoint)
@@ -155,7 +372,29 @@ impl ModelController {
                             }
                         }
                     }
+                    retained_controller_requests.insert(ControllerRequest::FetchSynonyms(true));
+                    retained_controller_requests.insert(ControllerRequest::FetchDeckMetadata(true));
                 },
+                ControllerRequest::FetchDeckMetadata(restart) => {
+                    match self.app_data.try_fetch_deck_metadata(restart) {
+                        Err(DownloadState::None) | Err(DownloadState::InProgress) => {
+                            retained_controller_requests
+                                .insert(ControllerRequest::FetchDeckMetadata(false));
+                        }
+                        _ => {
+                            self.refresh_deck_properties(view_model);
+                        }
+                    }
+                }
+                ControllerRequest::FetchSynonyms(restart) => {
+                    match self.app_data.try_fetch_synonyms(restart) {
+                        Err(DownloadState::None) | Err(DownloadState::InProgress) => {
+                            retained_controller_requests
+                                .insert(ControllerRequest::FetchSynonyms(false));
+                        }
+                        _ => {}
+                    }
+                }
                 ControllerRequest::TestCustomServerConnection(update) => {
                     if update {
                         self.app_data.test_custom_server_connection();
@@ -222,11 +461,81 @@ impl ModelController {
                     }
                 }
 
+                ControllerRequest::OptimizeFSRSParameters(start) => {
+                    if start {
+                        let mut parameters = fsrs::DEFAULT_PARAMETERS.to_vec();
+                        view_model.get_property(&PropertieKey::FSRSParameters, |val| {
+                            if let PropertieValue::VecFloat(ref fsrs_parameters) = val {
+                                parameters = fsrs_parameters.clone();
+                            }
+                        });
+                        view_model.remove_property(&PropertieKey::FSRSOptimizerResult);
+
+                        if !self.app_data.start_fsrs_optimizer(&parameters) {
+                            view_model.insert_property(
+                                PropertieKey::Alert,
+                                PropertieValue::String(
+                                    "Not enough review history to optimise the FSRS parameters."
+                                        .to_string(),
+                                ),
+                            );
+                        }
+                    }
+                    let mut is_done = true;
+                    if let Some(ref mut fsrs_optimizer) = self.app_data.fsrs_optimizer {
+                        fsrs_optimizer.step(5);
+                        is_done = fsrs_optimizer.is_done();
+                        view_model.insert_property(
+                            PropertieKey::FSRSOptimizerResult,
+                            PropertieValue::FSRSOptimizerResult(fsrs_optimizer.result()),
+                        );
+                    }
+                    if is_done {
+                        self.app_data.fsrs_optimizer = None;
+                    } else {
+                        retained_controller_requests
+                            .insert(ControllerRequest::OptimizeFSRSParameters(false));
+                    }
+                }
+                ControllerRequest::ApplyFSRSParameters => {
+                    let mut fitted_parameters = None;
+                    view_model.get_property(&PropertieKey::FSRSOptimizerResult, |val| {
+                        if let PropertieValue::FSRSOptimizerResult(ref result) = val {
+                            fitted_parameters = Some(result.parameters.clone());
+                        }
+                    });
+                    if let Some(parameters) = fitted_parameters {
+                        view_model.insert_property(
+                            PropertieKey::FSRSParameters,
+                            PropertieValue::VecFloat(parameters),
+                        );
+                        view_model.remove_property(&PropertieKey::FSRSOptimizerResult);
+                    }
+                }
+
                 ControllerRequest::RefreshCard => {
                     if self.app_data.card_list.len() > 0 {
+                        let choices = self.app_data.multiple_choice_options(0);
+                        let special_characters = self.app_data.special_characters(0);
+                        let deck_self_graded = self
+                            .app_data
+                            .deck_of_card(0)
+                            .and_then(|deck| deck.metadata.self_graded);
                         if let Ok(mut inner) = view_model.inner.lock() {
                             let current_card = &mut self.app_data.card_list[0];
 
+                            match choices {
+                                Some(choices) => {
+                                    inner.properties.insert(
+                                        PropertieKey::CardChoices,
+                                        PropertieValue::VecString(choices),
+                                    );
+                                }
+                                None => {
+                                    inner.properties.remove(&PropertieKey::CardChoices);
+                                }
+                            }
+
                             inner.properties.insert(
                                 PropertieKey::CardQuestion,
                                 PropertieValue::String(current_card.display_data.get_question()),
@@ -235,6 +544,17 @@ impl ModelController {
                                 PropertieKey::CardContext,
                                 PropertieValue::String(current_card.display_data.get_context()),
                             );
+                            match current_card.display_data.get_cloze_context() {
+                                Some(cloze_context) => {
+                                    inner.properties.insert(
+                                        PropertieKey::CardClozeContext,
+                                        PropertieValue::VecString(cloze_context),
+                                    );
+                                }
+                                None => {
+                                    inner.properties.remove(&PropertieKey::CardClozeContext);
+                                }
+                            }
                             inner.properties.insert(
                                 PropertieKey::UserTextInput,
                                 PropertieValue::String("".to_string()),
@@ -248,7 +568,50 @@ impl ModelController {
                             inner.properties.insert(
                                 PropertieKey::CardHasAudio,
                                 PropertieValue::Bool(current_card.display_data.has_audio()),
-                            );                               
+                            );
+                            let is_dictation = current_card.display_data.is_dictation();
+                            inner.properties.insert(
+                                PropertieKey::CardIsDictation,
+                                PropertieValue::Bool(is_dictation),
+                            );
+                            let auto_play_audio = matches!(
+                                inner.properties.get(&PropertieKey::AutoPlayAudio),
+                                Some(PropertieValue::Bool(true))
+                            );
+                            if current_card.display_data.has_audio()
+                                && (is_dictation || auto_play_audio)
+                            {
+                                retained_controller_requests
+                                    .insert(ControllerRequest::PlayCardAudio);
+                            }                               
+                            // the deck decides if its cards are typed or graded by the user.
+                            let self_graded = deck_self_graded.unwrap_or(matches!(
+                                inner.properties.get(&PropertieKey::SelfGraded),
+                                Some(PropertieValue::Bool(true))
+                            ));
+                            inner
+                                .properties
+                                .insert(PropertieKey::CardSelfGraded, PropertieValue::Bool(self_graded));
+                            inner.properties.remove(&PropertieKey::CardAnswer);
+
+                            inner.properties.remove(&PropertieKey::CardHint);
+                            inner.properties.insert(
+                                PropertieKey::CardHintsLeft,
+                                PropertieValue::Usize(if self_graded
+                                    || current_card.display_data.is_multiple_choice()
+                                {
+                                    0
+                                } else {
+                                    hint_levels(&current_card.display_data.get_label())
+                                }),
+                            );
+                            self.app_data.hints_used = 0;
+                            inner.properties.insert(
+                                PropertieKey::SpecialCharacters,
+                                PropertieValue::VecString(special_characters),
+                            );
+                            self.app_data.review_start_timestamp = Some(Date::now());
+                            self.app_data.current_card_key = Some(current_card.meta_data.key());
 
                             retained_controller_requests.insert(ControllerRequest::LoadImage);
                             retained_controller_requests.insert(ControllerRequest::LoadAudio);
@@ -256,9 +619,30 @@ impl ModelController {
                     }
                 }
                 ControllerRequest::FetchNewCard => {
-                    match self.app_data.try_add_new_card() {
-                        Ok(_) => {
+                    let mut add_reverse_cards = false;
+                    view_model.get_property(&PropertieKey::AddReverseCards, |val| {
+                        if let PropertieValue::Bool(ref val) = val {
+                            add_reverse_cards = *val;
+                        }
+                    });
+                    let mut add_dictation_cards = false;
+                    view_model.get_property(&PropertieKey::AddDictationCards, |val| {
+                        if let PropertieValue::Bool(ref val) = val {
+                            add_dictation_cards = *val;
+                        }
+                    });
+                    self.roll_over_daily_stats(view_model, Date::now());
+                    match self
+                        .app_data
+                        .try_add_new_card(add_reverse_cards, add_dictation_cards)
+                    {
+                        Ok(count) => {
                             //retained_controller_requests.insert(ControllerRequest::PlaySound(StaticSounds::BeginningOfLine));
+                            self.app_data.daily_stats.new_cards += count;
+                            view_model.insert_property(
+                                PropertieKey::DoneForToday,
+                                PropertieValue::Bool(false),
+                            );
 
                             if let Ok(mut inner) = view_model.inner.lock() {
                                 retained_controller_requests.insert(ControllerRequest::RefreshCard);
@@ -273,8 +657,6 @@ impl ModelController {
                                     PropertieValue::String(format!("{:?}", err)),
                                 );
 
-                                self.app_data.card_download = None;
-
                                 retained_controller_requests.insert(ControllerRequest::RefreshCard);
                             }
                             _ => {
@@ -284,79 +666,190 @@ impl ModelController {
                         },
                     }
                 }
+                ControllerRequest::ShowHint => {
+                    if self.app_data.card_list.len() == 0 {
+                        return;
+                    }
+                    let label = self.app_data.card_list[0].display_data.get_label();
+                    let levels = hint_levels(&label);
+                    self.app_data.hints_used = (self.app_data.hints_used + 1).min(levels);
+
+                    if let Ok(mut inner) = view_model.inner.lock() {
+                        inner.properties.insert(
+                            PropertieKey::CardHint,
+                            PropertieValue::String(hint(&label, self.app_data.hints_used)),
+                        );
+                        inner.properties.insert(
+                            PropertieKey::CardHintsLeft,
+                            PropertieValue::Usize(levels - self.app_data.hints_used),
+                        );
+                    }
+                }
+                ControllerRequest::ShowAnswer => {
+                    if self.app_data.card_list.len() == 0 {
+                        return;
+                    }
+                    view_model.insert_property(
+                        PropertieKey::CardAnswer,
+                        PropertieValue::String(self.app_data.card_list[0].display_data.get_label()),
+                    );
+                }
+                ControllerRequest::GradeReview(grade) => {
+                    if self.app_data.card_list.len() == 0 {
+                        return;
+                    }
+                    let quality = grade.score();
+                    self.record_review(view_model, quality, Date::now(), &mut retained_controller_requests);
+
+                    if is_success(quality) {
+                        retained_controller_requests
+                            .insert(ControllerRequest::PlaySound(StaticSounds::BeginningOfLine));
+                    } else {
+                        retained_controller_requests
+                            .insert(ControllerRequest::PlaySound(StaticSounds::ServiceLogout));
+                    }
+                    retained_controller_requests.insert(ControllerRequest::UpdateCardList);
+                    retained_controller_requests.insert(ControllerRequest::CloseReview);
+                }
                 ControllerRequest::CheckReview => {
                     if self.app_data.card_list.len() == 0 {
                         return;
                     }
 
-                    let mut spelling_correction_threshold: usize = 1;
+                    let mut typo_tolerance = TypoTolerance::default();
+                    let mut word_order_tolerant = false;
+                    let mut multiple_choice_weight: f32 = 0.2;
+                    let mut is_multiple_choice = false;
 
-                    let mut label = self.app_data.card_list[0].display_data.get_label();
+                    let accepted_labels = self.app_data.accepted_labels(0);
                     let mut output = "".to_string();
 
+                    let mut normalization = NormalizationPipeline::default();
+                    let mut deck_match_overrides = true;
+
                     if let Ok(mut inner) = view_model.inner.lock() {
                         if let Some(PropertieValue::String(ref user_text_input)) =
                             inner.properties.get(&PropertieKey::UserTextInput)
                         {
                             output.push_str(user_text_input);
                         }
+                        is_multiple_choice = inner.properties.contains_key(&PropertieKey::CardChoices);
+                        if let Some(PropertieValue::Float(ref val)) =
+                            inner.properties.get(&PropertieKey::MultipleChoiceRecallWeight)
+                        {
+                            multiple_choice_weight = *val;
+                        }
+                        if let Some(PropertieValue::NormalizationPipeline(ref val)) =
+                            inner.properties.get(&PropertieKey::Normalization)
+                        {
+                            normalization = val.clone();
+                        }
+                        if let Some(PropertieValue::TypoTolerance(ref val)) =
+                            inner.properties.get(&PropertieKey::TypoTolerance)
+                        {
+                            typo_tolerance = *val;
+                        }
                         if let Some(PropertieValue::Bool(ref val)) =
-                            inner.properties.get(&PropertieKey::MatchASCII)
+                            inner.properties.get(&PropertieKey::WordOrderTolerant)
                         {
-                            if *val {
-                                label = any_ascii(&label);
-                                output = any_ascii(&output);
-                            }
+                            word_order_tolerant = *val;
                         }
                         if let Some(PropertieValue::Bool(ref val)) =
-                            inner.properties.get(&PropertieKey::MatchCase)
+                            inner.properties.get(&PropertieKey::DeckMatchOverrides)
                         {
-                            if !*val {
-                                label = label.to_lowercase();
-                                output = output.to_lowercase();
-                            }
+                            deck_match_overrides = *val;
                         }
-                        if let Some(PropertieValue::Bool(ref val)) = inner
-                            .properties
-                            .get(&PropertieKey::IgnoreSentencePunctuationSymbols)
+                    }
+
+                    // the deck author knows best how answers in the deck's language compare.
+                    let deck_metadata = self.app_data.deck_of_card(0).map(|deck| &deck.metadata);
+                    if deck_match_overrides {
+                        if let Some(deck_normalization) =
+                            deck_metadata.and_then(|metadata| metadata.normalization_pipeline())
                         {
-                            if *val {
-                                label = label.replace(
-                                    &['?', '(', ')', ',', '\"', '.', ';', ':', '\''][..],
-                                    "",
-                                );
-                                output = output.replace(
-                                    &['?', '(', ')', ',', '\"', '.', ';', ':', '\''][..],
-                                    "",
-                                );
-                            }
+                            normalization = deck_normalization;
                         }
-                        if let Some(PropertieValue::Usize(ref val)) = inner
-                            .properties
-                            .get(&PropertieKey::SpellingCorrectionThreshold)
+                        let card_label = self.app_data.card_list[0].display_data.get_label();
+                        let card_match_overrides = self.app_data.card_list[0]
+                            .display_data
+                            .match_overrides
+                            .as_ref();
+                        for match_overrides in [
+                            deck_metadata.and_then(|metadata| metadata.match_overrides.as_ref()),
+                            card_match_overrides,
+                        ]
+                        .into_iter()
+                        .flatten()
                         {
-                            spelling_correction_threshold = *val;
+                            match_overrides.apply(
+                                &mut normalization,
+                                &mut typo_tolerance,
+                                &mut word_order_tolerant,
+                                &card_label,
+                            );
                         }
                     }
+                    // invalid patterns of the user are reported in the options, they are left out until fixed.
+                    let normalization = normalization.compile();
+                    let normalize = |text: &str| -> String { normalization.apply(text) };
+                    let output = normalize(&output);
+                    let typos_of = |label: &str| -> Typos {
+                        if word_order_tolerant {
+                            count_typos_any_order(label, &output, &typo_tolerance)
+                        } else {
+                            count_typos(label, &output, &typo_tolerance)
+                        }
+                    };
+
+                    // the answer is compared with the closest accepted label.
+                    let (closest_index, label, typos) = accepted_labels
+                        .iter()
+                        .map(|label| normalize(label))
+                        .enumerate()
+                        .map(|(i, label)| (i, typos_of(&label), label))
+                        .min_by(|a, b| a.1.distance.total_cmp(&b.1.distance))
+                        .map(|(i, typos, label)| (i, label, typos))
+                        .unwrap_or_else(|| (0, "".to_string(), typos_of("")));
+                    let other_accepted_labels: Vec<String> = accepted_labels
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| *i != closest_index)
+                        .map(|(_, label)| label.to_owned())
+                        .collect();
 
-                    let score = edit_distance::edit_distance(&output, &label)
-                        <= spelling_correction_threshold;
+                    let now = Date::now();
+                    let answer_time = now - self.app_data.review_start_timestamp.unwrap_or(now);
+
+                    let quality = if is_multiple_choice {
+                        recognition_quality(output == label, multiple_choice_weight as f64)
+                    } else {
+                        hinted_quality(
+                            recall_quality(
+                                &typos,
+                                label.chars().count(),
+                                answer_time,
+                            ),
+                            self.app_data.hints_used,
+                            hint_levels(&self.app_data.card_list[0].display_data.get_label()),
+                        )
+                    };
+                    let score = is_success(quality);
 
-                    self.app_data.card_list[0]
-                        .meta_data
-                        .timestamps
-                        .push(Date::now());
-                    self.app_data.card_list[0].meta_data.scores.push(score);
+                    self.record_review(view_model, quality, now, &mut retained_controller_requests);
 
-                    let changeset = Changeset::new(&label, &output, "");
+                    let differences = diff_tokens(&label, &output);
 
                     if let Ok(mut inner) = view_model.inner.lock() {
                         inner
                             .properties
                             .insert(PropertieKey::ReviewScore, PropertieValue::Bool(score));
+                        inner.properties.insert(
+                            PropertieKey::ReviewAlternatives,
+                            PropertieValue::VecString(other_accepted_labels),
+                        );
                         inner.volatile_properties.insert(
                             VolatilePropertieKey::Differences,
-                            VolatilePropertieValue::Differences(changeset.diffs),
+                            VolatilePropertieValue::Differences(differences),
                         );
                     }
 
@@ -369,16 +862,232 @@ impl ModelController {
                     }
                     retained_controller_requests.insert(ControllerRequest::UpdateCardList);
                 }
+                ControllerRequest::SuspendCard | ControllerRequest::BuryCard => {
+                    let now = Date::now();
+                    let bury_until = study_day_start(now, self.day_rollover_hour(view_model)) + ONE_DAY;
+
+                    if let Some(card_key) = self.app_data.current_card_key.clone() {
+                        if let Some(card) = self.app_data.get_card_mut(&card_key) {
+                            if request == ControllerRequest::SuspendCard {
+                                card.meta_data.suspended = true;
+                            } else {
+                                card.meta_data.buried_until = Some(bury_until);
+                            }
+                        }
+                    }
+                    retained_controller_requests.insert(ControllerRequest::RefreshSuspendedCards);
+                    retained_controller_requests.insert(ControllerRequest::CloseReview);
+                }
+                ControllerRequest::RestoreCard(card_key) => {
+                    if let Some(card) = self.app_data.get_card_mut(&card_key) {
+                        card.meta_data.suspended = false;
+                        card.meta_data.leech = false;
+                        card.meta_data.lapses = 0;
+                    }
+                    retained_controller_requests.insert(ControllerRequest::RefreshSuspendedCards);
+
+                    view_model.get_property(&PropertieKey::DoneForToday, |val| {
+                        if let PropertieValue::Bool(true) = val {
+                            retained_controller_requests
+                                .insert(ControllerRequest::FetchNewCardOrContinue);
+                        }
+                    });
+                }
+                ControllerRequest::RefreshSuspendedCards => {
+                    view_model.insert_property(
+                        PropertieKey::SuspendedCards,
+                        PropertieValue::CardList(self.app_data.suspended_cards()),
+                    );
+                    view_model.insert_property(
+                        PropertieKey::DeckSummary,
+                        PropertieValue::String(self.app_data.deck_summary()),
+                    );
+                }
+                ControllerRequest::RefreshDecks => {
+                    self.refresh_deck_properties(view_model);
+                }
+                ControllerRequest::AddDeck => {
+                    self.app_data.add_deck();
+                    retained_controller_requests.insert(ControllerRequest::RefreshRequestConfig);
+                    retained_controller_requests.insert(ControllerRequest::RefreshDecks);
+                }
+                ControllerRequest::EditDeck(key) => {
+                    if self.app_data.deck(key).is_some() {
+                        self.app_data.active_deck = key;
+                    }
+                    retained_controller_requests.insert(ControllerRequest::RefreshRequestConfig);
+                    retained_controller_requests.insert(ControllerRequest::RefreshDecks);
+                }
+                ControllerRequest::RemoveDeck(key) => {
+                    self.app_data.remove_deck(key);
+                    retained_controller_requests.insert(ControllerRequest::RefreshRequestConfig);
+                    retained_controller_requests.insert(ControllerRequest::RefreshDecks);
+                    retained_controller_requests.insert(ControllerRequest::RefreshSuspendedCards);
+                    // the current card may have been one of the removed deck.
+                    retained_controller_requests.insert(ControllerRequest::CloseReview);
+                }
+                ControllerRequest::ImportAnkiDeck(pick) => {
+                    if pick {
+                        self.app_data.pick_local_file("Anki deck", &["apkg"]);
+                        retained_controller_requests.insert(ControllerRequest::ImportAnkiDeck(false));
+                        continue;
+                    }
+                    let (file_name, bytes) = match self.app_data.try_read_local_file() {
+                        Ok(file) => file,
+                        Err(DownloadState::InProgress) => {
+                            retained_controller_requests
+                                .insert(ControllerRequest::ImportAnkiDeck(false));
+                            continue;
+                        }
+                        Err(_) => continue,
+                    };
+                    let mut mapping = AnkiFieldMapping::default();
+                    view_model.get_property(&PropertieKey::AnkiFieldMapping, |val| {
+                        if let PropertieValue::AnkiFieldMapping(ref val) = val {
+                            mapping = val.to_owned();
+                        }
+                    });
+                    let message = match parse_apkg(&bytes, &file_name, &mapping) {
+                        Ok(imported_deck) => {
+                            let name = imported_deck.name.to_owned();
+                            match self.app_data.add_imported_deck(imported_deck) {
+                                Ok((card_count, skipped)) => {
+                                    retained_controller_requests.insert(ControllerRequest::RefreshDecks);
+                                    retained_controller_requests
+                                        .insert(ControllerRequest::RefreshSuspendedCards);
+                                    retained_controller_requests
+                                        .insert(ControllerRequest::FetchNewCardOrContinue);
+                                    format!(
+                                        "Imported {} cards into the deck \"{}\".{}",
+                                        card_count,
+                                        name,
+                                        skipped_message(&skipped, "notes")
+                                    )
+                                }
+                                Err(err) => err,
+                            }
+                        }
+                        Err(err) => err,
+                    };
+                    view_model.insert_property(PropertieKey::Alert, PropertieValue::String(message));
+                }
+                ControllerRequest::PickCsvFile(pick) => {
+                    if pick {
+                        self.app_data
+                            .pick_local_file("CSV or TSV file", &["csv", "tsv", "txt"]);
+                        retained_controller_requests.insert(ControllerRequest::PickCsvFile(false));
+                        continue;
+                    }
+                    let (file_name, bytes) = match self.app_data.try_read_local_file() {
+                        Ok(file) => file,
+                        Err(DownloadState::InProgress) => {
+                            retained_controller_requests.insert(ControllerRequest::PickCsvFile(false));
+                            continue;
+                        }
+                        Err(_) => continue,
+                    };
+                    match self.app_data.read_csv_table(&file_name, &bytes) {
+                        Ok(preview) => {
+                            if let Ok(mut inner) = view_model.inner.lock() {
+                                inner.volatile_properties.insert(
+                                    VolatilePropertieKey::CsvPreview,
+                                    VolatilePropertieValue::CsvPreview(preview),
+                                );
+                            }
+                        }
+                        Err(err) => {
+                            view_model.insert_property(PropertieKey::Alert, PropertieValue::String(err));
+                        }
+                    }
+                }
+                ControllerRequest::ImportCsvDeck => {
+                    // a local deck has no server to load the media paths from.
+                    let mapping = CsvColumnMapping {
+                        audio_column: None,
+                        image_column: None,
+                        ..self.csv_column_mapping(view_model)
+                    };
+                    let imported_deck = match self.app_data.csv_table() {
+                        Some(csv_table) => csv_table.imported_deck(&mapping),
+                        None => continue,
+                    };
+                    let name = imported_deck.name.to_owned();
+                    let message = match self.app_data.add_imported_deck(imported_deck) {
+                        Ok((card_count, skipped)) => {
+                            retained_controller_requests.insert(ControllerRequest::CloseCsvImport);
+                            retained_controller_requests.insert(ControllerRequest::RefreshDecks);
+                            retained_controller_requests
+                                .insert(ControllerRequest::FetchNewCardOrContinue);
+                            format!(
+                                "Imported {} cards into the deck \"{}\".{}",
+                                card_count,
+                                name,
+                                skipped_message(&skipped, "rows")
+                            )
+                        }
+                        Err(err) => err,
+                    };
+                    view_model.insert_property(PropertieKey::Alert, PropertieValue::String(message));
+                }
+                ControllerRequest::ExportCsvCardFiles => {
+                    let mapping = self.csv_column_mapping(view_model);
+                    let csv_table = match self.app_data.csv_table() {
+                        Some(csv_table) => csv_table,
+                        None => continue,
+                    };
+                    let (card_files, skipped) = csv_table.card_files(&mapping);
+                    let folder_name = slug(&csv_table.file_name);
+                    let file_name = format!("{}.zip", folder_name);
+                    let saved = card_folder(&folder_name, &csv_table.deck_name(), &card_files)
+                        .and_then(|bytes| save_file(&file_name, &bytes));
+                    let message = match saved {
+                        Ok(()) => format!(
+                            "Saved {} card files to {}, unzip it into file_server/static.{}",
+                            card_files.len(),
+                            file_name,
+                            skipped_message(&skipped, "rows")
+                        ),
+                        Err(err) => err,
+                    };
+                    view_model.insert_property(PropertieKey::Alert, PropertieValue::String(message));
+                }
+                ControllerRequest::CloseCsvImport => {
+                    self.app_data.close_csv_table();
+                    if let Ok(mut inner) = view_model.inner.lock() {
+                        inner
+                            .volatile_properties
+                            .remove(&VolatilePropertieKey::CsvPreview);
+                    }
+                }
+                ControllerRequest::UpdateDecks => {
+                    let mut deck_settings = Vec::new();
+                    view_model.get_property(&PropertieKey::Decks, |val| {
+                        if let PropertieValue::DeckList(ref val) = val {
+                            deck_settings = val.to_owned();
+                        }
+                    });
+                    self.app_data.update_decks(&deck_settings);
+                    retained_controller_requests.insert(ControllerRequest::RefreshDecks);
+
+                    view_model.get_property(&PropertieKey::DoneForToday, |val| {
+                        if let PropertieValue::Bool(true) = val {
+                            retained_controller_requests
+                                .insert(ControllerRequest::FetchNewCardOrContinue);
+                        }
+                    });
+                }
                 ControllerRequest::CloseReview => {
                     if let Ok(mut inner) = view_model.inner.lock() {
                         inner.properties.remove(&PropertieKey::ReviewScore);
+                        inner.properties.remove(&PropertieKey::ReviewLeech);
+                        inner.properties.remove(&PropertieKey::ReviewAlternatives);
                         inner
                             .volatile_properties
                             .remove(&VolatilePropertieKey::Differences);
                     }
 
                     retained_controller_requests
-                        .insert(ControllerRequest::FetchNewCardAtThresholdOrContinue);
+                        .insert(ControllerRequest::FetchNewCardOrContinue);
                 }
                 ControllerRequest::UpdateCardList => {
                     if self.app_data.card_list.len() == 0 {
@@ -391,128 +1100,80 @@ impl ModelController {
                         }
                     });
 
-                    let mut list: HashMap<u16, u64> = HashMap::new();
+                    self.update_review_queue(view_model, Date::now());
+
                     let mut count_score_true: usize = 0;
                     let mut count_score_false: usize = 0;
                     let mut count_score_undefined: usize = 0;
-                    let mut timeout_list: HashMap<u16, bool> = HashMap::new();
 
-                    for i in 0..self.app_data.card_list.len() {
-                        {
-                            let last_three_indices =
-                                (0..self.app_data.card_list[i].meta_data.scores.len())
-                                    .rev()
-                                    .take(3);
-                            count_score_true += last_three_indices
-                                .clone()
-                                .filter_map(|x| {
-                                    match self.app_data.card_list[i].meta_data.scores[x] {
-                                        true => Some(true),
-                                        _ => None,
-                                    }
-                                })
-                                .count();
-                            count_score_false += last_three_indices
-                                .filter_map(|x| {
-                                    match self.app_data.card_list[i].meta_data.scores[x] {
-                                        false => Some(false),
-                                        _ => None,
-                                    }
-                                })
-                                .count();
-                            count_score_undefined +=
-                                if self.app_data.card_list[i].meta_data.scores.len() == 0 {
-                                    1usize
-                                } else {
-                                    0usize
-                                };
-                        }
-                        {
-                            let time: f64 = estimate_next_session_timestamp(
-                                &mut self.app_data.space_repetition_model,
-                                &mut self.app_data.card_list[i].meta_data,
-                            );
-                            // let date = Date::new(&wasm_bindgen::JsValue::from_f64(time));
-                            /*
-                            super::log(&format!(
-                                "Card_ID: {}\nCard_Question: {}\n{:?}\n{:?}",
-                                self.card_list[i].meta_data.id,
-                                self.card_list[i].display_data.get_context(),
-                                time,
-                                date.to_iso_string()
-                            ));
-                            */
-                            list.insert(
-                                self.app_data.card_list[i].meta_data.id,
-                                time.trunc() as u64,
-                            );
-                        }
-                        {
-                            // check if there are at least 3 timestamps within the last 7 minutes
-                            let seven_min_ago: f64 = Date::now() - 1000.0 * 60.0 * 7.0;
-                            timeout_list.insert(
-                                self.app_data.card_list[i].meta_data.id,
-                                self.app_data.card_list[i]
-                                    .meta_data
-                                    .timestamps
-                                    .iter()
-                                    .filter(|&t| t >= &seven_min_ago)
-                                    .count()
-                                    >= 3usize,
-                            );
-                        }
+                    for card in &self.app_data.card_list {
+                        let last_three_scores = card.meta_data.scores.iter().rev().take(3);
+                        count_score_true += last_three_scores
+                            .clone()
+                            .filter(|score| is_success(**score))
+                            .count();
+                        count_score_false += last_three_scores
+                            .filter(|score| !is_success(**score))
+                            .count();
+                        count_score_undefined += if card.meta_data.scores.len() == 0 {
+                            1usize
+                        } else {
+                            0usize
+                        };
                     }
 
                     let progress = count_score_true as f32
                         / ((count_score_true + count_score_false + count_score_undefined) as f32);
 
-                    let reviewed_card_id = self.app_data.card_list[0].meta_data.id;
-                    self.app_data
-                        .card_list
-                        .sort_unstable_by_key(|card| list.get(&card.meta_data.id));
-
-                    // maintaining previous sort, and sort by timeout_list
-                    // cards in timeout are moved to the end of the card_list.
-                    self.app_data
-                        .card_list
-                        .sort_by_key(|card| timeout_list.get(&card.meta_data.id).map(|x| *x as u8));
-
-                    // this prevents the same card being shown twice
-                    if self.app_data.card_list.len() > 1
-                        && self.app_data.card_list[0].meta_data.id == reviewed_card_id
-                    {
-                        self.app_data.card_list.swap(0, 1);
-                    }
-
                     view_model
                         .insert_property(PropertieKey::Progress, PropertieValue::Float(progress));
                 }
-                ControllerRequest::FetchNewCardAtThresholdOrContinue => {
-                    let mut progress = 0.0;
+                ControllerRequest::FetchNewCardOrContinue => {
+                    let now = Date::now();
+                    let due_card_count = self.update_review_queue(view_model, now);
+                    self.roll_over_daily_stats(view_model, now);
 
-                    view_model.get_property(&PropertieKey::Progress, |val| {
-                        if let PropertieValue::Float(ref val) = val {
-                            progress = *val;
-                        }
-                    });
-
-                    let mut fetch_new_card_at_threshold = false;
+                    let mut fetch_next_card = false;
                     view_model.get_property(&PropertieKey::FetchNextCardAtThreshold, |val| {
                         if let PropertieValue::Bool(ref val) = val {
-                            fetch_new_card_at_threshold = *val;
+                            fetch_next_card = *val;
+                        }
+                    });
+                    let mut new_cards_per_day = 0;
+                    view_model.get_property(&PropertieKey::NewCardsPerDay, |val| {
+                        if let PropertieValue::Usize(ref val) = val {
+                            new_cards_per_day = *val;
                         }
                     });
-                    let mut add_new_card_threshold = 100.0;
-                    view_model.get_property(&PropertieKey::AddNewCardThreshold, |val| {
-                        if let PropertieValue::Float(ref val) = val {
-                            add_new_card_threshold = *val;
+                    let mut maximum_reviews_per_day = 0;
+                    view_model.get_property(&PropertieKey::MaximumReviewsPerDay, |val| {
+                        if let PropertieValue::Usize(ref val) = val {
+                            maximum_reviews_per_day = *val;
                         }
                     });
+                    let can_fetch_new_card = self.app_data.can_add_new_card();
+
+                    let daily_stats = &self.app_data.daily_stats;
+                    let review_limit_reached = daily_stats.reviews >= maximum_reviews_per_day;
 
-                    if fetch_new_card_at_threshold && progress * 100.0 >= add_new_card_threshold {
+                    if !review_limit_reached && due_card_count > 0 {
+                        view_model.insert_property(
+                            PropertieKey::DoneForToday,
+                            PropertieValue::Bool(false),
+                        );
+                        retained_controller_requests.insert(ControllerRequest::RefreshCard);
+                    } else if !review_limit_reached
+                        && fetch_next_card
+                        && can_fetch_new_card
+                        && daily_stats.new_cards < new_cards_per_day
+                    {
                         retained_controller_requests.insert(ControllerRequest::FetchNewCard);
                     } else {
-                        retained_controller_requests.insert(ControllerRequest::RefreshCard);
+                        // a fresh app without any cards keeps showing the welcome text.
+                        view_model.insert_property(
+                            PropertieKey::DoneForToday,
+                            PropertieValue::Bool(!self.app_data.card_list.is_empty()),
+                        );
                     }
                 }
                 ControllerRequest::HideAlert => {
@@ -590,6 +1251,7 @@ impl Default for ModelController {
         Self {
             app_data: AppData::default(),
             checkpoints: HashMap::new(),
+            last_review_queue_check: 0.0,
         }
     }
 }
@@ -6,6 +6,7 @@ use crate::app_controller::view_controller::view_model_controller::view_model::{
     VolatilePropertieValue,
 };
 use crate::app_controller::ViewModel;
+use crate::app_controller::model_controller::data_model::spaced_repetition::Grade;
 
 use std::ops::Sub;
 
@@ -19,6 +20,25 @@ impl Default for AppDisplay {
 }
 
 impl AppDisplay {
+    fn show_done_for_today(&mut self, ui: &mut egui::Ui) {
+        ui.allocate_space(egui::Vec2 { x: 0.0, y: 10.0 });
+        ui.add(
+            egui::Label::new(egui::RichText::new("🎉 You're done for today!").heading()).wrap(true),
+        );
+        ui.separator();
+        ui.allocate_space(egui::Vec2 { x: 0.0, y: 20.0 });
+        ui.add(
+            egui::Label::new(
+                egui::RichText::new(
+                    "No card is due right now. Come back later or fetch a new card (📥).",
+                )
+                .color(egui::Color32::WHITE)
+                .size(20.0),
+            )
+            .wrap(true),
+        );
+    }
+
     fn show_card(&mut self, ui: &mut egui::Ui, inner: &mut InnerViewModel) {
         ui.allocate_space(egui::Vec2 { x: 0.0, y: 10.0 });
 
@@ -42,14 +62,22 @@ impl AppDisplay {
                 
             };
 
+            let is_dictation = matches!(
+                inner.properties.get(&PropertieKey::CardIsDictation),
+                Some(PropertieValue::Bool(true))
+            );
+
             if has_audio {
                 let mut play_audio_symbol = "🔇"; // <- stop audio from playing, needs to be implemented.
                 play_audio_symbol = "🔊";
 
                 if ui
                     .add(
-                        egui::Button::new(egui::RichText::new(play_audio_symbol).size(20.0))
-                            .frame(false),
+                        egui::Button::new(
+                            egui::RichText::new(play_audio_symbol)
+                                .size(if is_dictation { 40.0 } else { 20.0 }),
+                        )
+                        .frame(false),
                     )
                     .clicked()
                 {
@@ -59,7 +87,36 @@ impl AppDisplay {
                 }
             }
 
-            if let Some(PropertieValue::String(ref card_context)) =
+            if is_dictation {
+                // the context is what the learner has to type.
+            } else if let Some(PropertieValue::VecString(ref cloze_context)) =
+                inner.properties.get(&PropertieKey::CardClozeContext)
+            {
+                ui.horizontal_wrapped(|ui| {
+                    ui.spacing_mut().item_spacing.x = 0.0;
+                    for (i, segment) in cloze_context.iter().enumerate() {
+                        if i % 2 == 0 {
+                            ui.label(
+                                egui::RichText::new(segment)
+                                    .color(egui::Color32::WHITE)
+                                    .size(20.0),
+                            );
+                        } else {
+                            ui.label(
+                                egui::RichText::new(if segment.is_empty() {
+                                    " ______ ".to_string()
+                                } else {
+                                    format!(" {} ", segment)
+                                })
+                                .color(egui::Color32::BLACK)
+                                .background_color(egui::Color32::LIGHT_BLUE)
+                                .size(20.0)
+                                .strong(),
+                            );
+                        }
+                    }
+                });
+            } else if let Some(PropertieValue::String(ref card_context)) =
                 inner.properties.get(&PropertieKey::CardContext)
             {
                 ui.add(
@@ -73,6 +130,38 @@ impl AppDisplay {
             }
         });
 
+        let hints_left = match inner.properties.get(&PropertieKey::CardHintsLeft) {
+            Some(PropertieValue::Usize(val)) => *val,
+            _ => 0,
+        };
+        let card_hint = match inner.properties.get(&PropertieKey::CardHint) {
+            Some(PropertieValue::String(val)) => Some(val.to_owned()),
+            _ => None,
+        };
+        if hints_left > 0 || card_hint.is_some() {
+            ui.allocate_space(egui::Vec2 { x: 0.0, y: 10.0 });
+            ui.horizontal_wrapped(|ui| {
+                if ui
+                    .add_enabled(
+                        hints_left > 0,
+                        egui::Button::new(egui::RichText::new("💡 Hint").size(16.0)),
+                    )
+                    .on_hover_text("Each hint reveals more of the answer and lowers its score.")
+                    .clicked()
+                {
+                    inner.controller_requests.insert(ControllerRequest::ShowHint);
+                }
+                if let Some(card_hint) = card_hint {
+                    ui.label(
+                        egui::RichText::new(card_hint)
+                            .color(egui::Color32::LIGHT_GRAY)
+                            .monospace()
+                            .size(18.0),
+                    );
+                }
+            });
+        }
+
         ui.allocate_space(egui::Vec2 { x: 0.0, y: 10.0 });
         ui.vertical_centered_justified(|ui| {
             if let Some(VolatilePropertieValue::Image(ref image)) = inner
@@ -149,11 +238,101 @@ impl WindowViewModel for AppDisplay {
                             text_input_placeholder.push_str(val);
                         }
 
-                        self.show_card(ui, &mut inner);
+                        let done_for_today = matches!(
+                            inner.properties.get(&PropertieKey::DoneForToday),
+                            Some(PropertieValue::Bool(true))
+                        );
 
-                        if let Some(PropertieValue::String(ref mut text_input)) =
-                            inner.properties.get_mut(&PropertieKey::UserTextInput)
+                        if done_for_today {
+                            self.show_done_for_today(ui);
+                        } else {
+                            self.show_card(ui, &mut inner);
+                        }
+
+                        let mut special_characters = Vec::new();
+                        if let (
+                            Some(PropertieValue::Bool(true)),
+                            Some(PropertieValue::VecString(ref val)),
+                        ) = (
+                            inner.properties.get(&PropertieKey::ShowSpecialCharacters),
+                            inner.properties.get(&PropertieKey::SpecialCharacters),
+                        ) {
+                            special_characters = val.clone();
+                        }
+
+                        let self_graded = matches!(
+                            inner.properties.get(&PropertieKey::CardSelfGraded),
+                            Some(PropertieValue::Bool(true))
+                        );
+                        let mut card_answer = None;
+                        if let Some(PropertieValue::String(ref val)) =
+                            inner.properties.get(&PropertieKey::CardAnswer)
                         {
+                            card_answer = Some(val.clone());
+                        }
+
+                        let mut choices = None;
+                        if let Some(PropertieValue::VecString(ref val)) =
+                            inner.properties.get(&PropertieKey::CardChoices)
+                        {
+                            choices = Some(val.clone());
+                        }
+
+                        if !done_for_today && self_graded {
+                            if let Some(ref card_answer) = card_answer {
+                                ui.add(
+                                    egui::Label::new(
+                                        egui::RichText::new(card_answer)
+                                            .color(egui::Color32::GREEN)
+                                            .size(20.0),
+                                    )
+                                    .wrap(true),
+                                );
+                            }
+                        } else if let (false, Some(choices)) = (done_for_today, &choices) {
+                            let mut chosen = None;
+                            ui.vertical_centered_justified(|ui| {
+                                for choice in choices {
+                                    if ui
+                                        .add_sized(
+                                            [ui.available_width(), 40.0],
+                                            egui::Button::new(
+                                                egui::RichText::new(choice)
+                                                    .color(egui::Color32::WHITE)
+                                                    .size(20.0),
+                                            ),
+                                        )
+                                        .clicked()
+                                    {
+                                        chosen = Some(choice.to_owned());
+                                    }
+                                }
+                            });
+                            if let Some(choice) = chosen {
+                                inner.properties.insert(
+                                    PropertieKey::UserTextInput,
+                                    PropertieValue::String(choice),
+                                );
+                                inner
+                                    .controller_requests
+                                    .insert(ControllerRequest::CheckReview);
+                            }
+                        } else if let (false, Some(PropertieValue::String(ref mut text_input))) = (
+                            done_for_today,
+                            inner.properties.get_mut(&PropertieKey::UserTextInput),
+                        ) {
+                            if !special_characters.is_empty() {
+                                ui.horizontal_wrapped(|ui| {
+                                    for special_character in &special_characters {
+                                        if ui
+                                            .button(egui::RichText::new(special_character).size(18.0))
+                                            .clicked()
+                                        {
+                                            text_input.push_str(special_character);
+                                        }
+                                    }
+                                });
+                            }
                             let _text_input_response = ui.add_sized(
                                 ui.available_size().sub(
                                     [
@@ -196,19 +375,69 @@ impl WindowViewModel for AppDisplay {
                             });
                         }
 
-                        ui.with_layout(
-                            egui::Layout::left_to_right(egui::Align::BOTTOM)
-                                .with_main_justify(true),
-                            |ui| {
-                                let check =
-                                    ui.add(egui::Button::new(egui::RichText::new("").size(30.0)));
-                                if check.clicked() {
-                                    inner
-                                        .controller_requests
-                                        .insert(ControllerRequest::CheckReview);
-                                }
-                            },
-                        );
+                        if !done_for_today && self_graded {
+                            let mut grade_review = None;
+                            ui.with_layout(
+                                egui::Layout::left_to_right(egui::Align::BOTTOM)
+                                    .with_main_justify(true),
+                                |ui| {
+                                    if card_answer.is_none() {
+                                        let show_answer = ui.add(egui::Button::new(
+                                            egui::RichText::new("SHOW ANSWER").size(20.0),
+                                        ));
+                                        if show_answer.clicked() {
+                                            inner
+                                                .controller_requests
+                                                .insert(ControllerRequest::ShowAnswer);
+                                        }
+                                    } else {
+                                        ui.columns(4, |columns| {
+                                            for (column, (grade, text, color)) in
+                                                columns.iter_mut().zip([
+                                                    (Grade::Again, "AGAIN", egui::Color32::RED),
+                                                    (Grade::Hard, "HARD", egui::Color32::YELLOW),
+                                                    (Grade::Good, "GOOD", egui::Color32::GREEN),
+                                                    (Grade::Easy, "EASY", egui::Color32::LIGHT_BLUE),
+                                                ])
+                                            {
+                                                let button = column.add_sized(
+                                                    [column.available_width(), 40.0],
+                                                    egui::Button::new(
+                                                        egui::RichText::new(text)
+                                                            .strong()
+                                                            .monospace()
+                                                            .color(egui::Color32::BLACK),
+                                                    )
+                                                    .fill(color),
+                                                );
+                                                if button.clicked() {
+                                                    grade_review = Some(grade);
+                                                }
+                                            }
+                                        });
+                                    }
+                                },
+                            );
+                            if let Some(grade) = grade_review {
+                                inner
+                                    .controller_requests
+                                    .insert(ControllerRequest::GradeReview(grade));
+                            }
+                        } else if !done_for_today && choices.is_none() {
+                            ui.with_layout(
+                                egui::Layout::left_to_right(egui::Align::BOTTOM)
+                                    .with_main_justify(true),
+                                |ui| {
+                                    let check = ui
+                                        .add(egui::Button::new(egui::RichText::new("").size(30.0)));
+                                    if check.clicked() {
+                                        inner
+                                            .controller_requests
+                                            .insert(ControllerRequest::CheckReview);
+                                    }
+                                },
+                            );
+                        }
                     }
                     _ => {}
                 }
@@ -6,7 +6,9 @@ use crate::app_controller::view_controller::view_model_controller::view_model::{
     PropertieKey, PropertieValue, VolatilePropertieKey, VolatilePropertieValue,
 };
 use crate::app_controller::ViewModel;
-use difference::Difference;
+use crate::app_controller::model_controller::data_model::token_diff::{
+    is_correct, is_reordered, TokenClass,
+};
 
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct ReviewDisplay {}
@@ -72,19 +74,36 @@ impl DisplayViewModel for ReviewDisplay {
             {
                 score = *val;
             }
+            let mut alternatives = Vec::new();
+            if let Some(PropertieValue::VecString(ref val)) =
+                inner.properties.get(&PropertieKey::ReviewAlternatives)
+            {
+                alternatives = val.clone();
+            }
+            let mut leech = None;
+            if let Some(PropertieValue::Bool(ref val)) =
+                inner.properties.get(&PropertieKey::ReviewLeech)
+            {
+                leech = Some(*val);
+            }
             if let Some(VolatilePropertieValue::Differences(ref differences)) = inner
                 .volatile_properties
                 .get(&VolatilePropertieKey::Differences)
             {
-                let is_equal = differences.len() <= 1; // or <=1
+                let is_equal = is_correct(differences);
+                let is_reordered = is_reordered(differences);
                 ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
                     ui.label(
                         egui::RichText::new(if score {
                             if is_equal {
                                 "  ✅ Excellent!"
+                            } else if is_reordered {
+                                "  ✅ Correct words, different order:"
                             } else {
-                                "  ✅ You have a typo in your answer:"
+                                "  ✅ Almost, compare your answer:"
                             }
+                        } else if is_reordered {
+                            "   ❌ Correct words, different order:"
                         } else {
                             "   ❌ Correct solution:"
                         }) // Nicely done. Meaning: // Excellent! // Nicely done. // Good job!
@@ -101,40 +120,103 @@ impl DisplayViewModel for ReviewDisplay {
                     ui.allocate_space(egui::Vec2 { x: 0.0, y: 5.0 });
                 });
 
-                ui.with_layout(egui::Layout::left_to_right(egui::Align::LEFT), |ui| {
+                ui.horizontal_wrapped(|ui| {
                     ui.allocate_space(egui::Vec2 { x: 20.0, y: 0.0 });
-                    for c in differences {
-                        match *c {
-                            Difference::Same(ref z) => {
+                    ui.spacing_mut().item_spacing.x = 0.0;
+                    for (i, token) in differences.iter().enumerate() {
+                        if i > 0 && !(token.is_cjk() && differences[i - 1].is_cjk()) {
+                            ui.label(egui::RichText::new(" ").size(20.0).monospace());
+                        }
+                        match token.class {
+                            TokenClass::Correct => {
                                 ui.label(
-                                    egui::RichText::new(z)
+                                    egui::RichText::new(&token.text)
                                         .color(egui::Color32::GREEN)
                                         .size(20.0)
                                         .monospace(),
                                 );
                             }
-                            Difference::Add(ref z) => {
+                            TokenClass::Misspelt => {
                                 ui.label(
-                                    egui::RichText::new(z)
-                                        .color(egui::Color32::WHITE)
-                                        .background_color(egui::Color32::RED)
+                                    egui::RichText::new(&token.text)
+                                        .color(egui::Color32::BLACK)
+                                        .background_color(egui::Color32::YELLOW)
                                         .size(20.0)
                                         .monospace(),
-                                );
+                                )
+                                .on_hover_text(format!(
+                                    "You typed: {}",
+                                    token.given.as_deref().unwrap_or("")
+                                ));
                             }
-                            Difference::Rem(ref z) => {
+                            TokenClass::Missing => {
                                 ui.label(
-                                    egui::RichText::new(z)
+                                    egui::RichText::new(&token.text)
                                         .color(egui::Color32::WHITE)
                                         .background_color(egui::Color32::RED)
                                         .size(20.0)
                                         .monospace(),
-                                );
+                                )
+                                .on_hover_text("Missing");
+                            }
+                            TokenClass::Extra => {
+                                ui.label(
+                                    egui::RichText::new(&token.text)
+                                        .color(egui::Color32::GRAY)
+                                        .strikethrough()
+                                        .size(20.0)
+                                        .monospace(),
+                                )
+                                .on_hover_text("Not expected");
+                            }
+                            TokenClass::Misordered => {
+                                ui.label(
+                                    egui::RichText::new(&token.text)
+                                        .color(egui::Color32::BLACK)
+                                        .background_color(egui::Color32::LIGHT_BLUE)
+                                        .size(20.0)
+                                        .monospace(),
+                                )
+                                .on_hover_text("Correct word in the wrong place");
                             }
                         }
                     }
                 });
 
+                if !alternatives.is_empty() {
+                    ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+                        ui.allocate_space(egui::Vec2 { x: 0.0, y: 5.0 });
+                        ui.add(
+                            egui::Label::new(
+                                egui::RichText::new(format!(
+                                    "  Also accepted: {}",
+                                    alternatives.join(" · ")
+                                ))
+                                .color(egui::Color32::GRAY)
+                                .monospace()
+                                .size(12.0),
+                            )
+                            .wrap(true),
+                        );
+                    });
+                }
+
+                if let Some(suspended) = leech {
+                    ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+                        ui.allocate_space(egui::Vec2 { x: 0.0, y: 5.0 });
+                        ui.label(
+                            egui::RichText::new(if suspended {
+                                "  🐛 You keep forgetting this card, it has been suspended."
+                            } else {
+                                "  🐛 You keep forgetting this card, it has been tagged as a leech."
+                            })
+                            .color(egui::Color32::YELLOW)
+                            .monospace()
+                            .size(12.0),
+                        );
+                    });
+                }
+
                 ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
                     /*
                     ui.label(
@@ -170,6 +252,25 @@ impl DisplayViewModel for ReviewDisplay {
                             .controller_requests
                             .insert(ControllerRequest::CloseReview);
                     }
+
+                    ui.horizontal(|ui| {
+                        ui.allocate_space(egui::Vec2 { x: 20.0, y: 0.0 });
+                        if ui
+                            .small_button(egui::RichText::new("Suspend").monospace())
+                            .on_hover_text("Never show this card again until it is restored in Settings → Cards.")
+                            .clicked()
+                        {
+                            inner
+                                .controller_requests
+                                .insert(ControllerRequest::SuspendCard);
+                        }
+                        if ui
+                            .small_button(egui::RichText::new("Bury until tomorrow").monospace())
+                            .clicked()
+                        {
+                            inner.controller_requests.insert(ControllerRequest::BuryCard);
+                        }
+                    });
                 });
             }
         }
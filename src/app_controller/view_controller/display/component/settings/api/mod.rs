@@ -27,7 +27,7 @@ impl WindowViewModel for APISettingsDisplay {
         let available_rect = ctx.available_rect();
         egui::Window::new("API Settings")
             .fixed_rect(egui::Rect::from_min_size(
-                [available_rect.min.x + 5.0, available_rect.min.y + 140.0].into(),
+                [available_rect.min.x + 5.0, available_rect.min.y + 200.0].into(),
                 [available_rect.max.x - 20.0, available_rect.max.y].into(),
             ))
             .resizable(false)
@@ -54,6 +54,20 @@ impl DisplayViewModel for APISettingsDisplay {
             ui.with_layout(
                 egui::Layout::top_down(egui::Align::LEFT).with_cross_justify(true),
                 |ui| {
+                    if let Some(PropertieValue::DeckList(ref decks)) =
+                        inner.properties.get(&PropertieKey::Decks)
+                    {
+                        if let Some(deck) = decks.iter().find(|deck| deck.active) {
+                            ui.label(
+                                egui::RichText::new(format!("Server of the deck \"{}\"", deck.display_name))
+                                    .strong()
+                                    .size(16.0),
+                            )
+                            .on_hover_text("Choose the deck in the Decks settings.");
+                            ui.separator();
+                        }
+                    }
+
                     if let Some(PropertieValue::Bool(ref mut show_fetch_new_card_button)) = inner
                         .properties
                         .get_mut(&PropertieKey::ConnectToCustomServer)
@@ -170,16 +184,8 @@ impl DisplayViewModel for APISettingsDisplay {
                         {
                             ui.checkbox(
                                 featch_new_card_at_threshold,
-                                egui::RichText::new("Auto fetch next card").size(16.0),
-                            );
-                        }
-
-                        if let Some(PropertieValue::Float(ref mut add_new_card_threshold)) =
-                            inner.properties.get_mut(&PropertieKey::AddNewCardThreshold)
-                        {
-                            ui.add(
-                                egui::Slider::new(add_new_card_threshold, 0.0..=100.0)
-                                    .text(egui::RichText::new("at progress pct.").size(16.0)),
+                                egui::RichText::new("Auto fetch new cards when no card is due")
+                                    .size(16.0),
                             );
                         }
                     },
@@ -0,0 +1,88 @@
+use crate::app_controller::view_controller::display::DisplayViewModel;
+use crate::app_controller::view_controller::display::WindowViewModel;
+use crate::app_controller::view_controller::view_model_controller::view_model::DisplayKind;
+use crate::app_controller::view_controller::view_model_controller::view_model::{
+    ControllerRequest, PropertieKey, PropertieValue,
+};
+use crate::app_controller::ViewModel;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct CardsSettingsDisplay {}
+
+impl Default for CardsSettingsDisplay {
+    fn default() -> Self {
+        Self {}
+    }
+}
+
+impl WindowViewModel for CardsSettingsDisplay {
+    fn show(&mut self, ctx: &egui::Context, view_model: &ViewModel) {
+        if let Ok(mut inner) = view_model.inner.lock() {
+            if !(DisplayKind::CardsSettingsDisplay == inner.display_kind) {
+                return;
+            }
+        }
+        let available_rect = ctx.available_rect();
+        egui::Window::new("Cards Settings")
+            .fixed_rect(egui::Rect::from_min_size(
+                [available_rect.min.x + 5.0, available_rect.min.y + 200.0].into(),
+                [available_rect.max.x - 20.0, available_rect.max.y].into(),
+            ))
+            .resizable(false)
+            .title_bar(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                self.ui(ui, view_model);
+            });
+    }
+}
+
+impl DisplayViewModel for CardsSettingsDisplay {
+    fn ui(&mut self, ui: &mut egui::Ui, view_model: &ViewModel) {
+        if let Ok(mut inner) = view_model.inner.lock() {
+            ui.with_layout(
+                egui::Layout::top_down(egui::Align::LEFT).with_cross_justify(true),
+                |ui| {
+                    if let Some(PropertieValue::String(ref deck_summary)) =
+                        inner.properties.get(&PropertieKey::DeckSummary)
+                    {
+                        ui.label(egui::RichText::new(deck_summary).size(16.0));
+                        ui.separator();
+                    }
+
+                    ui.label(egui::RichText::new("Suspended cards:").strong().size(16.0));
+                    ui.separator();
+
+                    let mut restore_card = None;
+
+                    match inner.properties.get(&PropertieKey::SuspendedCards) {
+                        Some(PropertieValue::CardList(ref cards)) if !cards.is_empty() => {
+                            egui::ScrollArea::vertical().show(ui, |ui| {
+                                for (key, label) in cards {
+                                    ui.horizontal(|ui| {
+                                        if ui
+                                            .button(egui::RichText::new("Restore").size(16.0))
+                                            .clicked()
+                                        {
+                                            restore_card = Some(key.clone());
+                                        }
+                                        ui.label(egui::RichText::new(label).size(16.0));
+                                    });
+                                }
+                            });
+                        }
+                        _ => {
+                            ui.label(egui::RichText::new("No suspended cards.").size(16.0));
+                        }
+                    }
+
+                    if let Some(key) = restore_card {
+                        inner
+                            .controller_requests
+                            .insert(ControllerRequest::RestoreCard(key));
+                    }
+                },
+            );
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use crate::app_controller::view_controller::display::DisplayViewModel;
+use crate::app_controller::view_controller::display::WindowViewModel;
+use crate::app_controller::view_controller::view_model_controller::view_model::DisplayKind;
+use crate::app_controller::view_controller::view_model_controller::view_model::{
+    ControllerRequest, InnerViewModel, PropertieKey, PropertieValue, VolatilePropertieKey,
+    VolatilePropertieValue,
+};
+use crate::app_controller::ViewModel;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct DecksSettingsDisplay {}
+
+impl Default for DecksSettingsDisplay {
+    fn default() -> Self {
+        Self {}
+    }
+}
+
+impl WindowViewModel for DecksSettingsDisplay {
+    fn show(&mut self, ctx: &egui::Context, view_model: &ViewModel) {
+        if let Ok(mut inner) = view_model.inner.lock() {
+            if !(DisplayKind::DecksSettingsDisplay == inner.display_kind) {
+                return;
+            }
+        }
+        let available_rect = ctx.available_rect();
+        egui::Window::new("Decks Settings")
+            .fixed_rect(egui::Rect::from_min_size(
+                [available_rect.min.x + 5.0, available_rect.min.y + 200.0].into(),
+                [available_rect.max.x - 20.0, available_rect.max.y].into(),
+            ))
+            .resizable(false)
+            .title_bar(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                self.ui(ui, view_model);
+            });
+    }
+}
+
+impl DisplayViewModel for DecksSettingsDisplay {
+    fn ui(&mut self, ui: &mut egui::Ui, view_model: &ViewModel) {
+        if let Ok(mut inner) = view_model.inner.lock() {
+            let mut requests = Vec::new();
+            let mut edit_deck = false;
+            let mut remove_requested = match inner
+                .volatile_properties
+                .get(&VolatilePropertieKey::RemoveDeckRequested)
+            {
+                Some(VolatilePropertieValue::DeckKey(deck_key)) => Some(*deck_key),
+                _ => None,
+            };
+
+            ui.with_layout(
+                egui::Layout::top_down(egui::Align::LEFT).with_cross_justify(true),
+                |ui| {
+                    ui.label(
+                        egui::RichText::new("Cards of all checked decks are studied together.")
+                            .size(16.0),
+                    );
+                    ui.separator();
+
+                    if let Some(PropertieValue::DeckList(ref mut decks)) =
+                        inner.properties.get_mut(&PropertieKey::Decks)
+                    {
+                        let removable = decks.len() > 1;
+                        let mut changed = false;
+
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            for deck in decks.iter_mut() {
+                                ui.horizontal(|ui| {
+                                    changed |= ui.checkbox(&mut deck.selected, "").changed();
+                                    changed |= ui
+                                        .add(
+                                            egui::TextEdit::singleline(&mut deck.name)
+                                                .hint_text(egui::RichText::new("Deck name")),
+                                        )
+                                        .changed();
+                                    if ui
+                                        .add(egui::Button::new(
+                                            egui::RichText::new("Server").size(16.0).color(
+                                                if deck.active {
+                                                    egui::Color32::WHITE
+                                                } else {
+                                                    egui::Color32::GRAY
+                                                },
+                                            ),
+                                        ))
+                                        .on_hover_text("Edit the server of this deck in the API settings.")
+                                        .clicked()
+                                    {
+                                        requests.push(ControllerRequest::EditDeck(deck.key));
+                                        edit_deck = true;
+                                    }
+                                    // removing can not be undone, it takes a second click to confirm.
+                                    if removable && remove_requested == Some(deck.key) {
+                                        ui.label(
+                                            egui::RichText::new("Remove with all cards and their review history?")
+                                                .size(16.0)
+                                                .color(egui::Color32::YELLOW),
+                                        );
+                                        if ui.button(egui::RichText::new("Yes").size(16.0)).clicked() {
+                                            requests.push(ControllerRequest::RemoveDeck(deck.key));
+                                            remove_requested = None;
+                                        }
+                                        if ui.button(egui::RichText::new("No").size(16.0)).clicked() {
+                                            remove_requested = None;
+                                        }
+                                    } else if removable
+                                        && ui
+                                            .button(egui::RichText::new("Remove").size(16.0))
+                                            .on_hover_text("Removes the deck with all its cards and their review history.")
+                                            .clicked()
+                                    {
+                                        remove_requested = Some(deck.key);
+                                    }
+                                });
+                                ui.label(egui::RichText::new(&deck.summary).size(14.0));
+
+                                let mut own_limits = deck.new_cards_per_day.is_some()
+                                    || deck.maximum_reviews_per_day.is_some();
+                                if ui
+                                    .checkbox(
+                                        &mut own_limits,
+                                        egui::RichText::new("Own daily limits").size(16.0),
+                                    )
+                                    .changed()
+                                {
+                                    changed = true;
+                                    if own_limits {
+                                        deck.new_cards_per_day = Some(10);
+                                        deck.maximum_reviews_per_day = Some(200);
+                                    } else {
+                                        deck.new_cards_per_day = None;
+                                        deck.maximum_reviews_per_day = None;
+                                    }
+                                }
+                                if let Some(ref mut new_cards_per_day) = deck.new_cards_per_day {
+                                    changed |= ui
+                                        .add(egui::Slider::new(new_cards_per_day, 0..=100).text(
+                                            egui::RichText::new("New cards per day").size(16.0),
+                                        ))
+                                        .changed();
+                                }
+                                if let Some(ref mut maximum_reviews_per_day) =
+                                    deck.maximum_reviews_per_day
+                                {
+                                    changed |= ui
+                                        .add(
+                                            egui::Slider::new(maximum_reviews_per_day, 0..=1000)
+                                                .text(
+                                                    egui::RichText::new("Maximum reviews per day")
+                                                        .size(16.0),
+                                                ),
+                                        )
+                                        .changed();
+                                }
+                                ui.separator();
+                            }
+                        });
+
+                        if changed {
+                            requests.push(ControllerRequest::UpdateDecks);
+                        }
+                    }
+
+                    if ui
+                        .button(egui::RichText::new("➕ Add deck").size(16.0))
+                        .clicked()
+                    {
+                        requests.push(ControllerRequest::AddDeck);
+                        edit_deck = true;
+                    }
+                    ui.separator();
+
+                    if let Some(PropertieValue::AnkiFieldMapping(ref mut mapping)) =
+                        inner.properties.get_mut(&PropertieKey::AnkiFieldMapping)
+                    {
+                        ui.label(egui::RichText::new("Anki note fields (1 is the first field):").size(16.0));
+                        ui.horizontal(|ui| {
+                            let mut context_field = mapping.context_field + 1;
+                            ui.add(egui::DragValue::new(&mut context_field).clamp_range(1..=20));
+                            ui.label(egui::RichText::new("Front").size(16.0));
+                            mapping.context_field = context_field - 1;
+
+                            let mut label_field = mapping.label_field + 1;
+                            ui.add(egui::DragValue::new(&mut label_field).clamp_range(1..=20));
+                            ui.label(egui::RichText::new("Back (the answer)").size(16.0));
+                            mapping.label_field = label_field - 1;
+
+                            let mut question_field = mapping.question_field.map(|field| field + 1).unwrap_or(0);
+                            ui.add(egui::DragValue::new(&mut question_field).clamp_range(0..=20));
+                            ui.label(egui::RichText::new("Question (0 for none)").size(16.0));
+                            mapping.question_field = question_field.checked_sub(1);
+                        });
+                    }
+                    if ui
+                        .button(egui::RichText::new("📂 Import Anki deck (.apkg)").size(16.0))
+                        .on_hover_text("Adds the notes, media and review history of the Anki deck as a new deck, it works offline.")
+                        .clicked()
+                    {
+                        requests.push(ControllerRequest::ImportAnkiDeck(true));
+                    }
+                    ui.separator();
+
+                    if ui
+                        .button(egui::RichText::new("📂 Import CSV/TSV file").size(16.0))
+                        .on_hover_text("Every row becomes a card, its columns are mapped to the fields of the card before the import.")
+                        .clicked()
+                    {
+                        requests.push(ControllerRequest::PickCsvFile(true));
+                    }
+                    csv_import_ui(ui, &mut inner, &mut requests);
+                },
+            );
+
+            match remove_requested {
+                Some(deck_key) => inner.volatile_properties.insert(
+                    VolatilePropertieKey::RemoveDeckRequested,
+                    VolatilePropertieValue::DeckKey(deck_key),
+                ),
+                None => inner
+                    .volatile_properties
+                    .remove(&VolatilePropertieKey::RemoveDeckRequested),
+            };
+
+            // the server of the deck is set in the API settings.
+            if edit_deck {
+                inner.display_kind = DisplayKind::APISettingsDisplay;
+            }
+            inner.controller_requests.extend(requests);
+        }
+    }
+}
+
+/// Column mapping and first rows of the picked CSV or TSV file, see ControllerRequest::PickCsvFile.
+fn csv_import_ui(ui: &mut egui::Ui, inner: &mut InnerViewModel, requests: &mut Vec<ControllerRequest>) {
+    let preview = match inner.volatile_properties.get(&VolatilePropertieKey::CsvPreview) {
+        Some(VolatilePropertieValue::CsvPreview(preview)) => preview,
+        _ => return,
+    };
+    let mapping = match inner.properties.get_mut(&PropertieKey::CsvColumnMapping) {
+        Some(PropertieValue::CsvColumnMapping(mapping)) => mapping,
+        _ => return,
+    };
+
+    ui.label(
+        egui::RichText::new(format!(
+            "{}: {} rows",
+            preview.file_name,
+            preview.row_count.saturating_sub(mapping.has_header as usize)
+        ))
+        .size(16.0),
+    );
+    ui.checkbox(
+        &mut mapping.has_header,
+        egui::RichText::new("The first row names the columns").size(16.0),
+    );
+
+    let column_names: Vec<String> = (0..preview.column_count)
+        .map(|column| match preview.rows.first().and_then(|row| row.cells.get(column)) {
+            Some(name) if mapping.has_header && !name.trim().is_empty() => name.trim().to_string(),
+            _ => format!("Column {}", column + 1),
+        })
+        .collect();
+
+    egui::Grid::new("csv_column_mapping").show(ui, |ui| {
+        let mut context_column = Some(mapping.context_column);
+        column_combo_box(ui, "Context", &column_names, &mut context_column, false);
+        mapping.context_column = context_column.unwrap_or_default();
+
+        let mut label_column = Some(mapping.label_column);
+        column_combo_box(ui, "Label (the answer)", &column_names, &mut label_column, false);
+        mapping.label_column = label_column.unwrap_or_default();
+
+        column_combo_box(ui, "Question", &column_names, &mut mapping.question_column, true);
+        column_combo_box(ui, "Placeholder", &column_names, &mut mapping.placeholder_column, true);
+        column_combo_box(ui, "Audio path", &column_names, &mut mapping.audio_column, true);
+        column_combo_box(ui, "Image paths", &column_names, &mut mapping.image_column, true);
+        column_combo_box(ui, "Tags", &column_names, &mut mapping.tags_column, true);
+    });
+    // a CSV file carries no media, the paths are only loaded from a server hosting the saved card files.
+    if mapping.audio_column.is_some() || mapping.image_column.is_some() {
+        ui.label(
+            egui::RichText::new("Audio and images are left out of the created cards, they need the saved card files on a server next to the media.")
+                .size(14.0)
+                .color(egui::Color32::YELLOW),
+        );
+    }
+
+    egui::ScrollArea::horizontal()
+        .id_source("csv_preview")
+        .show(ui, |ui| {
+            egui::Grid::new("csv_preview").striped(true).show(ui, |ui| {
+                ui.label(egui::RichText::new("Line").strong());
+                for name in &column_names {
+                    ui.label(egui::RichText::new(name).strong());
+                }
+                ui.end_row();
+                for row in preview.rows.iter().skip(mapping.has_header as usize) {
+                    ui.label(row.line.to_string());
+                    for cell in &row.cells {
+                        ui.label(shorten(cell));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    ui.label(
+        egui::RichText::new("Several labels or image paths in a cell are separated by \"|\", rows that cannot be imported are listed by line afterwards.")
+            .size(14.0),
+    );
+
+    ui.horizontal(|ui| {
+        if ui
+            .button(egui::RichText::new("Create cards").size(16.0))
+            .on_hover_text("Adds the rows as a new deck, it works offline without audio and images.")
+            .clicked()
+        {
+            requests.push(ControllerRequest::ImportCsvDeck);
+        }
+        if ui
+            .button(egui::RichText::new("Save card files (.zip)").size(16.0))
+            .on_hover_text("A folder of card_0.json, card_1.json, ... and a deck.json to place in file_server/static.")
+            .clicked()
+        {
+            requests.push(ControllerRequest::ExportCsvCardFiles);
+        }
+        if ui.button(egui::RichText::new("Cancel").size(16.0)).clicked() {
+            requests.push(ControllerRequest::CloseCsvImport);
+        }
+    });
+}
+
+/// A row of the column mapping, only optional fields can be left without column.
+fn column_combo_box(
+    ui: &mut egui::Ui,
+    label: &str,
+    column_names: &[String],
+    column: &mut Option<usize>,
+    optional: bool,
+) {
+    ui.label(egui::RichText::new(label).size(16.0));
+    let selected_text = column
+        .and_then(|column| column_names.get(column))
+        .cloned()
+        .unwrap_or_else(|| "None".to_string());
+    egui::ComboBox::from_id_source(label)
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            if optional {
+                ui.selectable_value(column, None, "None");
+            }
+            for (index, name) in column_names.iter().enumerate() {
+                ui.selectable_value(column, Some(index), name.as_str());
+            }
+        });
+    ui.end_row();
+}
+
+/// The start of a cell on a single line, long cells would stretch the preview.
+fn shorten(cell: &str) -> String {
+    let line = cell.replace('\n', " ");
+    if line.chars().count() > 30 {
+        format!("{}…", line.chars().take(30).collect::<String>())
+    } else {
+        line
+    }
+}
@@ -1,8 +1,11 @@
 use crate::app_controller::view_controller::display::DisplayViewModel;
 use crate::app_controller::view_controller::display::WindowViewModel;
+use crate::app_controller::view_controller::view_model_controller::view_model::ControllerRequest;
 use crate::app_controller::view_controller::view_model_controller::view_model::DisplayKind;
 use crate::app_controller::ViewModel;
 pub mod api;
+pub mod cards;
+pub mod decks;
 pub mod options;
 pub mod save_load;
 
@@ -20,7 +23,9 @@ impl WindowViewModel for SettingsDisplay {
         if let Ok(mut inner) = view_model.inner.lock() {
             if !(DisplayKind::APISettingsDisplay == inner.display_kind
                 || DisplayKind::SaveLoadSettingsDisplay == inner.display_kind
-                || DisplayKind::OptionsSettingsDisplay == inner.display_kind)
+                || DisplayKind::OptionsSettingsDisplay == inner.display_kind
+                || DisplayKind::CardsSettingsDisplay == inner.display_kind
+                || DisplayKind::DecksSettingsDisplay == inner.display_kind)
             {
                 return;
             }
@@ -74,6 +79,40 @@ impl DisplayViewModel for SettingsDisplay {
                     {
                         inner.display_kind = DisplayKind::OptionsSettingsDisplay;
                     }
+                    if ui
+                        .add(egui::Button::new(
+                            egui::RichText::new("Decks").size(16.0).color(
+                                if inner.display_kind == DisplayKind::DecksSettingsDisplay {
+                                    egui::Color32::WHITE
+                                } else {
+                                    egui::Color32::GRAY
+                                },
+                            ),
+                        ))
+                        .clicked()
+                    {
+                        inner.display_kind = DisplayKind::DecksSettingsDisplay;
+                        inner
+                            .controller_requests
+                            .insert(ControllerRequest::RefreshDecks);
+                    }
+                    if ui
+                        .add(egui::Button::new(
+                            egui::RichText::new("Cards").size(16.0).color(
+                                if inner.display_kind == DisplayKind::CardsSettingsDisplay {
+                                    egui::Color32::WHITE
+                                } else {
+                                    egui::Color32::GRAY
+                                },
+                            ),
+                        ))
+                        .clicked()
+                    {
+                        inner.display_kind = DisplayKind::CardsSettingsDisplay;
+                        inner
+                            .controller_requests
+                            .insert(ControllerRequest::RefreshSuspendedCards);
+                    }
                     if ui
                         .add(egui::Button::new(
                             egui::RichText::new("Save/Load").size(16.0).color(
@@ -6,6 +6,17 @@ use crate::app_controller::view_controller::view_model_controller::view_model::{
 };
 use crate::app_controller::ViewModel;
 
+use crate::app_controller::model_controller::data_model::normalization::NormalizationStep;
+use crate::app_controller::model_controller::data_model::spaced_repetition::SchedulerKind;
+
+fn format_parameters(parameters: &[f64]) -> String {
+    parameters
+        .iter()
+        .map(|w| format!("{:.2}", w))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct OptionsSettingsDisplay {}
 
@@ -25,7 +36,7 @@ impl WindowViewModel for OptionsSettingsDisplay {
         let available_rect = ctx.available_rect();
         egui::Window::new("Options Settings")
             .fixed_rect(egui::Rect::from_min_size(
-                [available_rect.min.x + 5.0, available_rect.min.y + 140.0].into(),
+                [available_rect.min.x + 5.0, available_rect.min.y + 200.0].into(),
                 [available_rect.max.x - 20.0, available_rect.max.y].into(),
             ))
             .resizable(false)
@@ -43,45 +54,357 @@ impl DisplayViewModel for OptionsSettingsDisplay {
             ui.with_layout(
                 egui::Layout::top_down(egui::Align::LEFT).with_cross_justify(true),
                 |ui| {
-                    if let Some(PropertieValue::Usize(ref mut spelling_correction_threshold)) =
-                        inner
-                            .properties
-                            .get_mut(&PropertieKey::SpellingCorrectionThreshold)
+                    if let Some(PropertieValue::TypoTolerance(ref mut typo_tolerance)) =
+                        inner.properties.get_mut(&PropertieKey::TypoTolerance)
                     {
                         ui.add(
-                            egui::Slider::new(spelling_correction_threshold, 0..=10).text(
-                                egui::RichText::new("Permitted spelling mistakes").size(16.0),
-                            ),
+                            egui::Slider::new(&mut typo_tolerance.percentage, 0.0..=50.0)
+                                .suffix("%")
+                                .step_by(1.0)
+                                .text(
+                                    egui::RichText::new("Permitted spelling mistakes").size(16.0),
+                                ),
+                        )
+                        .on_hover_text("In percent of the characters of the answer. Typing a neighbouring key, swapping two letters or missing an accent counts as less than a mistake.");
+                        ui.checkbox(
+                            &mut typo_tolerance.per_word,
+                            egui::RichText::new("Permitted spelling mistakes per word").size(16.0),
                         );
                     }
 
-                    if let Some(PropertieValue::Bool(ref mut ignore_sentence_punctuation_symbols)) =
-                        inner
-                            .properties
-                            .get_mut(&PropertieKey::IgnoreSentencePunctuationSymbols)
+                    if let Some(PropertieValue::Bool(ref mut self_graded)) =
+                        inner.properties.get_mut(&PropertieKey::SelfGraded)
                     {
                         ui.checkbox(
-                            ignore_sentence_punctuation_symbols,
-                            egui::RichText::new("Ignore sentence punctuation symbols").size(16.0),
+                            self_graded,
+                            egui::RichText::new("Self-graded flashcards (no typing)").size(16.0),
+                        )
+                        .on_hover_text("Show the answer, then grade yourself with Again, Hard, Good or Easy. A deck may choose this mode for you.");
+                    }
+
+                    if let Some(PropertieValue::Bool(ref mut word_order_tolerant)) =
+                        inner.properties.get_mut(&PropertieKey::WordOrderTolerant)
+                    {
+                        ui.checkbox(
+                            word_order_tolerant,
+                            egui::RichText::new("Accept the right words in a different order")
+                                .size(16.0),
+                        )
+                        .on_hover_text("Every word out of place counts as half a spelling mistake.");
+                    }
+
+                    if let Some(PropertieValue::Bool(ref mut show_special_characters)) =
+                        inner.properties.get_mut(&PropertieKey::ShowSpecialCharacters)
+                    {
+                        ui.checkbox(
+                            show_special_characters,
+                            egui::RichText::new("Show special character buttons").size(16.0),
                         );
                     }
 
-                    if let Some(PropertieValue::Bool(ref mut match_ascii)) =
-                        inner.properties.get_mut(&PropertieKey::MatchASCII)
+                    if let Some(PropertieValue::Bool(ref mut deck_match_overrides)) =
+                        inner.properties.get_mut(&PropertieKey::DeckMatchOverrides)
+                    {
+                        ui.checkbox(
+                            deck_match_overrides,
+                            egui::RichText::new("Let decks and cards override my matching options")
+                                .size(16.0),
+                        )
+                        .on_hover_text("Uses the \"match\" settings of deck.json and of the card files.");
+                    }
+
+                    let deck_normalization = matches!(
+                        inner.properties.get(&PropertieKey::DeckNormalization),
+                        Some(PropertieValue::NormalizationPipeline(_))
+                    ) && matches!(
+                        inner.properties.get(&PropertieKey::DeckMatchOverrides),
+                        Some(PropertieValue::Bool(true))
+                    );
+
+                    if let Some(PropertieValue::NormalizationPipeline(ref mut normalization)) =
+                        inner.properties.get_mut(&PropertieKey::Normalization)
                     {
-                        ui.checkbox(match_ascii, egui::RichText::new("Match ASCII").size(16.0));
+                        ui.label(egui::RichText::new("Answer normalisation").size(16.0));
+                        if deck_normalization {
+                            ui.label(
+                                egui::RichText::new(
+                                    "Decks defining their own normalisation (deck.json) use it instead.",
+                                )
+                                .color(egui::Color32::YELLOW),
+                            );
+                        }
+
+                        let mut move_up = None;
+                        let mut move_down = None;
+                        let mut remove = None;
+                        let count = normalization.steps.len();
+                        for (i, step) in normalization.steps.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("{}. {}", i + 1, step.name()));
+                                if ui.add_enabled(i > 0, egui::Button::new("⬆")).clicked() {
+                                    move_up = Some(i);
+                                }
+                                if ui.add_enabled(i + 1 < count, egui::Button::new("⬇")).clicked() {
+                                    move_down = Some(i);
+                                }
+                                if ui.button("🗑").clicked() {
+                                    remove = Some(i);
+                                }
+                            });
+                            if let NormalizationStep::Replace {
+                                ref mut pattern,
+                                ref mut replacement,
+                            } = step
+                            {
+                                ui.horizontal(|ui| {
+                                    ui.add(
+                                        egui::TextEdit::singleline(pattern)
+                                            .hint_text("pattern (regex)")
+                                            .desired_width(120.0),
+                                    );
+                                    ui.label("→");
+                                    ui.add(
+                                        egui::TextEdit::singleline(replacement)
+                                            .hint_text("replacement")
+                                            .desired_width(120.0),
+                                    );
+                                });
+                            }
+                            if let Err(err) = step.compile() {
+                                ui.label(egui::RichText::new(err).color(egui::Color32::RED));
+                            }
+                        }
+                        if let Some(i) = move_up {
+                            normalization.steps.swap(i - 1, i);
+                        }
+                        if let Some(i) = move_down {
+                            normalization.steps.swap(i, i + 1);
+                        }
+                        if let Some(i) = remove {
+                            normalization.steps.remove(i);
+                        }
+
+                        ui.menu_button("➕ Add step", |ui| {
+                            for step in NormalizationStep::ALL {
+                                if ui.button(step.name()).clicked() {
+                                    normalization.steps.push(step);
+                                    ui.close_menu();
+                                }
+                            }
+                            let replace = NormalizationStep::Replace {
+                                pattern: String::new(),
+                                replacement: String::new(),
+                            };
+                            if ui.button(replace.name()).clicked() {
+                                normalization.steps.push(replace);
+                                ui.close_menu();
+                            }
+                        });
                     }
+                    ui.separator();
 
-                    if let Some(PropertieValue::Bool(ref mut match_case)) =
-                        inner.properties.get_mut(&PropertieKey::MatchCase)
+                    if let Some(PropertieValue::SchedulerKind(ref mut scheduler_kind)) =
+                        inner.properties.get_mut(&PropertieKey::Scheduler)
+                    {
+                        ui.label(egui::RichText::new("Spaced repetition algorithm").size(16.0));
+                        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
+                            for kind in SchedulerKind::ALL {
+                                ui.radio_value(
+                                    scheduler_kind,
+                                    kind,
+                                    egui::RichText::new(kind.name()).size(16.0),
+                                );
+                            }
+                        });
+                    }
+
+                    if let Some(PropertieValue::SchedulerKind(SchedulerKind::FSRS)) =
+                        inner.properties.get(&PropertieKey::Scheduler)
+                    {
+                        if let Some(PropertieValue::VecFloat(ref fsrs_parameters)) =
+                            inner.properties.get(&PropertieKey::FSRSParameters)
+                        {
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "Parameters: {}",
+                                    format_parameters(fsrs_parameters)
+                                ))
+                                .size(12.0)
+                                .monospace(),
+                            );
+                        }
+
+                        let mut optimization_running = false;
+                        let mut apply_parameters = false;
+
+                        if let Some(PropertieValue::FSRSOptimizerResult(ref result)) =
+                            inner.properties.get(&PropertieKey::FSRSOptimizerResult)
+                        {
+                            if result.progress < 1.0 {
+                                optimization_running = true;
+                                ui.add(
+                                    egui::widgets::ProgressBar::new(result.progress)
+                                        .text("Optimising..."),
+                                );
+                            } else {
+                                ui.label(
+                                    egui::RichText::new(format!(
+                                        "Fitted on {} reviews, log loss {:.4} -> {:.4}",
+                                        result.review_count,
+                                        result.initial_log_loss,
+                                        result.log_loss
+                                    ))
+                                    .size(16.0),
+                                );
+                                ui.label(
+                                    egui::RichText::new(format!(
+                                        "Predicted retention {:.1}% (actual {:.1}%)",
+                                        result.predicted_retention * 100.0,
+                                        result.actual_retention * 100.0
+                                    ))
+                                    .size(16.0),
+                                );
+                                ui.label(
+                                    egui::RichText::new(format!(
+                                        "Fitted: {}",
+                                        format_parameters(&result.parameters)
+                                    ))
+                                    .size(12.0)
+                                    .monospace(),
+                                );
+                                apply_parameters = ui
+                                    .add(egui::Button::new(
+                                        egui::RichText::new("Apply fitted parameters").size(16.0),
+                                    ))
+                                    .clicked();
+                            }
+                        }
+
+                        if apply_parameters {
+                            inner
+                                .controller_requests
+                                .insert(ControllerRequest::ApplyFSRSParameters);
+                        }
+
+                        if !optimization_running
+                            && ui
+                                .add(egui::Button::new(
+                                    egui::RichText::new("Optimise from review history").size(16.0),
+                                ))
+                                .clicked()
+                        {
+                            inner
+                                .controller_requests
+                                .insert(ControllerRequest::OptimizeFSRSParameters(true));
+                        }
+                    }
+                    ui.separator();
+
+                    ui.label(egui::RichText::new("Learning steps (e.g. 1m 10m 1h)").size(16.0));
+
+                    if let Some(PropertieValue::String(ref mut learning_steps)) =
+                        inner.properties.get_mut(&PropertieKey::LearningSteps)
+                    {
+                        ui.add(
+                            egui::TextEdit::singleline(learning_steps)
+                                .hint_text(egui::RichText::new("New cards, e.g. 1m 10m")),
+                        );
+                    }
+
+                    if let Some(PropertieValue::String(ref mut relearning_steps)) =
+                        inner.properties.get_mut(&PropertieKey::RelearningSteps)
+                    {
+                        ui.add(
+                            egui::TextEdit::singleline(relearning_steps)
+                                .hint_text(egui::RichText::new("Forgotten cards, e.g. 10m")),
+                        );
+                    }
+                    ui.separator();
+
+                    ui.label(egui::RichText::new("Daily limits").size(16.0));
+
+                    if let Some(PropertieValue::Usize(ref mut new_cards_per_day)) =
+                        inner.properties.get_mut(&PropertieKey::NewCardsPerDay)
+                    {
+                        ui.add(
+                            egui::Slider::new(new_cards_per_day, 0..=100)
+                                .text(egui::RichText::new("New cards per day (all decks)").size(16.0)),
+                        );
+                    }
+
+                    if let Some(PropertieValue::Usize(ref mut maximum_reviews_per_day)) =
+                        inner.properties.get_mut(&PropertieKey::MaximumReviewsPerDay)
+                    {
+                        ui.add(
+                            egui::Slider::new(maximum_reviews_per_day, 0..=1000)
+                                .text(egui::RichText::new("Maximum reviews per day (all decks)").size(16.0)),
+                        );
+                    }
+
+                    if let Some(PropertieValue::Usize(ref mut day_rollover_hour)) =
+                        inner.properties.get_mut(&PropertieKey::DayRolloverHour)
+                    {
+                        ui.add(
+                            egui::Slider::new(day_rollover_hour, 0..=23)
+                                .text(egui::RichText::new("Next day starts at (hour)").size(16.0)),
+                        );
+                    }
+                    ui.separator();
+
+                    if let Some(PropertieValue::Usize(ref mut leech_threshold)) =
+                        inner.properties.get_mut(&PropertieKey::LeechThreshold)
+                    {
+                        ui.add(
+                            egui::Slider::new(leech_threshold, 0..=20).text(
+                                egui::RichText::new("Lapses before a card is a leech (0 = never)")
+                                    .size(16.0),
+                            ),
+                        );
+                    }
+
+                    if let Some(PropertieValue::Bool(ref mut suspend_leeches)) =
+                        inner.properties.get_mut(&PropertieKey::SuspendLeeches)
                     {
                         ui.checkbox(
-                            match_case,
-                            egui::RichText::new("Match case (Lowercase/Uppercase)").size(16.0),
+                            suspend_leeches,
+                            egui::RichText::new("Suspend leeches").size(16.0),
                         );
                     }
                     ui.separator();
 
+                    if let Some(PropertieValue::Float(ref mut multiple_choice_weight)) =
+                        inner.properties.get_mut(&PropertieKey::MultipleChoiceRecallWeight)
+                    {
+                        ui.add(
+                            egui::Slider::new(multiple_choice_weight, 0.0..=1.0).text(
+                                egui::RichText::new("Weight of a correct multiple-choice answer")
+                                    .size(16.0),
+                            ),
+                        );
+                    }
+
+                    if let Some(PropertieValue::Bool(ref mut add_reverse_cards)) =
+                        inner.properties.get_mut(&PropertieKey::AddReverseCards)
+                    {
+                        ui.checkbox(
+                            add_reverse_cards,
+                            egui::RichText::new("Also add reverse cards (label → context)")
+                                .size(16.0),
+                        )
+                        .on_hover_text("Applies to new cards whose card file does not list its directions.");
+                    }
+
+                    if let Some(PropertieValue::Bool(ref mut add_dictation_cards)) =
+                        inner.properties.get_mut(&PropertieKey::AddDictationCards)
+                    {
+                        ui.checkbox(
+                            add_dictation_cards,
+                            egui::RichText::new("Also add dictation cards for new cards with audio")
+                                .size(16.0),
+                        )
+                        .on_hover_text("Applies to cards added from now on, cards already in the list keep their variants.");
+                    }
+
                     if let Some(PropertieValue::Bool(ref mut auto_play_audio)) =
                         inner.properties.get_mut(&PropertieKey::AutoPlayAudio)
                     {
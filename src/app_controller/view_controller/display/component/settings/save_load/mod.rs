@@ -26,7 +26,7 @@ impl WindowViewModel for SaveLoadSettingsDisplay {
         let available_rect = ctx.available_rect();
         egui::Window::new("Save/Load Settings")
             .fixed_rect(egui::Rect::from_min_size(
-                [available_rect.min.x + 5.0, available_rect.min.y + 140.0].into(),
+                [available_rect.min.x + 5.0, available_rect.min.y + 200.0].into(),
                 [available_rect.max.x - 20.0, available_rect.max.y].into(),
             ))
             .resizable(false)
@@ -10,6 +10,8 @@ use display::component::alert::AlertDisplay;
 use display::component::app::AppDisplay;
 use display::component::review::ReviewDisplay;
 use display::component::settings::api::APISettingsDisplay;
+use display::component::settings::cards::CardsSettingsDisplay;
+use display::component::settings::decks::DecksSettingsDisplay;
 use display::component::settings::options::OptionsSettingsDisplay;
 use display::component::settings::save_load::SaveLoadSettingsDisplay;
 use display::component::settings::SettingsDisplay;
@@ -33,6 +35,8 @@ impl ViewController {
         APISettingsDisplay::default().show(ctx, view_model);
         OptionsSettingsDisplay::default().show(ctx, view_model);
         SaveLoadSettingsDisplay::default().show(ctx, view_model);
+        CardsSettingsDisplay::default().show(ctx, view_model);
+        DecksSettingsDisplay::default().show(ctx, view_model);
         AlertDisplay::default().show(ctx, view_model);
         ReviewDisplay::default().show(ctx, view_model);
 
@@ -1,8 +1,18 @@
 // Everything the UI need to know. No more, no less.
 
+use crate::app_controller::model_controller::data_model::card_model::CardKey;
+use crate::app_controller::model_controller::data_model::deck::DeckSettings;
+use crate::app_controller::model_controller::data_model::import::anki::AnkiFieldMapping;
+use crate::app_controller::model_controller::data_model::import::csv::{CsvColumnMapping, CsvPreview};
 use crate::app_controller::model_controller::data_model::download::DownloadState;
+use crate::app_controller::model_controller::data_model::normalization::NormalizationPipeline;
+use crate::app_controller::model_controller::data_model::typo_tolerance::TypoTolerance;
+use crate::app_controller::model_controller::data_model::spaced_repetition::fsrs;
+use crate::app_controller::model_controller::data_model::spaced_repetition::fsrs::FSRSOptimizerResult;
+use crate::app_controller::model_controller::data_model::spaced_repetition::Grade;
+use crate::app_controller::model_controller::data_model::spaced_repetition::SchedulerKind;
 use crate::app_controller::model_controller::data_model::static_audio::StaticSounds;
-use difference::Difference;
+use crate::app_controller::model_controller::data_model::token_diff::DiffToken;
 use egui_extras::RetainedImage;
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex, RwLock};
@@ -17,6 +27,8 @@ pub enum ControllerRequest {
     UpdateAIRequestConfig,
     TestCustomServerConnection(bool),
     TestAIServerConnection(bool),
+    OptimizeFSRSParameters(bool),
+    ApplyFSRSParameters,
     FetchNewCard,
     CheckReview,
     HideAlert,
@@ -26,9 +38,28 @@ pub enum ControllerRequest {
     LoadAudio,
     CloseReview,
     UpdateCardList,
-    FetchNewCardAtThresholdOrContinue,
+    FetchNewCardOrContinue,
     RefreshCard,
     RefreshRequestConfig,
+    SuspendCard,
+    BuryCard,
+    RestoreCard(CardKey),
+    FetchSynonyms(bool),
+    FetchDeckMetadata(bool),
+    RefreshSuspendedCards,
+    ShowHint,
+    ShowAnswer,
+    GradeReview(Grade),
+    RefreshDecks,
+    AddDeck,
+    EditDeck(u32),
+    RemoveDeck(u32),
+    UpdateDecks,
+    ImportAnkiDeck(bool),
+    PickCsvFile(bool),
+    ImportCsvDeck,
+    ExportCsvCardFiles,
+    CloseCsvImport,
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -37,6 +68,8 @@ pub enum DisplayKind {
     APISettingsDisplay,
     SaveLoadSettingsDisplay,
     OptionsSettingsDisplay,
+    CardsSettingsDisplay,
+    DecksSettingsDisplay,
 }
 
 impl Default for DisplayKind {
@@ -53,11 +86,23 @@ pub enum PropertieKey {
     ConnectToCustomServer,
     FetchDalleGeneratedImages,
     EnableGPT3CardGeneration,
+    NewCardsPerDay,
+    MaximumReviewsPerDay,
+    DayRolloverHour,
+    LearningSteps,
+    RelearningSteps,
+    DoneForToday,
+    // Replaced options, kept so saved states still deserialize and can be migrated:
+    // AddNewCardThreshold by NewCardsPerDay, SpellingCorrectionThreshold by TypoTolerance,
+    // IgnoreSentencePunctuationSymbols, MatchASCII and MatchCase by Normalization.
     AddNewCardThreshold,
     SpellingCorrectionThreshold,
     IgnoreSentencePunctuationSymbols,
     MatchASCII,
     MatchCase,
+    Scheduler,
+    FSRSParameters,
+    FSRSOptimizerResult,
     CustomServerEndpoint,
     CustomServerUsername,
     CustomServerPassword,
@@ -76,6 +121,33 @@ pub enum PropertieKey {
     CardHasAudio,
     Alert,
     ReviewScore,
+    LeechThreshold,
+    SuspendLeeches,
+    ReviewLeech,
+    SuspendedCards,
+    AddReverseCards,
+    CardClozeContext,
+    CardChoices,
+    MultipleChoiceRecallWeight,
+    AddDictationCards,
+    CardIsDictation,
+    ReviewAlternatives,
+    Normalization,
+    DeckNormalization,
+    DeckMatchOverrides,
+    CardHint,
+    CardHintsLeft,
+    SpecialCharacters,
+    ShowSpecialCharacters,
+    TypoTolerance,
+    WordOrderTolerant,
+    SelfGraded,
+    CardSelfGraded,
+    CardAnswer,
+    DeckSummary,
+    Decks,
+    AnkiFieldMapping,
+    CsvColumnMapping,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Clone)]
@@ -85,18 +157,32 @@ pub enum PropertieValue {
     Usize(usize),
     String(String),
     VecString(Vec<String>),
+    VecFloat(Vec<f64>),
     DownloadState(DownloadState),
+    SchedulerKind(SchedulerKind),
+    FSRSOptimizerResult(FSRSOptimizerResult),
+    CardList(Vec<(CardKey, String)>),
+    NormalizationPipeline(NormalizationPipeline),
+    TypoTolerance(TypoTolerance),
+    DeckList(Vec<DeckSettings>),
+    AnkiFieldMapping(AnkiFieldMapping),
+    CsvColumnMapping(CsvColumnMapping),
 }
 
 #[derive(PartialEq, Eq, Hash)]
 pub enum VolatilePropertieKey {
     CardImage,
     Differences,
+    CsvPreview,
+    /// The deck whose "Remove" button was clicked, it is removed once the user confirms.
+    RemoveDeckRequested,
 }
 
 pub enum VolatilePropertieValue {
     Image(Arc<Mutex<RetainedImage>>),
-    Differences(Vec<Difference>),
+    Differences(Vec<DiffToken>),
+    CsvPreview(CsvPreview),
+    DeckKey(u32),
 }
 
 #[derive(serde::Deserialize, serde::Serialize, derivative::Derivative)]
@@ -141,20 +227,57 @@ impl Default for InnerViewModel {
                     PropertieKey::EnableGPT3CardGeneration,
                     PropertieValue::Bool(false),
                 ),
+                (PropertieKey::NewCardsPerDay, PropertieValue::Usize(10)),
                 (
-                    PropertieKey::AddNewCardThreshold,
-                    PropertieValue::Float(66.6),
+                    PropertieKey::MaximumReviewsPerDay,
+                    PropertieValue::Usize(200),
                 ),
+                (PropertieKey::DayRolloverHour, PropertieValue::Usize(4)),
                 (
-                    PropertieKey::SpellingCorrectionThreshold,
-                    PropertieValue::Usize(1),
+                    PropertieKey::LearningSteps,
+                    PropertieValue::String("1m 10m".to_string()),
                 ),
                 (
-                    PropertieKey::IgnoreSentencePunctuationSymbols,
-                    PropertieValue::Bool(true),
+                    PropertieKey::RelearningSteps,
+                    PropertieValue::String("10m".to_string()),
+                ),
+                (PropertieKey::DoneForToday, PropertieValue::Bool(false)),
+                (
+                    PropertieKey::TypoTolerance,
+                    PropertieValue::TypoTolerance(TypoTolerance::default()),
+                ),
+                (
+                    PropertieKey::Normalization,
+                    PropertieValue::NormalizationPipeline(NormalizationPipeline::default()),
+                ),
+                (PropertieKey::WordOrderTolerant, PropertieValue::Bool(false)),
+                (PropertieKey::SelfGraded, PropertieValue::Bool(false)),
+                (
+                    PropertieKey::AnkiFieldMapping,
+                    PropertieValue::AnkiFieldMapping(AnkiFieldMapping::default()),
+                ),
+                (
+                    PropertieKey::CsvColumnMapping,
+                    PropertieValue::CsvColumnMapping(CsvColumnMapping::default()),
+                ),
+                (PropertieKey::DeckMatchOverrides, PropertieValue::Bool(true)),
+                (PropertieKey::ShowSpecialCharacters, PropertieValue::Bool(true)),
+                (PropertieKey::LeechThreshold, PropertieValue::Usize(8)),
+                (PropertieKey::SuspendLeeches, PropertieValue::Bool(true)),
+                (PropertieKey::AddReverseCards, PropertieValue::Bool(false)),
+                (PropertieKey::AddDictationCards, PropertieValue::Bool(false)),
+                (
+                    PropertieKey::MultipleChoiceRecallWeight,
+                    PropertieValue::Float(0.2),
+                ),
+                (
+                    PropertieKey::Scheduler,
+                    PropertieValue::SchedulerKind(SchedulerKind::default()),
+                ),
+                (
+                    PropertieKey::FSRSParameters,
+                    PropertieValue::VecFloat(fsrs::DEFAULT_PARAMETERS.to_vec()),
                 ),
-                (PropertieKey::MatchASCII, PropertieValue::Bool(false)),
-                (PropertieKey::MatchCase, PropertieValue::Bool(false)),
                 (
                     PropertieKey::CustomServerEndpoint,
                     PropertieValue::String("".to_string()),
@@ -275,6 +398,44 @@ impl ViewModel {
         }
     }
 
+    /// Adds default values for properties missing in a previously saved state (e.g. settings added in a newer version).
+    pub fn restore_default_properties(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            if !inner.properties.contains_key(&PropertieKey::Normalization) {
+                let legacy_option = |key: &PropertieKey, default: bool| match inner.properties.get(key) {
+                    Some(PropertieValue::Bool(val)) => *val,
+                    _ => default,
+                };
+                let normalization = NormalizationPipeline::from_options(
+                    legacy_option(&PropertieKey::MatchASCII, false),
+                    legacy_option(&PropertieKey::MatchCase, false),
+                    legacy_option(&PropertieKey::IgnoreSentencePunctuationSymbols, true),
+                );
+                inner.properties.insert(
+                    PropertieKey::Normalization,
+                    PropertieValue::NormalizationPipeline(normalization),
+                );
+            }
+            if !inner.properties.contains_key(&PropertieKey::TypoTolerance) {
+                if let Some(PropertieValue::Usize(threshold)) =
+                    inner.properties.get(&PropertieKey::SpellingCorrectionThreshold)
+                {
+                    let typo_tolerance = TypoTolerance {
+                        percentage: TypoTolerance::threshold_percentage(*threshold, None),
+                        ..TypoTolerance::default()
+                    };
+                    inner.properties.insert(
+                        PropertieKey::TypoTolerance,
+                        PropertieValue::TypoTolerance(typo_tolerance),
+                    );
+                }
+            }
+            for (key, value) in InnerViewModel::default().properties {
+                inner.properties.entry(key).or_insert(value);
+            }
+        }
+    }
+
     /*
     pub fn update_property_bool<F>(&self, key: &PropertieKey, mut f: F)  where
          F: FnMut(&mut bool) {